@@ -0,0 +1,286 @@
+use crate::object::{Movement, AIR, DIRT, GRASS};
+use crate::physics::movement_at;
+use crate::rng::Rng;
+use crate::world::World;
+use std::cmp::Ordering;
+use std::collections::{BinaryHeap, HashMap, VecDeque};
+
+/// Position of a block in world coordinates
+pub type BlockPos = (i32, i32, i32);
+
+/// Why a block is updated
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum BlockUpdate {
+    /// The block was just placed
+    Placed,
+    /// The block next to it at this position changed
+    Neighbour(BlockPos),
+    /// A tick scheduled with [`World::schedule_tick`] is due
+    Scheduled,
+    /// The block was picked for a random tick
+    Random,
+}
+
+/// Reacts to an update of the block at the position. Gets a random number generator that is
+/// seeded from the world seed, the position and the tick, so updates are deterministic.
+pub type UpdateFn = fn(&mut World, BlockPos, BlockUpdate, &mut Rng);
+
+const NEIGHBOURS: [BlockPos; 6] = [
+    (1, 0, 0),
+    (-1, 0, 0),
+    (0, 1, 0),
+    (0, -1, 0),
+    (0, 0, 1),
+    (0, 0, -1),
+];
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+struct Scheduled {
+    due: u64,
+    priority: i32,
+    /// Order in which ticks were scheduled, so ticks of the same priority run in that order
+    order: u64,
+    position: BlockPos,
+}
+
+impl Ord for Scheduled {
+    /// Orders the heap so it returns the earliest tick first, then the highest priority
+    fn cmp(&self, other: &Scheduled) -> Ordering {
+        other
+            .due
+            .cmp(&self.due)
+            .then(self.priority.cmp(&other.priority))
+            .then(other.order.cmp(&self.order))
+    }
+}
+
+impl PartialOrd for Scheduled {
+    fn partial_cmp(&self, other: &Scheduled) -> Option<Ordering> {
+        Some(self.cmp(other))
+    }
+}
+
+/// Block updates waiting to run: updates caused by blocks changing, which run in the tick they
+/// happen, and ticks scheduled for later
+#[derive(Debug, Default)]
+pub struct BlockUpdates {
+    /// Number of ticks that have passed
+    time: u64,
+    /// Updates caused by blocks changing, which run in the same tick
+    changes: VecDeque<(BlockPos, BlockUpdate)>,
+    scheduled: BinaryHeap<Scheduled>,
+    /// Priorities of the scheduled ticks by position and time, so the same tick isn't scheduled
+    /// twice. Ticks in the heap with a lower priority than this were scheduled again with a
+    /// higher one and are skipped.
+    pending: HashMap<(BlockPos, u64), i32>,
+    next_order: u64,
+}
+
+impl BlockUpdates {
+    pub fn time(&self) -> u64 {
+        self.time
+    }
+
+    /// Schedules an update of the block at `position` in `delay` ticks. Of the ticks due at the
+    /// same time, the ones with the highest priority run first. Scheduling the same tick again
+    /// only raises its priority.
+    pub fn schedule(&mut self, position: BlockPos, delay: u64, priority: i32) {
+        let due = self.time + delay;
+        match self.pending.get(&(position, due)) {
+            Some(pending) if *pending >= priority => return,
+            _ => self.pending.insert((position, due), priority),
+        };
+        self.scheduled.push(Scheduled {
+            due,
+            priority,
+            order: self.next_order,
+            position,
+        });
+        self.next_order += 1;
+    }
+
+    /// Notifies the block at `position` that it was placed and the six blocks around it that it
+    /// changed
+    pub fn block_changed(&mut self, (x, y, z): BlockPos) {
+        self.changes.push_back(((x, y, z), BlockUpdate::Placed));
+        for (dx, dy, dz) in NEIGHBOURS {
+            let update = BlockUpdate::Neighbour((x, y, z));
+            self.changes.push_back(((x + dx, y + dy, z + dz), update));
+        }
+    }
+
+    /// Returns the next update that should run in the current tick
+    pub fn next_due(&mut self) -> Option<(BlockPos, BlockUpdate)> {
+        if let Some(change) = self.changes.pop_front() {
+            return Some(change);
+        }
+        loop {
+            let tick = self.scheduled.peek().filter(|tick| tick.due <= self.time)?;
+            let Scheduled {
+                position,
+                due,
+                priority,
+                ..
+            } = *tick;
+            self.scheduled.pop();
+            if self.pending.get(&(position, due)) == Some(&priority) {
+                self.pending.remove(&(position, due));
+                return Some((position, BlockUpdate::Scheduled));
+            }
+        }
+    }
+
+    /// Jumps to tick `time`, keeping scheduled ticks the same number of ticks away
+    pub fn set_time(&mut self, time: u64) {
+        let shift = |due: u64| (due + time).saturating_sub(self.time);
+        self.scheduled = self
+            .scheduled
+            .drain()
+            .map(|tick| Scheduled {
+                due: shift(tick.due),
+                ..tick
+            })
+            .collect();
+        self.pending = self
+            .pending
+            .drain()
+            .map(|((position, due), priority)| ((position, shift(due)), priority))
+            .collect();
+        self.time = time;
+    }
+
+    /// Moves on to the next tick
+    pub fn advance(&mut self) {
+        self.time += 1;
+    }
+}
+
+/// Ticks a falling block waits after it or a block next to it changed before it checks whether
+/// it is supported
+const FALL_DELAY: u64 = 2;
+
+/// Makes blocks whose type has [`crate::object::Object::gravity`] fall when nothing below holds
+/// them up, turning them into a falling block entity
+pub fn gravity_update(world: &mut World, (x, y, z): BlockPos, update: BlockUpdate) {
+    match update {
+        BlockUpdate::Placed | BlockUpdate::Neighbour(_) => {
+            world.schedule_tick((x, y, z), FALL_DELAY, 0)
+        }
+        BlockUpdate::Scheduled => {
+            let supported = y == 0
+                || matches!(
+                    movement_at(world, x, y - 1, z),
+                    Some(Movement::Solid | Movement::Door)
+                );
+            if !supported {
+                if let Some(id) = world.block_id_at(x, y, z) {
+                    world.set_block(x, y, z, AIR);
+                    world.start_falling((x, y, z), id);
+                }
+            }
+        }
+        BlockUpdate::Random => (),
+    }
+}
+
+/// Grass turns into dirt when something solid covers it, and otherwise spreads to dirt around it
+/// that has nothing solid on top
+pub fn grass_update(world: &mut World, (x, y, z): BlockPos, update: BlockUpdate, rng: &mut Rng) {
+    if update != BlockUpdate::Random {
+        return;
+    }
+    let covered = |world: &World, (x, y, z): BlockPos| {
+        movement_at(world, x, y + 1, z) == Some(Movement::Solid)
+    };
+    if covered(world, (x, y, z)) {
+        world.set_block(x, y, z, DIRT);
+        return;
+    }
+    let target = (
+        x + rng.range(-1, 2),
+        y + rng.range(-1, 2),
+        z + rng.range(-1, 2),
+    );
+    if world.block_id_at(target.0, target.1, target.2) == Some(DIRT) && !covered(world, target) {
+        world.set_block(target.0, target.1, target.2, GRASS);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::block_update::{BlockUpdate, BlockUpdates};
+    use crate::object::{DIRT, GRASS, STONE};
+    use crate::world::World;
+
+    #[test]
+    fn orders_updates() {
+        let mut updates = BlockUpdates::default();
+        updates.schedule((0, 0, 0), 2, 0);
+        updates.schedule((1, 0, 0), 1, 0);
+        updates.schedule((2, 0, 0), 1, 5);
+        updates.schedule((1, 0, 0), 1, 7);
+        updates.schedule((1, 0, 0), 1, 3);
+        updates.block_changed((5, 5, 5));
+
+        let due = |updates: &mut BlockUpdates| {
+            let due: Vec<_> = std::iter::from_fn(|| updates.next_due()).collect();
+            updates.advance();
+            due
+        };
+        // only the updates of the change run right away
+        let now = due(&mut updates);
+        assert_eq!(now.len(), 7);
+        assert_eq!(now[0], ((5, 5, 5), BlockUpdate::Placed));
+        assert!(now.contains(&((5, 6, 5), BlockUpdate::Neighbour((5, 5, 5)))));
+        // the higher priority first, and the same tick only once with its highest priority
+        assert_eq!(
+            due(&mut updates),
+            [
+                ((1, 0, 0), BlockUpdate::Scheduled),
+                ((2, 0, 0), BlockUpdate::Scheduled)
+            ]
+        );
+        assert_eq!(due(&mut updates), [((0, 0, 0), BlockUpdate::Scheduled)]);
+        assert_eq!(due(&mut updates), []);
+    }
+
+    /// Returns a world whose chunk at the origin is a dirt field at y = 1 with grass in the middle
+    /// and a stone slab on a grass block in the corner
+    fn field(seed: u64) -> World {
+        let mut world = World::with_seed(seed);
+        world.create_empty_chunk(0, 0);
+        for x in 0..16 {
+            for z in 0..16 {
+                world.set_block(x, 1, z, DIRT);
+            }
+        }
+        world.set_block(8, 1, 8, GRASS);
+        world.set_block(0, 1, 0, GRASS);
+        world.set_block(0, 2, 0, STONE);
+        world
+    }
+
+    #[test]
+    fn grass_spreads_deterministically() {
+        let grass = |world: &World| {
+            let mut blocks = vec![];
+            for x in 0..16 {
+                for z in 0..16 {
+                    if world.block_id_at(x, 1, z) == Some(GRASS) {
+                        blocks.push((x, z));
+                    }
+                }
+            }
+            blocks
+        };
+        let (mut world, mut again) = (field(3), field(3));
+        for _ in 0..20000 {
+            world.tick();
+            again.tick();
+        }
+        let spread = grass(&world);
+        assert!(spread.len() > 5);
+        assert!(!spread.contains(&(0, 0)));
+        assert_eq!(spread, grass(&again));
+    }
+}
@@ -1,10 +1,61 @@
+use crate::input::{Action, ActionState};
 use crate::math::Vec3;
+use crate::physics::{colliding, move_aabb, on_ground, on_ladder, Aabb};
 use crate::world::World;
-use glium::glutin::event::VirtualKeyCode;
-use std::collections::HashSet;
 
 const PLAYER_CAMERA_HEIGHT: f32 = 1.8;
+const SNEAK_CAMERA_HEIGHT: f32 = 1.5;
+pub const PLAYER_WIDTH: f32 = 0.6;
+pub const PLAYER_HEIGHT: f32 = 1.8;
 const JUMP: f32 = 0.03;
+const SNEAK_SPEED: f32 = 0.3;
+const SPRINT_SPEED: f32 = 1.5;
+const FLY_SPEED: f32 = 2.0;
+/// Distance flown up or down per update while jump or sneak is held
+const FLY_VERTICAL_SPEED: f32 = 0.01;
+/// Distance climbed up or slid down ladders per update
+const LADDER_SPEED: f32 = 0.04;
+/// Seconds within which a second press counts as a double tap
+const DOUBLE_TAP_TIME: f32 = 0.3;
+/// Field of view while sprinting, as a multiple of the normal one
+const SPRINT_FOV: f32 = 1.15;
+/// How quickly the field of view follows sprinting, per second
+const FOV_RATE: f32 = 10.0;
+
+/// The way the player moves
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum MovementState {
+    Walking,
+    /// Slower with a lower camera, and doesn't walk off edges
+    Sneaking,
+    /// Faster with a wider field of view, while moving forward
+    Sprinting,
+    /// No gravity and through blocks, jump and sneak move up and down
+    Flying,
+}
+
+/// Detects an action being pressed twice in quick succession
+#[derive(Debug, Default)]
+struct DoubleTap {
+    /// Seconds since the action was last pressed, if it was pressed recently
+    since_press: Option<f32>,
+}
+
+impl DoubleTap {
+    /// Returns whether `pressed` completes a double tap
+    fn update(&mut self, pressed: bool, dt: f32) -> bool {
+        let since_press = self.since_press.map(|time| time + dt);
+        self.since_press = since_press.filter(|time| *time <= DOUBLE_TAP_TIME);
+        if !pressed {
+            return false;
+        }
+        if self.since_press.take().is_some() {
+            return true;
+        }
+        self.since_press = Some(0.0);
+        false
+    }
+}
 
 pub struct Camera {
     acceleration: f32,
@@ -14,6 +65,12 @@ pub struct Camera {
     angle: (f32, f32),
     gravity: f32,
     vertical_speed: f32,
+    /// Radians the view turns per pixel of mouse movement
+    pub sensitivity: f32,
+    state: MovementState,
+    jump_taps: DoubleTap,
+    forward_taps: DoubleTap,
+    fov_scale: f32,
 }
 
 impl Default for Camera {
@@ -26,6 +83,11 @@ impl Default for Camera {
             angle: (0.0, 0.0),
             gravity: 0.0003,
             vertical_speed: 0.0,
+            sensitivity: 0.001,
+            state: MovementState::Walking,
+            jump_taps: DoubleTap::default(),
+            forward_taps: DoubleTap::default(),
+            fov_scale: 1.0,
         }
     }
 }
@@ -35,35 +97,106 @@ impl Camera {
         Vec3(0.0, 0.0, 1.0).rotate(self.angle.0, self.angle.1, 0.0)
     }
 
-    pub fn rotate(&mut self, (mut y_axis, mut x_axis): (f64, f64)) {
-        y_axis *= 0.001;
-        x_axis *= 0.001;
-        let mut x_angle = x_axis as f32 + self.angle.1;
-        if x_angle < -1.5 {
-            x_angle = -1.5;
-        } else if x_angle > 1.5 {
-            x_angle = 1.5;
-        }
-        self.angle = (self.angle.0 + y_axis as f32, x_angle);
-    }
-    pub fn handle_keys(&mut self, pressed_keys: &HashSet<VirtualKeyCode>, world: &mut World) {
-        if pressed_keys.contains(&VirtualKeyCode::Space) {
-            let x = self.position.0.floor() as i32;
-            let y = self.position.1;
-            let y = y - PLAYER_CAMERA_HEIGHT;
-            let y = y.ceil() as i32;
-            let z = self.position.2.floor() as i32;
-            if world.block_at(x, y, z).is_some() {
-                self.vertical_speed = JUMP;
+    /// Horizontal angle of the view direction, 0 faces +z and a quarter turn faces +x
+    pub fn yaw(&self) -> f32 {
+        self.angle.0
+    }
+
+    /// Vertical angle of the view direction, negative values look up
+    pub fn pitch(&self) -> f32 {
+        self.angle.1
+    }
+
+    /// Turns the view by a mouse movement in pixels
+    pub fn rotate(&mut self, (y_axis, x_axis): (f64, f64)) {
+        let sensitivity = self.sensitivity as f64;
+        self.turn((y_axis * sensitivity) as f32, (x_axis * sensitivity) as f32);
+    }
+
+    /// Turns the view by `yaw` and `pitch` radians, positive values turn right and down
+    pub fn turn(&mut self, yaw: f32, pitch: f32) {
+        let x_angle = (pitch + self.angle.1).clamp(-1.5, 1.5);
+        self.angle = (self.angle.0 + yaw, x_angle);
+    }
+
+    pub fn state(&self) -> MovementState {
+        self.state
+    }
+
+    /// Returns the field of view as a multiple of the one in the settings, which widens while
+    /// sprinting
+    pub fn fov_scale(&self) -> f32 {
+        self.fov_scale
+    }
+
+    /// Height of the camera above the player's feet
+    fn eye_height(&self) -> f32 {
+        if self.state == MovementState::Sneaking {
+            SNEAK_CAMERA_HEIGHT
+        } else {
+            PLAYER_CAMERA_HEIGHT
+        }
+    }
+
+    /// Returns the box the player takes up
+    pub fn aabb(&self) -> Aabb {
+        let feet = self.position - Vec3(0.0, self.eye_height(), 0.0);
+        Aabb::standing(feet, PLAYER_WIDTH, PLAYER_HEIGHT)
+    }
+
+    fn update_state(&mut self, actions: &ActionState, dt: f32) {
+        let jump_twice = self.jump_taps.update(actions.pressed(Action::Jump), dt);
+        let forward_twice = self
+            .forward_taps
+            .update(actions.pressed(Action::MoveForward), dt);
+        let forward = actions.held(Action::MoveForward);
+        let previous_height = self.eye_height();
+        self.state = match self.state {
+            MovementState::Flying if jump_twice => MovementState::Walking,
+            MovementState::Flying => MovementState::Flying,
+            _ if jump_twice => MovementState::Flying,
+            _ if actions.held(Action::Sneak) => MovementState::Sneaking,
+            MovementState::Sprinting if forward => MovementState::Sprinting,
+            _ if forward && (forward_twice || actions.held(Action::Sprint)) => {
+                MovementState::Sprinting
             }
+            _ => MovementState::Walking,
+        };
+        if self.state == MovementState::Flying {
+            self.vertical_speed = 0.0;
+        }
+        // the feet stay where they are when the camera is lowered or raised
+        self.position.1 += self.eye_height() - previous_height;
+
+        let fov_target = if self.state == MovementState::Sprinting {
+            SPRINT_FOV
+        } else {
+            1.0
+        };
+        self.fov_scale += (fov_target - self.fov_scale) * (FOV_RATE * dt).min(1.0);
+    }
+
+    /// Moves the player by the held actions. `dt` is the time since the last update in seconds,
+    /// which is used to detect double taps.
+    pub fn handle_actions(&mut self, actions: &ActionState, dt: f32, world: &World) {
+        self.update_state(actions, dt);
+        let flying = self.state == MovementState::Flying;
+        // lifts the player out of blocks, like after flying into them
+        while !flying && colliding(world, &self.aabb()) && self.position.1 < 512.0 {
+            self.position.1 =
+                (self.position.1 - self.eye_height()).floor() + 1.0 + self.eye_height();
+        }
+        let aabb = self.aabb();
+        let grounded = !flying && on_ground(world, &aabb);
+        if actions.held(Action::Jump) && grounded {
+            self.vertical_speed = JUMP;
         }
-        if pressed_keys.contains(&VirtualKeyCode::LShift) {}
 
         let mut any_nonconflicting = false;
-        if pressed_keys.contains(&VirtualKeyCode::W) != pressed_keys.contains(&VirtualKeyCode::S) {
+        if actions.held(Action::MoveForward) != actions.held(Action::MoveBack) {
             any_nonconflicting = true;
         }
-        if pressed_keys.contains(&VirtualKeyCode::A) != pressed_keys.contains(&VirtualKeyCode::D) {
+        if actions.held(Action::MoveLeft) != actions.held(Action::MoveRight) {
             any_nonconflicting = true;
         }
 
@@ -71,40 +204,161 @@ impl Camera {
             self.speed += self.acceleration;
         }
 
-        let x = self.position.0.floor() as i32;
-        let y = self.position.1;
-        let y = y - PLAYER_CAMERA_HEIGHT;
-        let y = y.ceil() as i32;
-        let z = self.position.2.floor() as i32;
-        if !world.block_at(x, y, z).is_some() {
+        if flying {
+            let up = actions.held(Action::Jump) as i32 - actions.held(Action::Sneak) as i32;
+            self.vertical_speed = up as f32 * FLY_VERTICAL_SPEED;
+        } else if on_ladder(world, &aabb) {
+            // climbs while jump is held and slides down slowly otherwise
+            self.vertical_speed = if actions.held(Action::Jump) {
+                LADDER_SPEED
+            } else {
+                self.vertical_speed.max(-LADDER_SPEED)
+            };
+        } else if !grounded {
             self.vertical_speed -= self.gravity;
         } else if self.vertical_speed <= 0.0 {
             self.vertical_speed = 0.0;
         }
 
-        let mut movement_dir = Vec3(0.0, 0.0, 0.0);
         let camera_dir = self.get_direction();
-        if pressed_keys.contains(&VirtualKeyCode::W) {
-            movement_dir += camera_dir;
+        let forward = Vec3(camera_dir.0, 0.0, camera_dir.2).normalize();
+        let right = forward.rotate(0.5 * std::f32::consts::PI, 0.0, 0.0);
+        // analog inputs move slower when they are only pushed part of the way
+        let mut movement_dir = forward
+            .scale(actions.strength(Action::MoveForward) - actions.strength(Action::MoveBack))
+            + right.scale(actions.strength(Action::MoveRight) - actions.strength(Action::MoveLeft));
+        if movement_dir.len() > 1.0 {
+            movement_dir = movement_dir.normalize();
+        }
+        let speed = self.speed
+            * match self.state {
+                MovementState::Walking => 1.0,
+                MovementState::Sneaking => SNEAK_SPEED,
+                MovementState::Sprinting => SPRINT_SPEED,
+                MovementState::Flying => FLY_SPEED,
+            };
+        let mut movement = movement_dir.scale(speed);
+        movement.1 = self.vertical_speed;
+        if flying {
+            // flies through blocks
+            self.position += movement;
+            return;
         }
-        if pressed_keys.contains(&VirtualKeyCode::S) {
-            movement_dir -= camera_dir;
+        if self.state == MovementState::Sneaking && grounded {
+            // sneaking stops at edges, but can still slide along them
+            if !on_ground(world, &aabb.offset(Vec3(movement.0, 0.0, 0.0))) {
+                movement.0 = 0.0;
+            }
+            if !on_ground(world, &aabb.offset(Vec3(movement.0, 0.0, movement.2))) {
+                movement.2 = 0.0;
+            }
+        }
+        let collision = move_aabb(world, &aabb, movement);
+        if collision.stopped.1 {
+            self.vertical_speed = 0.0;
         }
-        let right =
-            Vec3(camera_dir.0, 0.0, camera_dir.2).rotate(0.5 * std::f32::consts::PI, 0.0, 0.0);
+        self.position += collision.motion;
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::camera::{Camera, MovementState};
+    use crate::input::{Bindings, Input, InputState};
+    use crate::math::Vec3;
+    use crate::object::STONE;
+    use crate::world::World;
+    use glium::glutin::event::VirtualKeyCode;
+
+    const FRAME: f32 = 1.0 / 60.0;
 
-        if pressed_keys.contains(&VirtualKeyCode::D) {
-            movement_dir += right;
+    /// Returns a world with a 3x3 stone platform around the origin, with its top at y = 11
+    fn platform() -> World {
+        let mut world = World::new();
+        for x in -1..=1 {
+            for z in -1..=1 {
+                world.chunk_at_pos_create(x, z);
+                world.set_block(x, 10, z, STONE);
+            }
         }
-        if pressed_keys.contains(&VirtualKeyCode::A) {
-            movement_dir -= right
+        world
+    }
+
+    /// Returns a camera standing in the middle of the platform, facing +z
+    fn standing() -> Camera {
+        Camera {
+            position: Vec3(0.5, 11.0 + 1.8, 0.5),
+            ..Camera::default()
         }
-        movement_dir.1 = 0.0;
-        if movement_dir != Vec3(0.0, 0.0, 0.0) {
-            movement_dir = movement_dir.normalize();
-            movement_dir = movement_dir.scale(self.speed);
-            self.position += movement_dir;
+    }
+
+    fn update(camera: &mut Camera, input: &mut InputState, world: &mut World, frames: usize) {
+        for _ in 0..frames {
+            let actions = input.take_actions(&Bindings::default());
+            camera.handle_actions(&actions, FRAME, world);
         }
-        self.position.1 += self.vertical_speed;
+    }
+
+    fn tap(camera: &mut Camera, input: &mut InputState, world: &mut World, key: VirtualKeyCode) {
+        input.press(Input::Key(key));
+        update(camera, input, world, 1);
+        input.release(Input::Key(key));
+        update(camera, input, world, 1);
+    }
+
+    #[test]
+    fn sneaking_stops_at_edges() {
+        let mut world = platform();
+        let mut camera = standing();
+        let mut input = InputState::default();
+        input.press(Input::Key(VirtualKeyCode::LShift));
+        update(&mut camera, &mut input, &mut world, 1);
+        assert_eq!(camera.state(), MovementState::Sneaking);
+        assert!((camera.position.1 - 12.5).abs() < 1e-5);
+
+        input.press(Input::Key(VirtualKeyCode::W));
+        update(&mut camera, &mut input, &mut world, 5000);
+        // the player's box still overlaps the platform by a little
+        assert!(camera.position.2 < 2.3 && camera.position.2 > 2.2);
+        assert!((camera.position.1 - 12.5).abs() < 1e-5);
+
+        // without sneaking the player walks off and falls
+        input.release(Input::Key(VirtualKeyCode::LShift));
+        update(&mut camera, &mut input, &mut world, 100);
+        assert_eq!(camera.state(), MovementState::Walking);
+        assert!(camera.position.1 < 12.8);
+    }
+
+    #[test]
+    fn double_taps() {
+        let mut world = platform();
+        let mut camera = standing();
+        let mut input = InputState::default();
+
+        tap(&mut camera, &mut input, &mut world, VirtualKeyCode::W);
+        input.press(Input::Key(VirtualKeyCode::W));
+        update(&mut camera, &mut input, &mut world, 30);
+        assert_eq!(camera.state(), MovementState::Sprinting);
+        assert!(camera.fov_scale() > 1.1);
+        input.release(Input::Key(VirtualKeyCode::W));
+        update(&mut camera, &mut input, &mut world, 30);
+        assert_eq!(camera.state(), MovementState::Walking);
+        assert!(camera.fov_scale() < 1.01);
+
+        // taps too far apart don't count
+        tap(&mut camera, &mut input, &mut world, VirtualKeyCode::Space);
+        update(&mut camera, &mut input, &mut world, 60);
+        tap(&mut camera, &mut input, &mut world, VirtualKeyCode::Space);
+        assert_ne!(camera.state(), MovementState::Flying);
+
+        tap(&mut camera, &mut input, &mut world, VirtualKeyCode::Space);
+        assert_eq!(camera.state(), MovementState::Flying);
+        let height = camera.position.1;
+        update(&mut camera, &mut input, &mut world, 60);
+        assert_eq!(camera.position.1, height);
+        input.press(Input::Key(VirtualKeyCode::LShift));
+        update(&mut camera, &mut input, &mut world, 10);
+        assert_eq!(camera.state(), MovementState::Flying);
+        assert!(camera.position.1 < height);
     }
 }
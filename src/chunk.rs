@@ -9,6 +9,12 @@ pub struct Chunk {
     pub mesh: Option<(VertexBuffer<Vertex>, IndexBuffer<u32>)>,
 }
 
+impl Default for Chunk {
+    fn default() -> Chunk {
+        Chunk::new()
+    }
+}
+
 impl Chunk {
     pub fn new() -> Chunk {
         let mut chunk = Chunk {
@@ -22,6 +28,13 @@ impl Chunk {
         }
         chunk
     }
+    /// Returns the height and id of the topmost block that isn't air in column (x, z) of this chunk
+    pub fn top_block(&self, x: usize, z: usize) -> Option<(usize, u8)> {
+        (0..256)
+            .rev()
+            .map(|y| (y, self.blocks[x][y][z]))
+            .find(|(_, id)| *id != 0)
+    }
     pub fn prepare(&mut self, display: &Display) {
         let mut shape = vec![];
         let mut indices: Vec<u32> = vec![];
@@ -0,0 +1,9 @@
+#version 150
+
+out vec4 color;
+
+uniform vec4 colour;
+
+void main() {
+    color = colour;
+}
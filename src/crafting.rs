@@ -0,0 +1,420 @@
+use crate::inventory::{Inventory, ItemStack, MAX_STACK};
+use crate::world::World;
+use std::fmt::{Display, Formatter};
+
+/// Side length of the crafting grid
+pub const GRID_SIZE: usize = 3;
+
+/// The built-in recipes
+pub const DEFAULT_RECIPES: &str = include_str!("recipes.txt");
+
+#[derive(Debug, Clone, PartialEq)]
+pub enum Ingredients {
+    /// Rows of cells that have to be placed in this arrangement, or mirrored left to right.
+    /// All rows have the same length.
+    Shaped(Vec<Vec<Option<u8>>>),
+    /// Block ids that can be placed anywhere in the grid, sorted
+    Shapeless(Vec<u8>),
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct Recipe {
+    pub ingredients: Ingredients,
+    pub result: ItemStack,
+}
+
+#[derive(Debug, PartialEq)]
+pub enum RecipeError {
+    /// The line doesn't have the form of a recipe
+    Syntax { line: usize, message: String },
+    /// The recipe names a block type that doesn't exist
+    UnknownBlock { line: usize, name: String },
+}
+
+impl Display for RecipeError {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        match self {
+            RecipeError::Syntax { line, message } => write!(f, "line {}: {}", line, message),
+            RecipeError::UnknownBlock { line, name } => {
+                write!(f, "line {}: unknown block '{}'", line, name)
+            }
+        }
+    }
+}
+
+impl std::error::Error for RecipeError {}
+
+/// The cells of the crafting grid, indexed by [row][column] from the top left
+pub type CraftingGrid = [[Option<ItemStack>; GRID_SIZE]; GRID_SIZE];
+
+/// Returns the rows and columns of the grid that contain items, cut down to their bounding box
+fn trim(grid: &CraftingGrid) -> Vec<Vec<Option<u8>>> {
+    let filled = |row: usize, column: usize| grid[row][column].is_some();
+    let rows: Vec<usize> = (0..GRID_SIZE)
+        .filter(|row| (0..GRID_SIZE).any(|column| filled(*row, column)))
+        .collect();
+    let columns: Vec<usize> = (0..GRID_SIZE)
+        .filter(|column| (0..GRID_SIZE).any(|row| filled(row, *column)))
+        .collect();
+    let (Some(top), Some(bottom)) = (rows.first(), rows.last()) else {
+        return vec![];
+    };
+    let (left, right) = (columns[0], columns[columns.len() - 1]);
+    (*top..=*bottom)
+        .map(|row| {
+            (left..=right)
+                .map(|column| grid[row][column].map(|stack| stack.id))
+                .collect()
+        })
+        .collect()
+}
+
+impl Recipe {
+    /// Returns whether the items in the grid match this recipe
+    pub fn matches(&self, grid: &CraftingGrid) -> bool {
+        match &self.ingredients {
+            Ingredients::Shaped(rows) => {
+                let placed = trim(grid);
+                let mirrored: Vec<Vec<Option<u8>>> = rows
+                    .iter()
+                    .map(|row| row.iter().rev().copied().collect())
+                    .collect();
+                placed == *rows || placed == mirrored
+            }
+            Ingredients::Shapeless(ids) => {
+                let mut placed: Vec<u8> = grid.iter().flatten().flatten().map(|s| s.id).collect();
+                placed.sort_unstable();
+                placed == *ids
+            }
+        }
+    }
+}
+
+/// All known crafting recipes
+#[derive(Debug, Default)]
+pub struct RecipeBook {
+    recipes: Vec<Recipe>,
+}
+
+impl RecipeBook {
+    /// Parses recipes in the format of `recipes.txt`, looking up block names in the block types
+    /// of `world`
+    pub fn parse(text: &str, world: &World) -> Result<RecipeBook, RecipeError> {
+        let mut book = RecipeBook::default();
+        for (index, line) in text.lines().enumerate() {
+            let line_number = index + 1;
+            let line = line.trim();
+            if line.is_empty() || line.starts_with('#') {
+                continue;
+            }
+            let syntax = |message: &str| RecipeError::Syntax {
+                line: line_number,
+                message: message.to_string(),
+            };
+            let block = |name: &str| match name {
+                "_" => Ok(None),
+                name => world
+                    .block_id(name)
+                    .map(Some)
+                    .ok_or(RecipeError::UnknownBlock {
+                        line: line_number,
+                        name: name.to_string(),
+                    }),
+            };
+
+            let (head, body) = line.split_once('=').ok_or(syntax(
+                "expected '=' between the result and the ingredients",
+            ))?;
+            let [kind, result, count] = head.split_whitespace().collect::<Vec<_>>()[..] else {
+                return Err(syntax("expected KIND RESULT COUNT before '='"));
+            };
+            let id = block(result)?.ok_or(syntax("the result can't be empty"))?;
+            let count = count
+                .parse()
+                .ok()
+                .filter(|count| *count > 0)
+                .ok_or(syntax("the count must be a number from 1 to 255"))?;
+
+            let ingredients = match kind {
+                "shaped" => {
+                    let rows = body
+                        .split('/')
+                        .map(|row| row.split_whitespace().map(block).collect())
+                        .collect::<Result<Vec<Vec<_>>, _>>()?;
+                    if rows.len() > GRID_SIZE || rows.iter().any(|row| row.len() > GRID_SIZE) {
+                        return Err(syntax("the shape doesn't fit into the crafting grid"));
+                    }
+                    if rows.iter().any(|row| row.len() != rows[0].len()) {
+                        return Err(syntax("all rows must have the same length"));
+                    }
+                    let grid = rows.iter().enumerate().fold(
+                        CraftingGrid::default(),
+                        |mut grid, (r, row)| {
+                            for (c, cell) in row.iter().enumerate() {
+                                grid[r][c] = cell.map(|id| ItemStack::new(id, 1));
+                            }
+                            grid
+                        },
+                    );
+                    if trim(&grid) != rows {
+                        return Err(syntax("the shape has empty rows or columns at its border"));
+                    }
+                    Ingredients::Shaped(rows)
+                }
+                "shapeless" => {
+                    let mut ids = body
+                        .split_whitespace()
+                        .map(block)
+                        .collect::<Result<Option<Vec<u8>>, _>>()?
+                        .ok_or(syntax("shapeless recipes can't have empty cells"))?;
+                    if ids.is_empty() || ids.len() > GRID_SIZE * GRID_SIZE {
+                        return Err(syntax("a shapeless recipe needs 1 to 9 ingredients"));
+                    }
+                    ids.sort_unstable();
+                    Ingredients::Shapeless(ids)
+                }
+                _ => return Err(syntax("the kind must be 'shaped' or 'shapeless'")),
+            };
+            book.recipes.push(Recipe {
+                ingredients,
+                result: ItemStack::new(id, count),
+            });
+        }
+        Ok(book)
+    }
+
+    pub fn recipes(&self) -> &[Recipe] {
+        &self.recipes
+    }
+
+    /// Adds the recipes of `other` to this book
+    pub fn extend(&mut self, other: RecipeBook) {
+        self.recipes.extend(other.recipes);
+    }
+
+    /// Returns the first recipe that matches the items in the grid
+    pub fn find(&self, grid: &CraftingGrid) -> Option<&Recipe> {
+        self.recipes.iter().find(|recipe| recipe.matches(grid))
+    }
+
+    /// Returns all recipes that make the block type `id`
+    pub fn recipes_for(&self, id: u8) -> impl Iterator<Item = &Recipe> {
+        self.recipes
+            .iter()
+            .filter(move |recipe| recipe.result.id == id)
+    }
+
+    /// Crafts the recipe matching the grid once, taking one item from every cell
+    pub fn craft(&self, grid: &mut CraftingGrid) -> Option<ItemStack> {
+        let result = self.find(grid)?.result;
+        for cell in grid.iter_mut().flatten() {
+            if let Some(stack) = cell {
+                stack.count -= 1;
+                if stack.count == 0 {
+                    *cell = None;
+                }
+            }
+        }
+        Some(result)
+    }
+}
+
+/// The crafting grid the player is working with and the moves between it and the inventory
+#[derive(Debug, Default)]
+pub struct CraftingTable {
+    pub grid: CraftingGrid,
+}
+
+impl CraftingTable {
+    /// Moves one item of the selected hotbar stack into the cell, if the cell is empty or holds
+    /// the same block type
+    pub fn place(&mut self, row: usize, column: usize, inventory: &mut Inventory) {
+        let Some(selected) = inventory.selected_stack() else {
+            return;
+        };
+        let cell = &mut self.grid[row][column];
+        match cell {
+            None => *cell = Some(ItemStack::new(selected.id, 1)),
+            Some(stack) if stack.id == selected.id && stack.count < MAX_STACK => stack.count += 1,
+            Some(_) => return,
+        }
+        inventory.take_selected();
+    }
+
+    /// Moves the items in the cell back into the inventory, as far as they fit
+    pub fn take(&mut self, row: usize, column: usize, inventory: &mut Inventory) {
+        if let Some(stack) = self.grid[row][column] {
+            let left = inventory.add(stack.id, stack.count);
+            self.grid[row][column] = Some(ItemStack::new(stack.id, left)).filter(|s| s.count > 0);
+        }
+    }
+
+    /// Crafts the matching recipe once and puts the result into the inventory. Does nothing if
+    /// the result doesn't fit. Returns whether anything was crafted.
+    pub fn craft(&mut self, book: &RecipeBook, inventory: &mut Inventory) -> bool {
+        let Some(recipe) = book.find(&self.grid) else {
+            return false;
+        };
+        let mut after = inventory.clone();
+        if after.add(recipe.result.id, recipe.result.count) > 0 {
+            return false;
+        }
+        *inventory = after;
+        book.craft(&mut self.grid);
+        true
+    }
+
+    /// Moves all items in the grid back into the inventory
+    pub fn clear(&mut self, inventory: &mut Inventory) {
+        for row in 0..GRID_SIZE {
+            for column in 0..GRID_SIZE {
+                self.take(row, column, inventory);
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::crafting::{
+        CraftingGrid, CraftingTable, Ingredients, RecipeBook, RecipeError, DEFAULT_RECIPES,
+    };
+    use crate::inventory::{Inventory, ItemStack};
+    use crate::object::{COBBLESTONE, DIRT, GRASS, LEAVES, LOG, PLANKS, STONE};
+    use crate::world::World;
+
+    fn grid(cells: &[(usize, usize, u8)]) -> CraftingGrid {
+        let mut grid = CraftingGrid::default();
+        for (row, column, id) in cells {
+            grid[*row][*column] = Some(ItemStack::new(*id, 2));
+        }
+        grid
+    }
+
+    #[test]
+    fn default_recipes_are_valid() {
+        let book = RecipeBook::parse(DEFAULT_RECIPES, &World::new()).unwrap();
+        assert!(!book.recipes().is_empty());
+        assert_eq!(book.recipes_for(PLANKS).count(), 1);
+    }
+
+    #[test]
+    fn default_recipes_dont_duplicate_items() {
+        let book = RecipeBook::parse(DEFAULT_RECIPES, &World::new()).unwrap();
+        // rates[a][b] is the most of b that one a can be crafted into, counting each ingredient
+        // as if it were the only one, so the check errs on the side of finding duplication
+        let mut rates = vec![vec![0.0f32; 256]; 256];
+        for recipe in book.recipes() {
+            let ids: Vec<u8> = match &recipe.ingredients {
+                Ingredients::Shaped(rows) => rows.iter().flatten().flatten().copied().collect(),
+                Ingredients::Shapeless(ids) => ids.clone(),
+            };
+            for id in &ids {
+                let used = ids.iter().filter(|other| *other == id).count() as f32;
+                let rate = &mut rates[*id as usize][recipe.result.id as usize];
+                *rate = rate.max(recipe.result.count as f32 / used);
+            }
+        }
+        // the best chain between every two items, which gains items if it leads back to the start
+        for via in 0..256 {
+            for from in 0..256 {
+                for to in 0..256 {
+                    let chained = rates[from][via] * rates[via][to];
+                    rates[from][to] = rates[from][to].max(chained);
+                }
+            }
+        }
+        for (id, row) in rates.iter().enumerate() {
+            assert!(row[id] <= 1.0, "recipes duplicate block {}", id);
+        }
+    }
+
+    #[test]
+    fn shaped_recipes_match_anywhere_and_mirrored() {
+        let world = World::new();
+        let book = RecipeBook::parse(
+            "shaped stone 2 = cobblestone _ / cobblestone cobblestone\nshaped grass 1 = leaves / dirt",
+            &world,
+        )
+        .unwrap();
+        let c = COBBLESTONE;
+        let result = |grid| book.find(&grid).map(|recipe| recipe.result);
+
+        assert_eq!(
+            result(grid(&[(0, 0, c), (1, 0, c), (1, 1, c)])),
+            Some(ItemStack::new(STONE, 2))
+        );
+        // moved to the bottom right
+        assert!(result(grid(&[(1, 1, c), (2, 1, c), (2, 2, c)])).is_some());
+        // mirrored left to right
+        assert!(result(grid(&[(0, 1, c), (1, 0, c), (1, 1, c)])).is_some());
+        // upside down isn't the same shape
+        assert!(result(grid(&[(0, 0, c), (0, 1, c), (1, 0, c)])).is_none());
+        // an extra item breaks the shape
+        assert!(result(grid(&[(0, 0, c), (1, 0, c), (1, 1, c), (2, 2, c)])).is_none());
+
+        assert_eq!(
+            result(grid(&[(1, 2, LEAVES), (2, 2, DIRT)])),
+            Some(ItemStack::new(GRASS, 1))
+        );
+        assert!(result(grid(&[(1, 2, DIRT), (2, 2, LEAVES)])).is_none());
+    }
+
+    #[test]
+    fn shapeless_recipes_and_crafting() {
+        let book = RecipeBook::parse("shapeless planks 4 = log log", &World::new()).unwrap();
+        let mut grid = grid(&[(2, 0, LOG), (0, 2, LOG)]);
+        assert_eq!(book.craft(&mut grid), Some(ItemStack::new(PLANKS, 4)));
+        assert_eq!(book.craft(&mut grid), Some(ItemStack::new(PLANKS, 4)));
+        assert_eq!(grid, CraftingGrid::default());
+        assert_eq!(book.craft(&mut grid), None);
+    }
+
+    #[test]
+    fn invalid_recipes() {
+        let world = World::new();
+        let error = |text| RecipeBook::parse(text, &world).unwrap_err();
+        assert_eq!(
+            error("# comment\n\nshaped planks 4 = wood"),
+            RecipeError::UnknownBlock {
+                line: 3,
+                name: "wood".to_string()
+            }
+        );
+        assert!(matches!(
+            error("shaped planks 4 = log / log log"),
+            RecipeError::Syntax { line: 1, .. }
+        ));
+        assert!(matches!(
+            error("shaped planks 4 = _ log"),
+            RecipeError::Syntax { .. }
+        ));
+        assert!(matches!(
+            error("shapeless planks 0 = log"),
+            RecipeError::Syntax { .. }
+        ));
+        assert!(matches!(
+            error("cooked planks 1 = log"),
+            RecipeError::Syntax { .. }
+        ));
+    }
+
+    #[test]
+    fn crafting_table_moves_items() {
+        let book = RecipeBook::parse("shapeless planks 4 = log", &World::new()).unwrap();
+        let mut inventory = Inventory::default();
+        inventory.add(LOG, 2);
+        let mut table = CraftingTable::default();
+        table.place(1, 1, &mut inventory);
+        table.place(1, 1, &mut inventory);
+        table.place(1, 1, &mut inventory);
+        assert_eq!(table.grid[1][1], Some(ItemStack::new(LOG, 2)));
+        assert_eq!(inventory.count(LOG), 0);
+
+        assert!(table.craft(&book, &mut inventory));
+        assert_eq!(inventory.count(PLANKS), 4);
+        table.clear(&mut inventory);
+        assert_eq!(inventory.count(LOG), 1);
+        assert_eq!(table.grid, CraftingGrid::default());
+    }
+}
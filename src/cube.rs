@@ -152,4 +152,38 @@ impl Cube {
             },
         ]
     }
+
+    /// Returns all six faces as a triangle list
+    pub fn faces() -> Vec<Vertex> {
+        let faces = [
+            Cube::left(),
+            Cube::right(),
+            Cube::top(),
+            Cube::bottom(),
+            Cube::front(),
+            Cube::back(),
+        ];
+        faces
+            .iter()
+            .flat_map(|face| [0, 1, 2, 1, 3, 2].map(|i| face[i]))
+            .collect()
+    }
+
+    /// Returns the twelve edges of the cube as a line list
+    pub fn edges() -> Vec<Vertex> {
+        let corner = |x: f32, y: f32, z: f32| Vertex {
+            position: [x, y, z],
+            normal: [0.0, 0.0, 0.0],
+            tex_coords: [0.0, 0.0],
+        };
+        let mut edges = vec![];
+        for a in [0.0, 1.0] {
+            for b in [0.0, 1.0] {
+                edges.extend([corner(0.0, a, b), corner(1.0, a, b)]);
+                edges.extend([corner(a, 0.0, b), corner(a, 1.0, b)]);
+                edges.extend([corner(a, b, 0.0), corner(a, b, 1.0)]);
+            }
+        }
+        edges
+    }
 }
@@ -0,0 +1,200 @@
+use crate::math::Vec3;
+use crate::sky::clock;
+use std::collections::VecDeque;
+use std::fmt::{Display, Formatter};
+
+/// Number of frames kept for the frame time graphs
+pub const HISTORY_LENGTH: usize = 120;
+
+/// The most recent durations of something that happens once per frame, in milliseconds
+#[derive(Debug, Default, Clone)]
+pub struct TimeHistory {
+    samples: VecDeque<f32>,
+}
+
+impl TimeHistory {
+    pub fn push(&mut self, milliseconds: f32) {
+        if self.samples.len() == HISTORY_LENGTH {
+            self.samples.pop_front();
+        }
+        self.samples.push_back(milliseconds);
+    }
+
+    /// Returns the samples from oldest to newest
+    pub fn samples(&self) -> impl Iterator<Item = f32> + '_ {
+        self.samples.iter().copied()
+    }
+
+    pub fn last(&self) -> f32 {
+        self.samples.back().copied().unwrap_or(0.0)
+    }
+
+    pub fn average(&self) -> f32 {
+        if self.samples.is_empty() {
+            return 0.0;
+        }
+        self.samples.iter().sum::<f32>() / self.samples.len() as f32
+    }
+
+    pub fn max(&self) -> f32 {
+        self.samples.iter().copied().fold(0.0, f32::max)
+    }
+}
+
+/// Returns the compass direction of a horizontal direction. +z is south and +x is east.
+pub fn compass(direction: Vec3) -> &'static str {
+    if direction.0.abs() > direction.2.abs() {
+        if direction.0 > 0.0 {
+            "east"
+        } else {
+            "west"
+        }
+    } else if direction.2 > 0.0 {
+        "south"
+    } else {
+        "north"
+    }
+}
+
+/// A snapshot of the state of the game for the debug overlay, collected once per frame
+#[derive(Debug, Default, Clone)]
+pub struct DebugInfo {
+    /// Position of the camera
+    pub position: Vec3,
+    pub direction: Vec3,
+    /// Horizontal and vertical view angles in radians
+    pub angles: (f32, f32),
+    /// Position and name of the block being looked at
+    pub looked_at: Option<((i32, i32, i32), String)>,
+    pub loaded_chunks: usize,
+    pub rendered_chunks: usize,
+    /// Number of vertices in all chunk meshes that were drawn
+    pub vertices: usize,
+    pub frame_times: TimeHistory,
+    /// Time spent updating the game each frame, from the start of the frame until drawing
+    pub update_times: TimeHistory,
+    /// World time in ticks
+    pub time: u64,
+    /// Size of the window in physical pixels
+    pub window_size: (u32, u32),
+    /// Physical pixels per logical pixel of the window
+    pub scale_factor: f64,
+}
+
+impl DebugInfo {
+    /// Returns the block the camera is in
+    pub fn block(&self) -> (i32, i32, i32) {
+        (
+            self.position.0.floor() as i32,
+            self.position.1.floor() as i32,
+            self.position.2.floor() as i32,
+        )
+    }
+
+    /// Returns the **chunk position** of the camera and its block within the chunk
+    pub fn chunk(&self) -> ((i32, i32), (i32, i32, i32)) {
+        let (x, y, z) = self.block();
+        (
+            (x.div_euclid(16), z.div_euclid(16)),
+            (x.rem_euclid(16), y, z.rem_euclid(16)),
+        )
+    }
+
+    /// Returns the overlay text, one entry per line
+    pub fn lines(&self) -> Vec<String> {
+        let Vec3(x, y, z) = self.position;
+        let (bx, by, bz) = self.block();
+        let ((cx, cz), (rx, ry, rz)) = self.chunk();
+        let (day, hour, minute) = clock(self.time);
+        let frames = &self.frame_times;
+        let fps = if frames.average() > 0.0 {
+            1000.0 / frames.average()
+        } else {
+            0.0
+        };
+        let mut lines = vec![
+            format!("XYZ: {:.3} / {:.3} / {:.3}", x, y, z),
+            format!("Block: {} {} {}", bx, by, bz),
+            format!("Chunk: {} {} (in chunk {} {} {})", cx, cz, rx, ry, rz),
+            format!(
+                "Facing: {} (yaw {:.1}, pitch {:.1})",
+                compass(self.direction),
+                self.angles.0.to_degrees(),
+                self.angles.1.to_degrees()
+            ),
+        ];
+        lines.push(match &self.looked_at {
+            Some(((x, y, z), name)) => format!("Looking at: {} {} {} ({})", x, y, z, name),
+            None => "Looking at: nothing".to_string(),
+        });
+        lines.extend([
+            format!(
+                "Chunks: {} rendered, {} loaded",
+                self.rendered_chunks, self.loaded_chunks
+            ),
+            format!("Vertices: {}", self.vertices),
+            format!(
+                "Time: day {}, {:02}:{:02} ({})",
+                day, hour, minute, self.time
+            ),
+            format!(
+                "Frame: {:.1} ms avg, {:.1} ms max ({:.0} fps)",
+                frames.average(),
+                frames.max(),
+                fps
+            ),
+            format!(
+                "Update: {:.2} ms avg, {:.2} ms max",
+                self.update_times.average(),
+                self.update_times.max()
+            ),
+            format!(
+                "Window: {}x{} at scale {}",
+                self.window_size.0, self.window_size.1, self.scale_factor
+            ),
+        ]);
+        lines
+    }
+}
+
+impl Display for DebugInfo {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        write!(f, "{}", self.lines().join("\n"))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::debug::{compass, DebugInfo, TimeHistory, HISTORY_LENGTH};
+    use crate::math::Vec3;
+
+    #[test]
+    fn time_history() {
+        let mut history = TimeHistory::default();
+        assert_eq!(history.average(), 0.0);
+        for i in 0..HISTORY_LENGTH + 10 {
+            history.push(i as f32);
+        }
+        assert_eq!(history.samples().count(), HISTORY_LENGTH);
+        assert_eq!(history.samples().next(), Some(10.0));
+        assert_eq!(history.max(), (HISTORY_LENGTH + 9) as f32);
+        assert_eq!(history.last(), history.max());
+    }
+
+    #[test]
+    fn coordinates_and_lines() {
+        let info = DebugInfo {
+            position: Vec3(-0.5, 70.2, 33.0),
+            direction: Vec3(0.2, 0.0, -0.9),
+            looked_at: Some(((-1, 69, 33), "grass".to_string())),
+            ..DebugInfo::default()
+        };
+        assert_eq!(info.block(), (-1, 70, 33));
+        assert_eq!(info.chunk(), ((-1, 2), (15, 70, 1)));
+        assert_eq!(compass(info.direction), "north");
+        let text = info.to_string();
+        assert!(text.contains("Looking at: -1 69 33 (grass)"));
+        assert!(text.contains("Time: day 1, 06:00 (0)"));
+        assert_eq!(text.lines().count(), info.lines().len());
+    }
+}
@@ -0,0 +1,775 @@
+use crate::inventory::{Inventory, ItemStack, MAX_STACK};
+use crate::math::Vec3;
+use crate::object::AIR;
+use crate::pathfinding::{Cell, PathOptions, PathSearch, SearchState};
+use crate::physics::{is_solid, move_aabb, on_ground, on_ladder, Aabb};
+use crate::rng::Rng;
+use crate::save::write_atomic;
+use crate::world::World;
+use std::collections::BTreeMap;
+use std::fs;
+use std::io;
+use std::path::Path;
+
+/// Acceleration downwards in blocks per second squared
+const GRAVITY: f32 = 20.0;
+const MAX_FALL_SPEED: f32 = 40.0;
+/// Entities that fall below this height are removed
+const VOID_DEPTH: f32 = -64.0;
+/// Longest time step of a tick, so entities don't move through blocks after a slow frame
+const MAX_TICK: f32 = 0.05;
+/// Fraction of its horizontal speed an item on the ground keeps per second
+const ITEM_FRICTION: f32 = 0.02;
+/// Seconds until a dropped item disappears
+const ITEM_LIFETIME: f32 = 300.0;
+/// Seconds before a dropped item can be picked up
+const PICKUP_DELAY: f32 = 0.5;
+/// How far beyond the player's box items are picked up
+const PICKUP_REACH: f32 = 0.5;
+/// Distance within which items of the same block type merge into one stack
+const MERGE_DISTANCE: f32 = 0.75;
+/// Fastest horizontal speed of an item dropped from a block
+const DROP_SPREAD: f32 = 1.5;
+/// Seconds until a projectile that got stuck nowhere disappears
+const PROJECTILE_LIFETIME: f32 = 30.0;
+const THROW_SPEED: f32 = 20.0;
+const PROJECTILE_DAMAGE: f32 = 4.0;
+const KNOCKBACK: f32 = 6.0;
+const MOB_HEALTH: f32 = 10.0;
+const MOB_SPEED: f32 = 1.5;
+const MOB_JUMP: f32 = 7.5;
+/// Blocks per second mobs climb ladders
+const CLIMB_SPEED: f32 = 2.5;
+/// Farthest a mob wanders in one go, along x and z
+const WANDER_DISTANCE: i32 = 10;
+/// Nodes all path searches together may expand per tick
+const NODE_BUDGET: usize = 256;
+const MOB_PATH: PathOptions = PathOptions {
+    width: 0.9,
+    height: 0.9,
+    jump_height: 1,
+    max_fall: 3,
+    open_doors: false,
+    climb_ladders: true,
+};
+const MAX_MOBS: usize = 8;
+/// Chance per second that a mob spawns while there are fewer than [`MAX_MOBS`]
+const SPAWN_CHANCE: f32 = 0.2;
+/// Horizontal distance from the player within which mobs spawn and stay
+const SPAWN_DISTANCE: (i32, i32) = (12, 32);
+const DESPAWN_DISTANCE: f32 = 64.0;
+
+pub type EntityId = u64;
+
+/// State of a wandering mob
+#[derive(Debug, Clone, PartialEq)]
+pub struct Mob {
+    pub health: f32,
+    /// Direction the mob walks in, as a yaw in radians
+    pub heading: f32,
+    /// Seconds until the mob decides where to go next
+    wander_time: f32,
+    /// Points of the path the mob is following that it hasn't reached yet
+    pub path: Vec<Vec3>,
+}
+
+impl Mob {
+    pub fn new() -> Mob {
+        Mob {
+            health: MOB_HEALTH,
+            heading: 0.0,
+            wander_time: 0.0,
+            path: vec![],
+        }
+    }
+}
+
+impl Default for Mob {
+    fn default() -> Mob {
+        Mob::new()
+    }
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub enum EntityKind {
+    Item(ItemStack),
+    Projectile,
+    Mob(Mob),
+    /// Block of this id that fell because nothing held it up, see
+    /// [`crate::block_update::gravity_update`]
+    FallingBlock(u8),
+}
+
+/// Something that moves through the world on its own. Velocities are in blocks per second.
+#[derive(Debug, Clone, PartialEq)]
+pub struct Entity {
+    /// Centre of the bottom of the entity
+    pub position: Vec3,
+    pub velocity: Vec3,
+    pub kind: EntityKind,
+    pub on_ground: bool,
+    /// Seconds since the entity was spawned or loaded
+    pub age: f32,
+}
+
+impl Entity {
+    pub fn new(kind: EntityKind, position: Vec3, velocity: Vec3) -> Entity {
+        Entity {
+            position,
+            velocity,
+            kind,
+            on_ground: false,
+            age: 0.0,
+        }
+    }
+
+    /// Returns the width and height of the entity
+    pub fn size(&self) -> (f32, f32) {
+        match self.kind {
+            EntityKind::Item(_) => (0.25, 0.25),
+            EntityKind::Projectile => (0.25, 0.25),
+            EntityKind::Mob(_) => (0.9, 0.9),
+            EntityKind::FallingBlock(_) => (0.98, 0.98),
+        }
+    }
+
+    pub fn aabb(&self) -> Aabb {
+        let (width, height) = self.size();
+        Aabb::standing(self.position, width, height)
+    }
+
+    /// Returns the **chunk position** of the chunk the entity is in
+    pub fn chunk(&self) -> (i32, i32) {
+        (
+            (self.position.0.floor() as i32).div_euclid(16),
+            (self.position.2.floor() as i32).div_euclid(16),
+        )
+    }
+
+    /// Writes the entity as one line of the entity files, `kind x y z vx vy vz` followed by the
+    /// state of its kind
+    fn to_line(&self) -> String {
+        let Vec3(x, y, z) = self.position;
+        let Vec3(vx, vy, vz) = self.velocity;
+        let (kind, state) = match &self.kind {
+            EntityKind::Item(stack) => ("item", format!("{} {}", stack.id, stack.count)),
+            EntityKind::Projectile => ("projectile", format!("{}", self.age)),
+            EntityKind::Mob(mob) => ("mob", format!("{}", mob.health)),
+            EntityKind::FallingBlock(id) => ("falling", format!("{}", id)),
+        };
+        format!(
+            "{} {} {} {} {} {} {} {}\n",
+            kind, x, y, z, vx, vy, vz, state
+        )
+    }
+
+    /// Parses a line written by [`Entity::to_line`]. Items with an invalid block id or count are
+    /// rejected and stacks above [`MAX_STACK`] are cut down.
+    fn from_line(line: &str) -> Option<Entity> {
+        let mut parts = line.split_whitespace();
+        let kind = parts.next()?;
+        let mut number = || parts.next()?.parse::<f32>().ok();
+        let position = Vec3(number()?, number()?, number()?);
+        let velocity = Vec3(number()?, number()?, number()?);
+        let mut entity = Entity::new(EntityKind::Projectile, position, velocity);
+        entity.kind = match kind {
+            "item" => {
+                let (id, count) = (number()?, number()?);
+                if !(0.0..=u8::MAX as f32).contains(&id) || count < 1.0 {
+                    return None;
+                }
+                EntityKind::Item(ItemStack::new(id as u8, (count as u8).min(MAX_STACK)))
+            }
+            "projectile" => {
+                entity.age = number()?;
+                EntityKind::Projectile
+            }
+            "mob" => EntityKind::Mob(Mob {
+                health: number()?,
+                ..Mob::new()
+            }),
+            "falling" => EntityKind::FallingBlock(number()? as u8),
+            _ => return None,
+        };
+        Some(entity)
+    }
+}
+
+/// All entities of a world. They are saved next to the chunk they are in, as
+/// `chunks/<x>.<z>.entities` in the world directory with one entity per line.
+#[derive(Debug)]
+pub struct Entities {
+    entities: BTreeMap<EntityId, Entity>,
+    next_id: EntityId,
+    rng: Rng,
+    /// Paths being searched for mobs, a few nodes per tick
+    searches: BTreeMap<EntityId, PathSearch>,
+}
+
+impl Default for Entities {
+    fn default() -> Entities {
+        Entities::new(0)
+    }
+}
+
+impl Entities {
+    pub fn new(seed: u64) -> Entities {
+        Entities {
+            entities: BTreeMap::new(),
+            next_id: 0,
+            rng: Rng::new(seed),
+            searches: BTreeMap::new(),
+        }
+    }
+
+    pub fn spawn(&mut self, entity: Entity) -> EntityId {
+        let id = self.next_id;
+        self.next_id += 1;
+        self.entities.insert(id, entity);
+        id
+    }
+
+    pub fn get(&self, id: EntityId) -> Option<&Entity> {
+        self.entities.get(&id)
+    }
+
+    pub fn remove(&mut self, id: EntityId) -> Option<Entity> {
+        self.entities.remove(&id)
+    }
+
+    pub fn iter(&self) -> impl Iterator<Item = (EntityId, &Entity)> {
+        self.entities.iter().map(|(id, entity)| (*id, entity))
+    }
+
+    pub fn len(&self) -> usize {
+        self.entities.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.entities.is_empty()
+    }
+
+    /// Throws a projectile from `from` in `direction`
+    pub fn throw(&mut self, from: Vec3, direction: Vec3) -> EntityId {
+        let velocity = direction.normalize().scale(THROW_SPEED);
+        self.spawn(Entity::new(EntityKind::Projectile, from, velocity))
+    }
+
+    /// Spawns the items that breaking the block `id` at `position` drops, as rolled from its drop
+    /// table
+    pub fn drop_block(&mut self, world: &World, (x, y, z): (i32, i32, i32), id: u8) {
+        let Some(object) = world.block_type(id) else {
+            return;
+        };
+        for stack in object.roll_drops(id, &mut self.rng) {
+            let mut spread = || (self.rng.next_f32() * 2.0 - 1.0) * DROP_SPREAD;
+            let velocity = Vec3(spread(), 3.0, spread());
+            let position = Vec3(x as f32 + 0.5, y as f32 + 0.375, z as f32 + 0.5);
+            self.spawn(Entity::new(EntityKind::Item(stack), position, velocity));
+        }
+    }
+
+    /// Moves the items the box `aabb` of the player touches into `inventory`. Items that don't fit
+    /// stay where they are.
+    pub fn pick_up(&mut self, aabb: &Aabb, inventory: &mut Inventory) {
+        let reach = Aabb {
+            min: aabb.min - Vec3(PICKUP_REACH, 0.0, PICKUP_REACH),
+            max: aabb.max + Vec3(PICKUP_REACH, 0.0, PICKUP_REACH),
+        };
+        self.entities.retain(|_, entity| {
+            let touching = entity.age >= PICKUP_DELAY && entity.aabb().intersects(&reach);
+            match &mut entity.kind {
+                EntityKind::Item(stack) if touching => {
+                    stack.count = inventory.add(stack.id, stack.count);
+                    stack.count > 0
+                }
+                _ => true,
+            }
+        });
+    }
+
+    /// Returns the stack of the item entity `id`
+    fn item_mut(&mut self, id: EntityId) -> Option<&mut ItemStack> {
+        match &mut self.entities.get_mut(&id)?.kind {
+            EntityKind::Item(stack) => Some(stack),
+            _ => None,
+        }
+    }
+
+    /// Merges items of the same block type that lie close together into one stack, keeping the
+    /// oldest entity
+    fn merge_items(&mut self) {
+        let items: Vec<_> = self
+            .entities
+            .iter()
+            .filter_map(|(id, entity)| match entity.kind {
+                EntityKind::Item(stack) => Some((*id, stack.id, entity.position)),
+                _ => None,
+            })
+            .collect();
+        for (i, (into, block, position)) in items.iter().enumerate() {
+            for (from, other_block, other_position) in &items[i + 1..] {
+                if block != other_block || (*position - *other_position).len() > MERGE_DISTANCE {
+                    continue;
+                }
+                let (Some(total), Some(moved)) = (
+                    self.item_mut(*into).map(|stack| stack.count),
+                    self.item_mut(*from).map(|stack| stack.count),
+                ) else {
+                    continue;
+                };
+                if total as u32 + moved as u32 <= MAX_STACK as u32 {
+                    self.entities.remove(from);
+                    self.item_mut(*into).unwrap().count += moved;
+                }
+            }
+        }
+    }
+
+    /// Moves all entities in loaded chunks by `dt` seconds, letting them collide with the world
+    /// the same way the player does. Blocks that started falling become entities, and falling
+    /// blocks that land are placed again.
+    pub fn tick(&mut self, world: &mut World, dt: f32) {
+        for ((x, y, z), id) in world.take_falling() {
+            let position = Vec3(x as f32 + 0.5, y as f32, z as f32 + 0.5);
+            self.spawn(Entity::new(
+                EntityKind::FallingBlock(id),
+                position,
+                Vec3(0.0, 0.0, 0.0),
+            ));
+        }
+        let dt = dt.min(MAX_TICK);
+        let mut budget = NODE_BUDGET;
+        let entities = &mut self.entities;
+        self.searches
+            .retain(|id, search| match search.step(world, &mut budget) {
+                SearchState::Searching => true,
+                SearchState::Found(path) => {
+                    if let Some(EntityKind::Mob(mob)) = entities.get_mut(id).map(|e| &mut e.kind) {
+                        mob.path = path.points;
+                    }
+                    false
+                }
+                SearchState::NotFound => false,
+            });
+
+        let mut removed = vec![];
+        let mut hits = vec![];
+        let mut wanders = vec![];
+        let mut broken = vec![];
+        for (id, entity) in self.entities.iter_mut() {
+            let (chunk_x, chunk_z) = entity.chunk();
+            if !world.chunk_exists(chunk_x, chunk_z) {
+                continue;
+            }
+            entity.age += dt;
+            let climbing = on_ladder(world, &entity.aabb());
+            if let EntityKind::Mob(mob) = &mut entity.kind {
+                if let Some(offset) = wander(mob, &mut self.rng, dt) {
+                    wanders.push((*id, entity.position, offset));
+                }
+                follow(
+                    mob,
+                    entity.position,
+                    entity.on_ground,
+                    climbing,
+                    &mut entity.velocity,
+                );
+            }
+            entity.velocity.1 = (entity.velocity.1 - GRAVITY * dt).max(-MAX_FALL_SPEED);
+            let aabb = entity.aabb();
+            let collision = move_aabb(world, &aabb, entity.velocity.scale(dt));
+            entity.position += collision.motion;
+            let (stopped_x, stopped_y, stopped_z) = collision.stopped;
+            if stopped_y {
+                entity.velocity.1 = 0.0;
+            }
+            entity.on_ground = on_ground(world, &entity.aabb());
+            match &entity.kind {
+                EntityKind::Item(_) => {
+                    if entity.on_ground {
+                        let friction = ITEM_FRICTION.powf(dt);
+                        entity.velocity.0 *= friction;
+                        entity.velocity.2 *= friction;
+                    }
+                    if entity.age > ITEM_LIFETIME {
+                        removed.push(*id);
+                    }
+                }
+                EntityKind::Projectile => {
+                    if stopped_x || stopped_y || stopped_z || entity.age > PROJECTILE_LIFETIME {
+                        removed.push(*id);
+                    } else {
+                        hits.push((*id, entity.aabb(), entity.velocity));
+                    }
+                }
+                EntityKind::Mob(_) => {
+                    // jumps over blocks in the way
+                    if (stopped_x || stopped_z) && entity.on_ground {
+                        entity.velocity.1 = MOB_JUMP;
+                    }
+                }
+                EntityKind::FallingBlock(block) => {
+                    // placed right away, so blocks falling right above land on it
+                    if entity.on_ground {
+                        let (x, y, z) = cell(entity.position);
+                        if world.block_id_at(x, y, z) == Some(AIR) {
+                            world.set_block(x, y, z, *block);
+                        } else {
+                            // something like a flower is in the way, so it breaks
+                            broken.push((*block, entity.position));
+                        }
+                        removed.push(*id);
+                    }
+                }
+            }
+            if entity.position.1 < VOID_DEPTH {
+                removed.push(*id);
+            }
+        }
+
+        for (projectile, aabb, velocity) in hits {
+            let target = self.entities.iter_mut().find(|(_, entity)| {
+                matches!(entity.kind, EntityKind::Mob(_)) && entity.aabb().intersects(&aabb)
+            });
+            let Some((id, entity)) = target else {
+                continue;
+            };
+            if let EntityKind::Mob(mob) = &mut entity.kind {
+                mob.health -= PROJECTILE_DAMAGE;
+                if mob.health <= 0.0 {
+                    removed.push(*id);
+                }
+            }
+            let push = Vec3(velocity.0, 0.0, velocity.2)
+                .normalize()
+                .scale(KNOCKBACK);
+            entity.velocity = Vec3(push.0, KNOCKBACK / 2.0, push.2);
+            removed.push(projectile);
+        }
+        for id in removed {
+            self.entities.remove(&id);
+            self.searches.remove(&id);
+        }
+        for (block, position) in broken {
+            let item = EntityKind::Item(ItemStack::new(block, 1));
+            self.spawn(Entity::new(item, position, Vec3(0.0, 0.0, 0.0)));
+        }
+        for (id, position, (dx, dz)) in wanders {
+            let start = cell(position);
+            let goal = (-3..=3)
+                .map(|dy| (start.0 + dx, start.1 + dy, start.2 + dz))
+                .find(|goal| MOB_PATH.can_stand(world, *goal));
+            if let Some(goal) = goal {
+                self.searches
+                    .insert(id, PathSearch::new(start, goal, MOB_PATH));
+            }
+        }
+        self.merge_items();
+    }
+
+    /// Spawns mobs on the ground at random places around `around` and removes the ones that
+    /// wandered too far away
+    pub fn spawn_mobs(&mut self, world: &World, around: Vec3, dt: f32) {
+        self.entities.retain(|_, entity| {
+            let offset = entity.position - around;
+            !matches!(entity.kind, EntityKind::Mob(_))
+                || Vec3(offset.0, 0.0, offset.2).len() < DESPAWN_DISTANCE
+        });
+        let mobs = self
+            .entities
+            .values()
+            .filter(|entity| matches!(entity.kind, EntityKind::Mob(_)))
+            .count();
+        if mobs >= MAX_MOBS || !self.rng.chance(SPAWN_CHANCE * dt) {
+            return;
+        }
+        let angle = self.rng.next_f32() * std::f32::consts::TAU;
+        let distance = self.rng.range(SPAWN_DISTANCE.0, SPAWN_DISTANCE.1) as f32;
+        let x = (around.0 + angle.sin() * distance).floor() as i32;
+        let z = (around.2 + angle.cos() * distance).floor() as i32;
+        if let Some(y) = surface(world, x, z) {
+            let position = Vec3(x as f32 + 0.5, y as f32, z as f32 + 0.5);
+            let mob = Entity::new(EntityKind::Mob(Mob::new()), position, Vec3(0.0, 0.0, 0.0));
+            self.spawn(mob);
+        }
+    }
+
+    /// Writes the entities in the chunk at **chunk position** (x, z), one per line
+    pub fn chunk_to_text(&self, chunk_x: i32, chunk_z: i32) -> String {
+        self.entities
+            .values()
+            .filter(|entity| entity.chunk() == (chunk_x, chunk_z))
+            .map(Entity::to_line)
+            .collect()
+    }
+
+    /// Adds the entities written by [`Entities::chunk_to_text`]. Lines that can't be parsed are
+    /// skipped.
+    pub fn add_text(&mut self, text: &str) {
+        for entity in text.lines().filter_map(Entity::from_line) {
+            self.spawn(entity);
+        }
+    }
+
+    /// Loads the entities saved in the world directory `dir`
+    pub fn load(dir: &Path, seed: u64) -> io::Result<Entities> {
+        let mut entities = Entities::new(seed);
+        let files = match fs::read_dir(dir.join("chunks")) {
+            Ok(files) => files,
+            Err(e) if e.kind() == io::ErrorKind::NotFound => return Ok(entities),
+            Err(e) => return Err(e),
+        };
+        for file in files {
+            let path = file?.path();
+            if path
+                .extension()
+                .is_some_and(|extension| extension == "entities")
+            {
+                entities.add_text(&fs::read_to_string(path)?);
+            }
+        }
+        Ok(entities)
+    }
+
+    /// Saves the entities of every loaded chunk of `world`, removing the files of loaded chunks
+    /// that no longer have any
+    pub fn save(&self, dir: &Path, world: &World) -> io::Result<()> {
+        fs::create_dir_all(dir.join("chunks"))?;
+        for (chunk_x, chunk_z) in world.chunks.keys() {
+            let path = dir
+                .join("chunks")
+                .join(format!("{}.{}.entities", chunk_x, chunk_z));
+            let text = self.chunk_to_text(*chunk_x, *chunk_z);
+            if !text.is_empty() {
+                write_atomic(&path, text)?;
+            } else if let Err(e) = fs::remove_file(path) {
+                if e.kind() != io::ErrorKind::NotFound {
+                    return Err(e);
+                }
+            }
+        }
+        Ok(())
+    }
+}
+
+/// Returns the cell an entity standing at `position` is in
+fn cell(position: Vec3) -> Cell {
+    (
+        position.0.floor() as i32,
+        (position.1 + 0.01).floor() as i32,
+        position.2.floor() as i32,
+    )
+}
+
+/// Makes a mob stand still for a while, then returns how far along x and z it should walk next
+fn wander(mob: &mut Mob, rng: &mut Rng, dt: f32) -> Option<(i32, i32)> {
+    mob.wander_time -= dt;
+    if mob.wander_time > 0.0 {
+        return None;
+    }
+    mob.wander_time = 3.0 + rng.next_f32() * 5.0;
+    mob.path.clear();
+    let mut offset = || rng.range(-WANDER_DISTANCE, WANDER_DISTANCE + 1);
+    Some((offset(), offset()))
+}
+
+/// Steers a mob towards the next point of its path, jumping and climbing where the path goes up
+fn follow(mob: &mut Mob, position: Vec3, on_ground: bool, climbing: bool, velocity: &mut Vec3) {
+    let horizontal = |point: Vec3| Vec3(point.0 - position.0, 0.0, point.2 - position.2);
+    while let Some(next) = mob.path.first() {
+        if horizontal(*next).len() < 0.15 && (next.1 - position.1).abs() < 0.5 {
+            mob.path.remove(0);
+        } else {
+            break;
+        }
+    }
+    // mobs that were knocked back only steer again once they land
+    if !on_ground && !climbing {
+        return;
+    }
+    let Some(next) = mob.path.first() else {
+        velocity.0 = 0.0;
+        velocity.2 = 0.0;
+        return;
+    };
+    let offset = horizontal(*next);
+    if offset.len() > 0.05 {
+        mob.heading = offset.0.atan2(offset.2);
+        let direction = offset.normalize().scale(MOB_SPEED);
+        velocity.0 = direction.0;
+        velocity.2 = direction.2;
+    } else {
+        velocity.0 = 0.0;
+        velocity.2 = 0.0;
+    }
+    if climbing && next.1 > position.1 + 0.05 {
+        velocity.1 = CLIMB_SPEED;
+    }
+}
+
+/// Returns the height of the top of the highest solid block at (x, z) if there is room to stand
+/// on it
+fn surface(world: &World, x: i32, z: i32) -> Option<i32> {
+    let top = (0..256).rev().find(|y| is_solid(world, x, *y, z))?;
+    (!is_solid(world, x, top + 1, z) && !is_solid(world, x, top + 2, z)).then_some(top + 1)
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::entity::{Entities, Entity, EntityKind, Mob};
+    use crate::inventory::{Inventory, ItemStack, MAX_STACK};
+    use crate::math::Vec3;
+    use crate::object::{COBBLESTONE, DIRT, FLOWER, GRAVEL, SAND, STONE};
+    use crate::physics::Aabb;
+    use crate::world::World;
+
+    /// Returns a world with the chunk at the origin, which has a stone floor with its top at y = 1
+    fn floor() -> World {
+        let mut world = World::new();
+        world.create_empty_chunk(0, 0);
+        world
+    }
+
+    #[test]
+    fn entities_fall_and_collide() {
+        let mut world = floor();
+        let mut entities = Entities::new(1);
+        let item = ItemStack::new(STONE, 3);
+        let item = entities.spawn(Entity::new(
+            EntityKind::Item(item),
+            Vec3(4.5, 5.0, 4.5),
+            Vec3(2.0, 0.0, 0.0),
+        ));
+        let mob = entities.spawn(Entity::new(
+            EntityKind::Mob(Mob::new()),
+            Vec3(8.5, 1.0, 8.5),
+            Vec3(0.0, 0.0, 0.0),
+        ));
+        // outside the loaded chunk, so it stays where it is
+        let outside = Vec3(-8.0, 5.0, 4.0);
+        let frozen = entities.spawn(Entity::new(
+            EntityKind::Item(ItemStack::new(STONE, 1)),
+            outside,
+            Vec3(0.0, 0.0, 0.0),
+        ));
+        for _ in 0..100 {
+            entities.tick(&mut world, 0.05);
+        }
+        let item = entities.get(item).unwrap();
+        assert!(item.on_ground);
+        assert!((item.position.1 - 1.0).abs() < 1e-4);
+        assert!(item.position.0 > 4.5 && item.velocity.0.abs() < 0.1);
+        assert_eq!(entities.get(frozen).unwrap().position, outside);
+
+        // a projectile thrown at the mob hurts it and knocks it back
+        let target = entities.get(mob).unwrap().position + Vec3(0.0, 0.5, 0.0);
+        let from = target - Vec3(0.0, 0.0, 3.0);
+        let projectile = entities.throw(from, target - from);
+        for _ in 0..20 {
+            entities.tick(&mut world, 0.01);
+        }
+        assert!(entities.get(projectile).is_none());
+        let EntityKind::Mob(mob) = &entities.get(mob).unwrap().kind else {
+            panic!("not a mob");
+        };
+        assert!(mob.health < 10.0);
+    }
+
+    #[test]
+    fn items_drop_merge_and_are_picked_up() {
+        let mut world = floor();
+        let mut entities = Entities::new(1);
+        entities.drop_block(&world, (10, 1, 10), STONE);
+        let dropped: Vec<_> = entities
+            .iter()
+            .map(|(_, entity)| entity.kind.clone())
+            .collect();
+        assert_eq!(dropped, [EntityKind::Item(ItemStack::new(COBBLESTONE, 1))]);
+
+        let mut spawn = |id, x| {
+            let item = EntityKind::Item(ItemStack::new(id, 1));
+            entities.spawn(Entity::new(item, Vec3(x, 1.0, 4.5), Vec3(0.0, 0.0, 0.0)))
+        };
+        let cobblestone = spawn(COBBLESTONE, 4.5);
+        spawn(COBBLESTONE, 4.8);
+        spawn(DIRT, 4.6);
+        for _ in 0..20 {
+            entities.tick(&mut world, 0.05);
+        }
+        assert_eq!(entities.len(), 3);
+        let merged = &entities.get(cobblestone).unwrap().kind;
+        assert_eq!(*merged, EntityKind::Item(ItemStack::new(COBBLESTONE, 2)));
+
+        let mut inventory = Inventory::default();
+        let player = Aabb::standing(Vec3(4.5, 1.0, 5.0), 0.6, 1.8);
+        entities.pick_up(&player, &mut inventory);
+        assert_eq!(inventory.count(COBBLESTONE), 2);
+        assert_eq!(inventory.count(DIRT), 1);
+        // the block dropped further away is still there
+        assert_eq!(entities.len(), 1);
+    }
+
+    #[test]
+    fn unsupported_blocks_fall() {
+        let mut world = floor();
+        let mut entities = Entities::new(1);
+        world.set_block(4, 4, 4, GRAVEL);
+        world.set_block(4, 5, 4, SAND);
+        world.set_block(8, 1, 8, FLOWER);
+        world.set_block(8, 5, 8, SAND);
+        world.set_block(12, 5, 12, STONE);
+        for _ in 0..60 {
+            world.tick();
+            entities.tick(&mut world, 0.05);
+        }
+        assert_eq!(world.block_id_at(4, 1, 4), Some(GRAVEL));
+        assert_eq!(world.block_id_at(4, 2, 4), Some(SAND));
+        assert_eq!(world.block_id_at(4, 4, 4), Some(0));
+        // the flower is in the way, so the sand drops as an item
+        assert_eq!(world.block_id_at(8, 1, 8), Some(FLOWER));
+        assert_eq!(world.block_id_at(8, 5, 8), Some(0));
+        let left: Vec<_> = entities.iter().map(|(_, e)| e.kind.clone()).collect();
+        assert_eq!(left, [EntityKind::Item(ItemStack::new(SAND, 1))]);
+        assert_eq!(world.block_id_at(12, 5, 12), Some(STONE));
+    }
+
+    #[test]
+    fn text_round_trip() {
+        let mut entities = Entities::new(1);
+        entities.spawn(Entity::new(
+            EntityKind::Item(ItemStack::new(STONE, 12)),
+            Vec3(1.5, 2.0, 3.25),
+            Vec3(0.0, -1.0, 0.5),
+        ));
+        entities.spawn(Entity::new(
+            EntityKind::Mob(Mob::new()),
+            Vec3(-4.5, 70.0, 8.0),
+            Vec3(0.0, 0.0, 0.0),
+        ));
+        entities.throw(Vec3(20.0, 70.0, 3.0), Vec3(1.0, 0.0, 0.0));
+
+        let text = entities.chunk_to_text(0, 0);
+        assert_eq!(text.lines().count(), 1);
+        let mut loaded = Entities::new(2);
+        loaded.add_text(&text);
+        loaded.add_text(&entities.chunk_to_text(-1, 0));
+        loaded.add_text("dragon 1 2 3 0 0 0\nitem 1 2\n");
+        let entity = |entities: &Entities, chunk| {
+            entities
+                .iter()
+                .map(|(_, entity)| entity.clone())
+                .find(|entity| entity.chunk() == chunk)
+                .unwrap()
+        };
+        assert_eq!(loaded.len(), 2);
+        assert_eq!(entity(&loaded, (0, 0)), entity(&entities, (0, 0)));
+        assert_eq!(entity(&loaded, (-1, 0)), entity(&entities, (-1, 0)));
+
+        let mut loaded = Entities::new(3);
+        loaded.add_text("item 1 2 3 0 0 0 300 1\nitem 1 2 3 0 0 0 1 -1\nitem 1 2 3 0 0 0 1 1000\n");
+        assert_eq!(loaded.len(), 1);
+        let (_, entity) = loaded.iter().next().unwrap();
+        assert_eq!(entity.kind, EntityKind::Item(ItemStack::new(1, MAX_STACK)));
+    }
+}
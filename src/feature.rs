@@ -0,0 +1,174 @@
+use crate::object::{
+    AIR, COAL_ORE, DIAMOND_ORE, FLOWER, GOLD_ORE, GRASS, IRON_ORE, LEAVES, LOG, STONE,
+};
+use crate::rng::Rng;
+use crate::world_gen::ProtoChunk;
+
+/// The furthest a feature may reach outside of the chunk it was planned in, in blocks.
+/// Population only looks at the direct neighbours of a chunk, so this has to stay below 16.
+pub const MAX_FEATURE_REACH: i32 = 8;
+
+/// How the height of ore veins is distributed
+#[derive(Debug, Clone, Copy)]
+pub enum Depth {
+    /// Every height in [min, max) is equally likely
+    Uniform { min: i32, max: i32 },
+    /// Heights around `center` are the most likely, falling off linearly to `center ± spread`
+    Triangle { center: i32, spread: i32 },
+}
+
+impl Depth {
+    pub fn sample(&self, rng: &mut Rng) -> i32 {
+        match *self {
+            Depth::Uniform { min, max } => rng.range(min, max),
+            Depth::Triangle { center, spread } => {
+                center - spread + rng.range(0, spread + 1) + rng.range(0, spread + 1)
+            }
+        }
+    }
+}
+
+#[derive(Debug, Clone, Copy)]
+pub struct OreConfig {
+    pub block: u8,
+    pub depth: Depth,
+    pub veins_per_chunk: i32,
+    pub vein_size: i32,
+}
+
+pub const ORES: [OreConfig; 4] = [
+    OreConfig {
+        block: COAL_ORE,
+        depth: Depth::Uniform { min: 5, max: 80 },
+        veins_per_chunk: 16,
+        vein_size: 8,
+    },
+    OreConfig {
+        block: IRON_ORE,
+        depth: Depth::Triangle {
+            center: 32,
+            spread: 24,
+        },
+        veins_per_chunk: 10,
+        vein_size: 6,
+    },
+    OreConfig {
+        block: GOLD_ORE,
+        depth: Depth::Uniform { min: 2, max: 32 },
+        veins_per_chunk: 3,
+        vein_size: 6,
+    },
+    OreConfig {
+        block: DIAMOND_ORE,
+        depth: Depth::Uniform { min: 2, max: 16 },
+        veins_per_chunk: 1,
+        vein_size: 4,
+    },
+];
+
+/// A single decoration, planned in world coordinates
+#[derive(Debug, Clone, PartialEq)]
+pub enum Feature {
+    OreVein {
+        block: u8,
+        positions: Vec<(i32, i32, i32)>,
+    },
+    Boulder {
+        x: i32,
+        y: i32,
+        z: i32,
+        radius: i32,
+    },
+    Tree {
+        x: i32,
+        y: i32,
+        z: i32,
+        height: i32,
+    },
+    Flower {
+        x: i32,
+        y: i32,
+        z: i32,
+    },
+}
+
+impl Feature {
+    /// Plans an ore vein as a random walk starting at (x, y, z)
+    pub fn ore_vein(ore: &OreConfig, x: i32, y: i32, z: i32, rng: &mut Rng) -> Feature {
+        let mut positions = Vec::with_capacity(ore.vein_size as usize);
+        let (mut vx, mut vy, mut vz) = (x, y, z);
+        for _ in 0..ore.vein_size.min(MAX_FEATURE_REACH) {
+            positions.push((vx, vy, vz));
+            let step = if rng.chance(0.5) { 1 } else { -1 };
+            match rng.range(0, 3) {
+                0 => vx += step,
+                1 => vy += step,
+                _ => vz += step,
+            }
+        }
+        Feature::OreVein {
+            block: ore.block,
+            positions,
+        }
+    }
+
+    /// Features of earlier phases are placed first, regardless of which chunk they come from
+    pub fn phase(&self) -> u8 {
+        match self {
+            Feature::OreVein { .. } => 0,
+            Feature::Boulder { .. } => 1,
+            Feature::Tree { .. } => 2,
+            Feature::Flower { .. } => 3,
+        }
+    }
+
+    /// Writes the part of this feature that lies inside `chunk`
+    pub fn place(&self, chunk: &mut ProtoChunk) {
+        match *self {
+            Feature::OreVein {
+                block,
+                ref positions,
+            } => {
+                for &(x, y, z) in positions {
+                    chunk.set(x, y, z, block, |old| old == STONE);
+                }
+            }
+            Feature::Boulder { x, y, z, radius } => {
+                for dx in -radius..=radius {
+                    for dy in -radius..=radius {
+                        for dz in -radius..=radius {
+                            if dx * dx + dy * dy + dz * dz <= radius * radius {
+                                chunk.set(x + dx, y + dy, z + dz, STONE, |old| {
+                                    old == AIR || old == FLOWER
+                                });
+                            }
+                        }
+                    }
+                }
+            }
+            Feature::Tree { x, y, z, height } => {
+                for dy in height - 2..=height + 1 {
+                    let radius: i32 = if dy > height { 1 } else { 2 };
+                    for dx in -radius..=radius {
+                        for dz in -radius..=radius {
+                            if radius == 2 && dx.abs() == 2 && dz.abs() == 2 {
+                                continue;
+                            }
+                            chunk.set(x + dx, y + dy, z + dz, LEAVES, |old| old == AIR);
+                        }
+                    }
+                }
+                for dy in 0..height {
+                    chunk.set(x, y + dy, z, LOG, |old| {
+                        old == AIR || old == LEAVES || old == FLOWER
+                    });
+                }
+            }
+            Feature::Flower { x, y, z } => {
+                if chunk.get(x, y - 1, z) == Some(GRASS) {
+                    chunk.set(x, y, z, FLOWER, |old| old == AIR);
+                }
+            }
+        }
+    }
+}
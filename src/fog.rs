@@ -0,0 +1,130 @@
+/// Fraction of the fog that is left at the view distance with exponential fog, so the world has
+/// almost faded into the sky where it is clipped
+const FAR_VISIBILITY: f32 = 0.02;
+/// Height around which height fog is thickest, the base height of the terrain
+pub const HEIGHT_FOG_BASE: f32 = 64.0;
+/// Blocks above [`HEIGHT_FOG_BASE`] over which the extra thickness of height fog fades out
+pub const HEIGHT_FOG_FALLOFF: f32 = 16.0;
+/// How much thicker height fog is at [`HEIGHT_FOG_BASE`] and below than exponential fog
+pub const HEIGHT_FOG_THICKNESS: f32 = 3.0;
+
+/// How the world fades into the sky with distance
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum FogMode {
+    Off,
+    /// Starts at a fraction of the view distance and grows evenly until the view distance
+    Linear,
+    /// Grows with the square of the distance, so it stays thin nearby and thickens quickly
+    Exponential,
+    /// Exponential fog that is thicker in valleys and thinner on mountain tops
+    Height,
+}
+
+impl FogMode {
+    pub const ALL: [FogMode; 4] = [
+        FogMode::Off,
+        FogMode::Linear,
+        FogMode::Exponential,
+        FogMode::Height,
+    ];
+
+    pub fn name(&self) -> &'static str {
+        match self {
+            FogMode::Off => "off",
+            FogMode::Linear => "linear",
+            FogMode::Exponential => "exponential",
+            FogMode::Height => "height",
+        }
+    }
+
+    pub fn from_name(name: &str) -> Option<FogMode> {
+        FogMode::ALL.into_iter().find(|mode| mode.name() == name)
+    }
+
+    /// Returns the mode after this one in [`FogMode::ALL`], going back to the first after the
+    /// last
+    pub fn cycle(&self) -> FogMode {
+        let index = FogMode::ALL.iter().position(|mode| mode == self).unwrap();
+        FogMode::ALL[(index + 1) % FogMode::ALL.len()]
+    }
+}
+
+/// Fog for a view distance, passed to the chunk shader as uniforms
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct Fog {
+    pub mode: FogMode,
+    /// Distance at which linear fog starts
+    pub start: f32,
+    /// Distance at which linear fog hides everything, the view distance
+    pub end: f32,
+    /// Density of exponential and height fog
+    pub density: f32,
+}
+
+impl Fog {
+    /// Returns the fog for the view distance `view_distance`, where linear fog starts at
+    /// `start` times the view distance
+    pub fn new(mode: FogMode, start: f32, view_distance: f32) -> Fog {
+        Fog {
+            mode,
+            start: start * view_distance,
+            end: view_distance,
+            density: (-FAR_VISIBILITY.ln()).sqrt() / view_distance,
+        }
+    }
+
+    /// Returns how much of the colour of something `distance` away at height `y` is replaced by
+    /// the sky colour, from 0 to 1. The chunk shader does the same.
+    pub fn amount(&self, distance: f32, y: f32) -> f32 {
+        let exponential = |density: f32| 1.0 - (-(distance * density).powi(2)).exp();
+        match self.mode {
+            FogMode::Off => 0.0,
+            FogMode::Linear => ((distance - self.start) / (self.end - self.start)).clamp(0.0, 1.0),
+            FogMode::Exponential => exponential(self.density),
+            FogMode::Height => {
+                let above = (y - HEIGHT_FOG_BASE).max(0.0);
+                let thickness = 1.0 + HEIGHT_FOG_THICKNESS * (-above / HEIGHT_FOG_FALLOFF).exp();
+                exponential(self.density * thickness)
+            }
+        }
+    }
+
+    /// Returns the number the chunk shader uses for the mode
+    pub fn shader_mode(&self) -> i32 {
+        self.mode as i32
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::fog::{Fog, FogMode, FAR_VISIBILITY};
+
+    #[test]
+    fn fog_follows_view_distance() {
+        let off = Fog::new(FogMode::Off, 0.5, 200.0);
+        assert_eq!(off.amount(500.0, 0.0), 0.0);
+
+        let linear = Fog::new(FogMode::Linear, 0.5, 200.0);
+        assert_eq!(linear.amount(50.0, 70.0), 0.0);
+        assert_eq!(linear.amount(150.0, 70.0), 0.5);
+        assert_eq!(linear.amount(300.0, 70.0), 1.0);
+
+        for distance in [100.0, 1000.0] {
+            let exponential = Fog::new(FogMode::Exponential, 0.5, distance);
+            let far = exponential.amount(distance, 70.0);
+            assert!((far - (1.0 - FAR_VISIBILITY)).abs() < 1e-4);
+            assert!(exponential.amount(distance / 4.0, 70.0) < 0.25);
+        }
+
+        // thicker in a valley than on a mountain, where it is like exponential fog
+        let height = Fog::new(FogMode::Height, 0.5, 200.0);
+        let valley = height.amount(50.0, 60.0);
+        let mountain = height.amount(50.0, 200.0);
+        assert!(valley > mountain * 2.0);
+        let exponential = Fog::new(FogMode::Exponential, 0.5, 200.0);
+        assert!((mountain - exponential.amount(50.0, 200.0)).abs() < 1e-3);
+
+        assert_eq!(FogMode::from_name("height"), Some(FogMode::Height));
+        assert_eq!(FogMode::Height.cycle(), FogMode::Off);
+    }
+}
@@ -0,0 +1,165 @@
+use crate::sprite_batch::{Rect, SpriteBatch, TextureId};
+use font8x8::legacy::BASIC_LEGACY;
+use glium::texture::RawImage2d;
+use glium::{Display, Texture2d};
+use image::{Rgba, RgbaImage};
+
+/// Size of a glyph in the font, in pixels
+pub const GLYPH_SIZE: u32 = 8;
+/// Number of glyphs per row of the atlas
+const ATLAS_COLUMNS: u32 = 16;
+const ATLAS_ROWS: u32 = 128 / ATLAS_COLUMNS;
+/// Extra space between lines, in font pixels
+const LINE_GAP: u32 = 2;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Align {
+    Left,
+    Centre,
+    Right,
+}
+
+/// Draws the 128 ASCII glyphs of the font white on transparent into one image, 16 glyphs per row
+/// starting with code 0 at the top left
+pub fn atlas_image() -> RgbaImage {
+    let mut image = RgbaImage::new(ATLAS_COLUMNS * GLYPH_SIZE, ATLAS_ROWS * GLYPH_SIZE);
+    for (code, glyph) in BASIC_LEGACY.iter().enumerate() {
+        let left = code as u32 % ATLAS_COLUMNS * GLYPH_SIZE;
+        let top = code as u32 / ATLAS_COLUMNS * GLYPH_SIZE;
+        for (y, row) in glyph.iter().enumerate() {
+            for x in 0..GLYPH_SIZE {
+                // the lowest bit is the leftmost pixel
+                if row & (1 << x) != 0 {
+                    image.put_pixel(left + x, top + y as u32, Rgba([255; 4]));
+                }
+            }
+        }
+    }
+    image
+}
+
+/// Returns the atlas rectangle of a character, in texture coordinates of an atlas uploaded
+/// top row first. Characters outside of ASCII show as '?'.
+fn glyph_uv(c: char) -> Rect {
+    let code = if c.is_ascii() { c as u32 } else { '?' as u32 };
+    let (w, h) = (1.0 / ATLAS_COLUMNS as f32, 1.0 / ATLAS_ROWS as f32);
+    let left = (code % ATLAS_COLUMNS) as f32 * w;
+    // the first row of the image is at texture coordinate 0, so glyphs are upside down in
+    // texture space and the top of a glyph is its smaller v
+    let top = (code / ATLAS_COLUMNS) as f32 * h;
+    ([left, top + h], [left + w, top])
+}
+
+/// A glyph placed on screen
+#[derive(Debug, Clone, PartialEq)]
+pub struct PlacedGlyph {
+    pub rect: Rect,
+    pub uv: Rect,
+}
+
+/// Fixed width bitmap font for the HUD and menus
+pub struct Font {
+    texture: TextureId,
+}
+
+impl Font {
+    /// Uploads the glyph atlas and registers it with the batch
+    pub fn new(display: &Display, batch: &mut SpriteBatch) -> Font {
+        let image = atlas_image();
+        let dimensions = image.dimensions();
+        let raw = RawImage2d::from_raw_rgba(image.into_raw(), dimensions);
+        Font {
+            texture: batch.add_texture(Texture2d::new(display, raw).unwrap()),
+        }
+    }
+
+    /// Returns the width and height of the text in pixels when drawn at `scale` screen pixels per
+    /// font pixel
+    pub fn measure(text: &str, scale: f32) -> (f32, f32) {
+        let columns = text.lines().map(|line| line.chars().count()).max();
+        let lines = text.lines().count().max(1);
+        let width = columns.unwrap_or(0) as f32 * GLYPH_SIZE as f32;
+        let height = lines as f32 * line_height() - LINE_GAP as f32;
+        (width * scale, height * scale)
+    }
+
+    /// Places the glyphs of the text. `position` is the top of the first line, on its left end,
+    /// centre or right end depending on `align`. Lines are aligned separately.
+    pub fn layout(text: &str, position: [f32; 2], scale: f32, align: Align) -> Vec<PlacedGlyph> {
+        let size = GLYPH_SIZE as f32 * scale;
+        let mut glyphs = vec![];
+        for (index, line) in text.lines().enumerate() {
+            let width = line.chars().count() as f32 * size;
+            let left = match align {
+                Align::Left => position[0],
+                Align::Centre => position[0] - width / 2.0,
+                Align::Right => position[0] - width,
+            };
+            let top = position[1] - index as f32 * line_height() * scale;
+            for (column, c) in line.chars().enumerate() {
+                if c == ' ' {
+                    continue;
+                }
+                let x = left + column as f32 * size;
+                glyphs.push(PlacedGlyph {
+                    rect: ([x, top - size], [x + size, top]),
+                    uv: glyph_uv(c),
+                });
+            }
+        }
+        glyphs
+    }
+
+    /// Adds the text to the batch, see [`Font::layout`]
+    pub fn draw(
+        &self,
+        batch: &mut SpriteBatch,
+        text: &str,
+        position: [f32; 2],
+        scale: f32,
+        align: Align,
+        colour: [f32; 4],
+    ) {
+        for glyph in Font::layout(text, position, scale, align) {
+            batch.rect(self.texture, glyph.rect, glyph.uv, colour);
+        }
+    }
+}
+
+fn line_height() -> f32 {
+    (GLYPH_SIZE + LINE_GAP) as f32
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::font::{atlas_image, Align, Font};
+
+    #[test]
+    fn atlas_has_glyphs() {
+        let atlas = atlas_image();
+        assert_eq!(atlas.dimensions(), (128, 64));
+        let lit = |code: u32| {
+            let (left, top) = (code % 16 * 8, code / 16 * 8);
+            (0..64)
+                .filter(|i| atlas.get_pixel(left + i % 8, top + i / 8).0[3] > 0)
+                .count()
+        };
+        assert_eq!(lit(' ' as u32), 0);
+        assert!(lit('A' as u32) > 10);
+    }
+
+    #[test]
+    fn alignment() {
+        assert_eq!(Font::measure("ab\nc", 2.0), (32.0, 36.0));
+        let left = Font::layout("ab", [100.0, 50.0], 2.0, Align::Left);
+        assert_eq!(left[0].rect, ([100.0, 34.0], [116.0, 50.0]));
+        let centre = Font::layout("ab", [100.0, 50.0], 2.0, Align::Centre);
+        assert_eq!(centre[0].rect.0[0], 84.0);
+        let right = Font::layout("ab\nc", [100.0, 50.0], 1.0, Align::Right);
+        assert_eq!(right[1].rect.1[0], 100.0);
+        // the second line is one line height lower
+        assert_eq!(right[2].rect, ([92.0, 32.0], [100.0, 40.0]));
+        // spaces take room but have no quad
+        assert_eq!(Font::layout("a b", [0.0, 0.0], 1.0, Align::Left).len(), 2);
+    }
+}
@@ -3,10 +3,38 @@
 in vec3 v_normal;
 in vec3 v_position;
 in vec2 v_tex_coords;
+in vec3 v_world_position;
+in vec3 v_world_normal;
 
 out vec4 color;
 
+// direction towards the sun or moon in world space, and how bright its light is
 uniform vec3 u_light;
+uniform float u_skylight;
+uniform mat4 view;
+uniform vec3 camera;
+
+// see shadow.rs: the shadow maps of the cascades, which end at the distances in
+// cascade_ends and have texels of cascade_texels blocks. No cascades turns shadows off.
+uniform sampler2DArrayShadow shadow_maps;
+uniform mat4 light_matrices[4];
+uniform vec4 cascade_ends;
+uniform vec4 cascade_texels;
+uniform int cascade_count;
+
+// see fog.rs: 0 is off, 1 linear, 2 exponential and 3 height fog
+uniform int fog_mode;
+uniform float fog_start;
+uniform float fog_end;
+uniform float fog_density;
+uniform float height_fog_base;
+uniform float height_fog_falloff;
+uniform float height_fog_thickness;
+// colour of the sky at the horizon, which the fog fades to
+uniform vec3 horizon;
+uniform vec3 sun;
+// colour of the sunlight scattered in the air, black without scattering
+uniform vec3 scattering;
 
 uniform sampler2D diffuse_tex;
 uniform sampler2D normal_tex;
@@ -28,6 +56,50 @@ mat3 cotangent_frame(vec3 normal, vec3 pos, vec2 uv) {
     return mat3(T * invmax, B * invmax, normal);
 }
 
+// same as in sky_fragment.glsl, so the fog matches the sky behind it
+vec3 scatter(vec3 direction) {
+    float to_sun = max(dot(direction, sun), 0.0);
+    return scattering * (pow(to_sun, 8.0) * 0.5 + to_sun * to_sun * 0.15);
+}
+
+// returns how much of the light reaches the fragment, from 0 in shadow to 1
+float shadow(float depth) {
+    for (int i = 0; i < cascade_count; i++) {
+        if (depth < cascade_ends[i]) {
+            // moved out of the surface, so it doesn't shadow itself
+            vec3 offset = normalize(v_world_normal) * cascade_texels[i] * 1.5;
+            vec4 light_position = light_matrices[i] * vec4(v_world_position + offset, 1.0);
+            vec3 coords = light_position.xyz / light_position.w * 0.5 + 0.5;
+            vec2 texel = 1.0 / vec2(textureSize(shadow_maps, 0).xy);
+            // percentage closer filtering over 3 by 3 texels, each of which is filtered again
+            float lit = 0.0;
+            for (int x = -1; x <= 1; x++) {
+                for (int y = -1; y <= 1; y++) {
+                    vec2 at = coords.xy + vec2(x, y) * texel;
+                    lit += texture(shadow_maps, vec4(at, float(i), coords.z - 0.0005));
+                }
+            }
+            return lit / 9.0;
+        }
+    }
+    return 1.0;
+}
+
+float fog_amount(float distance, float height) {
+    if (fog_mode == 1) {
+        return clamp((distance - fog_start) / (fog_end - fog_start), 0.0, 1.0);
+    }
+    float density = fog_density;
+    if (fog_mode == 3) {
+        float above = max(height - height_fog_base, 0.0);
+        density *= 1.0 + height_fog_thickness * exp(-above / height_fog_falloff);
+    }
+    if (fog_mode >= 2) {
+        return 1.0 - exp(-pow(distance * density, 2.0));
+    }
+    return 0.0;
+}
+
 void main() {
     vec3 diffuse_color = texture(diffuse_tex, v_tex_coords).rgb;
     vec3 ambient_color = diffuse_color * 0.1;
@@ -35,11 +107,21 @@ void main() {
     vec3 v_normal_unit = normalize(v_normal);
     vec3 normal_map = texture(normal_tex, v_tex_coords).rgb;
     mat3 tbn = cotangent_frame(v_normal, -v_position, v_tex_coords);
-    vec3 real_normal = normalize(tbn * -(normal_map* 2.0 - 1.0));
-    float diffuse = max(dot(real_normal, normalize(u_light)), 0.0);
+    vec3 real_normal = normalize(tbn * (normal_map* 2.0 - 1.0));
+    // the normals are in view space
+    vec3 light = normalize(mat3(view) * u_light);
+    float diffuse = max(dot(real_normal, light), 0.0);
     vec3 camera_dir = normalize(-v_position);
-    vec3 half_direction = normalize(normalize(u_light) + camera_dir);
+    vec3 half_direction = normalize(light + camera_dir);
     float specular = pow(max(dot(half_direction, real_normal), 0.0), 16.0);
 
-    color = vec4(ambient_color + diffuse * diffuse_color + specular * specular_color, 1.0);
+    // the view looks along +z in view space
+    float depth = (view * vec4(v_world_position, 1.0)).z;
+    float light_amount = u_skylight * shadow(depth);
+    vec3 lit = ambient_color + (diffuse * diffuse_color + specular * specular_color) * light_amount;
+
+    vec3 to_fragment = v_world_position - camera;
+    vec3 fog_color = horizon + scatter(normalize(to_fragment));
+    float fog = fog_amount(length(to_fragment), v_world_position.y);
+    color = vec4(mix(lit, fog_color, fog), 1.0);
 }
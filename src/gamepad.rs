@@ -0,0 +1,249 @@
+use crate::input::{GamepadButton, Input, InputState, Stick, StickDirection};
+
+/// How gamepad sticks and triggers respond, from the `[gamepad]` section of the settings
+#[derive(Debug, Clone, PartialEq)]
+pub struct GamepadSettings {
+    /// Distance from the centre, from 0 to 1, within which a stick counts as released
+    pub deadzone: f32,
+    /// Exponent applied to the stick distance outside the deadzone. Higher values give finer
+    /// control near the centre.
+    pub response_curve: f32,
+    /// Radians per second the view turns with the look stick pushed fully
+    pub look_speed: f32,
+    /// How far a trigger has to be pulled, from 0 to 1, before it counts as pressed
+    pub trigger_threshold: f32,
+}
+
+impl Default for GamepadSettings {
+    fn default() -> GamepadSettings {
+        GamepadSettings {
+            deadzone: 0.15,
+            response_curve: 2.0,
+            look_speed: 3.0,
+            trigger_threshold: 0.5,
+        }
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum GamepadAxis {
+    LeftStickX,
+    LeftStickY,
+    RightStickX,
+    RightStickY,
+    LeftTrigger,
+    RightTrigger,
+}
+
+/// Something that happened on a gamepad. Stick axes go from -1 to 1 with up and right being
+/// positive, and triggers from 0 to 1.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum GamepadEvent {
+    ButtonPressed(GamepadButton),
+    ButtonReleased(GamepadButton),
+    AxisChanged(GamepadAxis, f32),
+    Disconnected,
+}
+
+/// Applies the deadzone and response curve to a stick position. The distance from the centre
+/// outside the deadzone is rescaled to go from 0 to 1 and raised to the power of the curve, while
+/// the direction is kept.
+pub fn shape_stick((x, y): (f32, f32), deadzone: f32, curve: f32) -> (f32, f32) {
+    let length = (x * x + y * y).sqrt();
+    if length <= deadzone {
+        return (0.0, 0.0);
+    }
+    let shaped = ((length - deadzone) / (1.0 - deadzone))
+        .min(1.0)
+        .powf(curve);
+    (x / length * shaped, y / length * shaped)
+}
+
+/// Turns gamepad events into inputs. Sticks become one analog input per direction and triggers
+/// analog buttons, so they can be bound to actions like keys.
+#[derive(Debug, Default)]
+pub struct Gamepad {
+    /// Raw positions of the left and right sticks
+    sticks: [(f32, f32); 2],
+}
+
+impl Gamepad {
+    fn trigger(
+        button: GamepadButton,
+        value: f32,
+        settings: &GamepadSettings,
+        input: &mut InputState,
+    ) {
+        let pulled = value >= settings.trigger_threshold;
+        input.set_analog(Input::Gamepad(button), if pulled { value } else { 0.0 });
+    }
+
+    pub fn handle(
+        &mut self,
+        event: GamepadEvent,
+        settings: &GamepadSettings,
+        input: &mut InputState,
+    ) {
+        match event {
+            GamepadEvent::ButtonPressed(button) => input.press(Input::Gamepad(button)),
+            GamepadEvent::ButtonReleased(button) => input.release(Input::Gamepad(button)),
+            GamepadEvent::AxisChanged(GamepadAxis::LeftTrigger, value) => {
+                Gamepad::trigger(GamepadButton::LeftTrigger, value, settings, input)
+            }
+            GamepadEvent::AxisChanged(GamepadAxis::RightTrigger, value) => {
+                Gamepad::trigger(GamepadButton::RightTrigger, value, settings, input)
+            }
+            GamepadEvent::AxisChanged(axis, value) => {
+                let (stick, index) = match axis {
+                    GamepadAxis::LeftStickX | GamepadAxis::LeftStickY => (Stick::Left, 0),
+                    _ => (Stick::Right, 1),
+                };
+                match axis {
+                    GamepadAxis::LeftStickX | GamepadAxis::RightStickX => {
+                        self.sticks[index].0 = value
+                    }
+                    _ => self.sticks[index].1 = value,
+                }
+                let (x, y) = shape_stick(
+                    self.sticks[index],
+                    settings.deadzone,
+                    settings.response_curve,
+                );
+                input.set_analog(Input::Stick(stick, StickDirection::Up), y.max(0.0));
+                input.set_analog(Input::Stick(stick, StickDirection::Down), (-y).max(0.0));
+                input.set_analog(Input::Stick(stick, StickDirection::Left), (-x).max(0.0));
+                input.set_analog(Input::Stick(stick, StickDirection::Right), x.max(0.0));
+            }
+            GamepadEvent::Disconnected => {
+                self.sticks = Default::default();
+                input.release_gamepad();
+            }
+        }
+    }
+}
+
+/// Reads gamepad events from the operating system through gilrs
+#[cfg(feature = "gamepad")]
+pub struct GamepadBackend {
+    gilrs: gilrs::Gilrs,
+}
+
+#[cfg(feature = "gamepad")]
+impl GamepadBackend {
+    pub fn new() -> Result<GamepadBackend, String> {
+        let gilrs = gilrs::Gilrs::new().map_err(|e| e.to_string())?;
+        Ok(GamepadBackend { gilrs })
+    }
+
+    fn button(button: gilrs::Button) -> Option<GamepadButton> {
+        use gilrs::Button;
+        Some(match button {
+            Button::South => GamepadButton::South,
+            Button::East => GamepadButton::East,
+            Button::North => GamepadButton::North,
+            Button::West => GamepadButton::West,
+            Button::LeftTrigger => GamepadButton::LeftBumper,
+            Button::RightTrigger => GamepadButton::RightBumper,
+            Button::Select => GamepadButton::Select,
+            Button::Start => GamepadButton::Start,
+            Button::LeftThumb => GamepadButton::LeftStick,
+            Button::RightThumb => GamepadButton::RightStick,
+            Button::DPadUp => GamepadButton::DPadUp,
+            Button::DPadDown => GamepadButton::DPadDown,
+            Button::DPadLeft => GamepadButton::DPadLeft,
+            Button::DPadRight => GamepadButton::DPadRight,
+            _ => return None,
+        })
+    }
+
+    /// Returns the events since the last call
+    pub fn poll(&mut self) -> Vec<GamepadEvent> {
+        use gilrs::{Axis, Button, EventType};
+        let mut events = vec![];
+        while let Some(event) = self.gilrs.next_event() {
+            let event = match event.event {
+                // triggers are read from how far they are pulled
+                EventType::ButtonChanged(Button::LeftTrigger2, value, _) => {
+                    GamepadEvent::AxisChanged(GamepadAxis::LeftTrigger, value)
+                }
+                EventType::ButtonChanged(Button::RightTrigger2, value, _) => {
+                    GamepadEvent::AxisChanged(GamepadAxis::RightTrigger, value)
+                }
+                EventType::ButtonPressed(button, _) => match GamepadBackend::button(button) {
+                    Some(button) => GamepadEvent::ButtonPressed(button),
+                    None => continue,
+                },
+                EventType::ButtonReleased(button, _) => match GamepadBackend::button(button) {
+                    Some(button) => GamepadEvent::ButtonReleased(button),
+                    None => continue,
+                },
+                EventType::AxisChanged(axis, value, _) => {
+                    let axis = match axis {
+                        Axis::LeftStickX => GamepadAxis::LeftStickX,
+                        Axis::LeftStickY => GamepadAxis::LeftStickY,
+                        Axis::RightStickX => GamepadAxis::RightStickX,
+                        Axis::RightStickY => GamepadAxis::RightStickY,
+                        _ => continue,
+                    };
+                    GamepadEvent::AxisChanged(axis, value)
+                }
+                EventType::Disconnected => GamepadEvent::Disconnected,
+                _ => continue,
+            };
+            events.push(event);
+        }
+        events
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::gamepad::{shape_stick, Gamepad, GamepadAxis, GamepadEvent, GamepadSettings};
+    use crate::input::{Action, Bindings, GamepadButton, InputState};
+
+    #[test]
+    fn deadzone_and_curve() {
+        assert_eq!(shape_stick((0.1, 0.05), 0.15, 2.0), (0.0, 0.0));
+        assert_eq!(shape_stick((0.0, -1.0), 0.15, 2.0), (0.0, -1.0));
+        // halfway between the deadzone and the edge, squared
+        let (x, y) = shape_stick((0.575, 0.0), 0.15, 2.0);
+        assert!((x - 0.25).abs() < 1e-5 && y == 0.0);
+        // the direction is kept
+        let (x, y) = shape_stick((0.6, 0.6), 0.15, 1.0);
+        assert!((x - y).abs() < 1e-6 && x > 0.0);
+    }
+
+    #[test]
+    fn events_drive_actions() {
+        let settings = GamepadSettings::default();
+        let bindings = Bindings::default();
+        let mut gamepad = Gamepad::default();
+        let mut input = InputState::default();
+        let mut send = |event| gamepad.handle(event, &settings, &mut input);
+
+        send(GamepadEvent::AxisChanged(GamepadAxis::LeftStickY, 0.1));
+        send(GamepadEvent::AxisChanged(GamepadAxis::RightTrigger, 0.3));
+        let actions = input.take_actions(&bindings);
+        assert!(!actions.held(Action::MoveForward));
+        assert!(!actions.held(Action::Attack));
+
+        let mut send = |event| gamepad.handle(event, &settings, &mut input);
+        send(GamepadEvent::AxisChanged(GamepadAxis::LeftStickY, 1.0));
+        send(GamepadEvent::AxisChanged(GamepadAxis::RightStickX, -0.5));
+        send(GamepadEvent::AxisChanged(GamepadAxis::RightTrigger, 0.8));
+        send(GamepadEvent::ButtonPressed(GamepadButton::South));
+        let actions = input.take_actions(&bindings);
+        assert_eq!(actions.strength(Action::MoveForward), 1.0);
+        assert!(!actions.held(Action::MoveBack));
+        let look = actions.strength(Action::LookLeft);
+        assert!(look > 0.0 && look < 0.5);
+        assert!(actions.pressed(Action::Attack));
+        assert!(actions.pressed(Action::Jump));
+
+        gamepad.handle(GamepadEvent::Disconnected, &settings, &mut input);
+        let actions = input.take_actions(&bindings);
+        assert!(!actions.held(Action::MoveForward));
+        assert!(!actions.held(Action::Attack));
+        assert!(!actions.held(Action::Jump));
+    }
+}
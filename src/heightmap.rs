@@ -0,0 +1,206 @@
+use crate::object::{Movement, DIRT, GRASS, STONE};
+use crate::world::World;
+use crate::world_gen::{Generator, WorldGen};
+use image::{GrayImage, RgbImage};
+use std::fmt::{Display, Formatter};
+use std::path::Path;
+
+/// Number of blocks below the surface block that use the surface material (or dirt under grass)
+const TOPSOIL_DEPTH: i32 = 3;
+
+#[derive(Debug)]
+pub enum ImportError {
+    Image(image::ImageError),
+    /// The colour map doesn't have the same dimensions as the heightmap
+    SizeMismatch {
+        heightmap: (u32, u32),
+        colour_map: (u32, u32),
+    },
+    /// The palette has no colours to match against
+    EmptyPalette,
+}
+
+impl Display for ImportError {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        match self {
+            ImportError::Image(e) => write!(f, "failed to read image: {}", e),
+            ImportError::SizeMismatch {
+                heightmap,
+                colour_map,
+            } => write!(
+                f,
+                "colour map is {}x{} but the heightmap is {}x{}",
+                colour_map.0, colour_map.1, heightmap.0, heightmap.1
+            ),
+            ImportError::EmptyPalette => write!(f, "the palette contains no colours"),
+        }
+    }
+}
+
+impl std::error::Error for ImportError {}
+
+impl From<image::ImageError> for ImportError {
+    fn from(e: image::ImageError) -> Self {
+        ImportError::Image(e)
+    }
+}
+
+pub struct HeightmapOptions {
+    /// Height of the surface where the heightmap is black
+    pub min_height: i32,
+    /// Height of the surface where the heightmap is white
+    pub max_height: i32,
+    /// Colours of the colour map and the block ids they stand for. Pixels are matched to the
+    /// closest colour. If empty, the map colours of all known block types are used.
+    pub palette: Vec<([u8; 3], u8)>,
+}
+
+impl Default for HeightmapOptions {
+    fn default() -> HeightmapOptions {
+        HeightmapOptions {
+            min_height: 1,
+            max_height: 128,
+            palette: vec![],
+        }
+    }
+}
+
+/// Returns the block of the palette colour closest to `colour`, the lowest id of equally close
+/// ones so the result doesn't depend on the order of the palette
+fn closest(palette: &[([u8; 3], u8)], colour: [u8; 3]) -> u8 {
+    palette
+        .iter()
+        .min_by_key(|(c, id)| {
+            let distance = (0..3)
+                .map(|i| (c[i] as i32 - colour[i] as i32).pow(2))
+                .sum::<i32>();
+            (distance, *id)
+        })
+        .map(|(_, id)| *id)
+        .unwrap()
+}
+
+/// Creates a world from a grayscale heightmap with one pixel per column, starting at world column
+/// (0, 0) and going towards positive x and z. If a colour map is given, each of its pixels selects
+/// the surface block of the column. Everything outside of the image is left flat.
+pub fn world_from_heightmap(
+    heightmap: &GrayImage,
+    colour_map: Option<&RgbImage>,
+    options: &HeightmapOptions,
+) -> Result<World, ImportError> {
+    if let Some(colour_map) = colour_map {
+        if colour_map.dimensions() != heightmap.dimensions() {
+            return Err(ImportError::SizeMismatch {
+                heightmap: heightmap.dimensions(),
+                colour_map: colour_map.dimensions(),
+            });
+        }
+    }
+    let mut world = World::with_world_gen(WorldGen::with_generator(0, Generator::Flat));
+    let palette = if options.palette.is_empty() {
+        world
+            .block_types()
+            .filter(|(_, object)| object.movement == Movement::Solid)
+            .map(|(id, object)| (object.colour, id))
+            .collect()
+    } else {
+        options.palette.clone()
+    };
+    if palette.is_empty() {
+        return Err(ImportError::EmptyPalette);
+    }
+
+    let min_height = options.min_height.clamp(0, 255);
+    let max_height = options.max_height.clamp(min_height, 255);
+    let (width, depth) = heightmap.dimensions();
+    for chunk_x in 0..(width as i32 + 15) / 16 {
+        for chunk_z in 0..(depth as i32 + 15) / 16 {
+            world.create_empty_chunk(chunk_x, chunk_z);
+        }
+    }
+    for (x, z, pixel) in heightmap.enumerate_pixels() {
+        let height = min_height + (pixel.0[0] as i32 * (max_height - min_height) + 127) / 255;
+        let surface = match colour_map {
+            Some(colour_map) => closest(&palette, colour_map.get_pixel(x, z).0),
+            None => GRASS,
+        };
+        let topsoil = if surface == GRASS { DIRT } else { surface };
+        let chunk = world.chunk_at_pos_create(x as i32, z as i32);
+        let column = (x % 16) as usize;
+        let row = (z % 16) as usize;
+        for y in 0..=height {
+            chunk.blocks[column][y as usize][row] = if y == height {
+                surface
+            } else if y >= height - TOPSOIL_DEPTH {
+                topsoil
+            } else {
+                STONE
+            };
+        }
+    }
+    Ok(world)
+}
+
+/// Reads a heightmap and an optional colour map from image files and creates a world from them.
+/// See [`world_from_heightmap`].
+pub fn import_heightmap(
+    heightmap: &Path,
+    colour_map: Option<&Path>,
+    options: &HeightmapOptions,
+) -> Result<World, ImportError> {
+    let heightmap = image::open(heightmap)?.to_luma8();
+    let colour_map = match colour_map {
+        Some(path) => Some(image::open(path)?.to_rgb8()),
+        None => None,
+    };
+    world_from_heightmap(&heightmap, colour_map.as_ref(), options)
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::heightmap::{closest, world_from_heightmap, HeightmapOptions, ImportError};
+    use crate::object::{DIRT, GRASS, STONE};
+    use image::{GrayImage, Luma, Rgb, RgbImage};
+
+    #[test]
+    fn heights_and_colours() {
+        let mut heightmap = GrayImage::new(20, 3);
+        heightmap.put_pixel(0, 0, Luma([255]));
+        heightmap.put_pixel(17, 2, Luma([128]));
+        let mut colours = RgbImage::from_pixel(20, 3, Rgb([90, 160, 50]));
+        colours.put_pixel(17, 2, Rgb([120, 120, 130]));
+        let options = HeightmapOptions {
+            min_height: 10,
+            max_height: 20,
+            palette: vec![],
+        };
+        let world = world_from_heightmap(&heightmap, Some(&colours), &options).unwrap();
+
+        assert_eq!(world.chunks.len(), 2);
+        assert_eq!(world.block_id_at(0, 20, 0), Some(GRASS));
+        assert_eq!(world.block_id_at(0, 21, 0), Some(0));
+        assert_eq!(world.block_id_at(0, 19, 0), Some(DIRT));
+        assert_eq!(world.block_id_at(1, 10, 0), Some(GRASS));
+        assert_eq!(world.block_id_at(17, 15, 2), Some(STONE));
+        assert_eq!(world.block_id_at(17, 16, 2), Some(0));
+    }
+
+    #[test]
+    fn ties_pick_the_lowest_id() {
+        let palette = [([0, 0, 0], 9), ([20, 20, 20], 4), ([0, 0, 20], 7)];
+        assert_eq!(closest(&palette, [10, 10, 10]), 4);
+        let reversed = [palette[2], palette[1], palette[0]];
+        assert_eq!(closest(&reversed, [0, 0, 10]), 7);
+        assert_eq!(closest(&palette, [0, 0, 10]), 7);
+    }
+
+    #[test]
+    fn colour_map_must_match() {
+        let heightmap = GrayImage::new(4, 4);
+        let colours = RgbImage::new(4, 5);
+        assert!(matches!(
+            world_from_heightmap(&heightmap, Some(&colours), &HeightmapOptions::default()),
+            Err(ImportError::SizeMismatch { .. })
+        ));
+    }
+}
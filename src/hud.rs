@@ -0,0 +1,300 @@
+use crate::crafting::{CraftingTable, RecipeBook, GRID_SIZE};
+use crate::debug::{DebugInfo, TimeHistory, HISTORY_LENGTH};
+use crate::font::{Align, Font};
+use crate::inventory::{Inventory, ItemStack, HOTBAR_SIZE};
+use crate::sprite_batch::SpriteBatch;
+use crate::world::World;
+
+/// Side length of a hotbar slot on screen, in logical pixels
+const SLOT_SIZE: f32 = 44.0;
+const SLOT_GAP: f32 = 4.0;
+/// Distance of the hotbar from the bottom of the screen, in logical pixels
+const BOTTOM_MARGIN: f32 = 16.0;
+/// Width of the frame around the selected slot, in logical pixels
+const SELECTION_WIDTH: f32 = 3.0;
+const ICON_SIZE: f32 = 28.0;
+const COUNT_SCALE: f32 = 1.5;
+const SLOT_COLOUR: [f32; 4] = [0.1, 0.1, 0.1, 0.6];
+const SELECTION_COLOUR: [f32; 4] = [1.0, 1.0, 1.0, 0.9];
+const TEXT_COLOUR: [f32; 4] = [1.0, 1.0, 1.0, 1.0];
+/// Colour that darkens the world behind the crafting screen
+const BACKDROP_COLOUR: [f32; 4] = [0.0, 0.0, 0.0, 0.4];
+
+/// Adds a square around `centre` with half side length `half`
+fn square(batch: &mut SpriteBatch, (x, y): (f32, f32), half: f32, colour: [f32; 4]) {
+    batch.fill(([x - half, y - half], [x + half, y + half]), colour);
+}
+
+/// Adds a slot with the icon and count of the stack in it, if there is one. `scale` is the number
+/// of pixels per logical pixel.
+fn slot(
+    batch: &mut SpriteBatch,
+    font: &Font,
+    world: &World,
+    centre: (f32, f32),
+    stack: Option<ItemStack>,
+    scale: f32,
+) {
+    square(batch, centre, SLOT_SIZE * scale / 2.0, SLOT_COLOUR);
+    let Some(stack) = stack else {
+        return;
+    };
+    if let Some(object) = world.block_type(stack.id) {
+        let [r, g, b] = object.colour.map(|c| c as f32 / 255.0);
+        square(batch, centre, ICON_SIZE * scale / 2.0, [r, g, b, 1.0]);
+    }
+    if stack.count > 1 {
+        let corner = [centre.0 + (SLOT_SIZE / 2.0 - 2.0) * scale, centre.1];
+        let text = stack.count.to_string();
+        font.draw(
+            batch,
+            &text,
+            corner,
+            COUNT_SCALE * scale,
+            Align::Right,
+            TEXT_COLOUR,
+        );
+    }
+}
+
+/// HUD bar at the bottom of the screen showing the hotbar slots of the inventory, with the
+/// selected slot framed
+pub struct Hotbar;
+
+impl Hotbar {
+    /// Returns the centre of hotbar slot `index` in pixels from the bottom left of the screen
+    fn slot_centre(index: usize, width: f32, scale: f32) -> (f32, f32) {
+        let total = HOTBAR_SIZE as f32 * (SLOT_SIZE + SLOT_GAP) - SLOT_GAP;
+        let left = (width - total * scale) / 2.0;
+        (
+            left + (index as f32 * (SLOT_SIZE + SLOT_GAP) + SLOT_SIZE / 2.0) * scale,
+            (BOTTOM_MARGIN + SLOT_SIZE / 2.0) * scale,
+        )
+    }
+
+    /// Draws the hotbar at the bottom of a screen `width` pixels wide, with `scale` pixels per
+    /// logical pixel
+    pub fn draw(
+        batch: &mut SpriteBatch,
+        font: &Font,
+        world: &World,
+        inventory: &Inventory,
+        width: f32,
+        scale: f32,
+    ) {
+        for (index, stack) in inventory.hotbar().iter().enumerate() {
+            let centre = Hotbar::slot_centre(index, width, scale);
+            if index == inventory.selected() {
+                let half = (SLOT_SIZE / 2.0 + SELECTION_WIDTH) * scale;
+                square(batch, centre, half, SELECTION_COLOUR);
+            }
+            slot(batch, font, world, centre, *stack, scale);
+        }
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum CraftingSlot {
+    /// Cell of the crafting grid at (row, column)
+    Grid(usize, usize),
+    Result,
+}
+
+/// Screen in the middle of the window showing the crafting grid and what it makes
+pub struct CraftingView;
+
+impl CraftingView {
+    /// Returns the centre of a slot in pixels from the bottom left of a screen of the given size,
+    /// with `scale` pixels per logical pixel
+    fn slot_centre(slot: CraftingSlot, (width, height): (f32, f32), scale: f32) -> (f32, f32) {
+        let step = (SLOT_SIZE + SLOT_GAP) * scale;
+        // the grid, a gap of one slot and the result slot are centred on the screen
+        let left = width / 2.0 - (GRID_SIZE + 1) as f32 * step / 2.0 - step / 2.0;
+        let top = height / 2.0 + (GRID_SIZE - 1) as f32 * step / 2.0;
+        match slot {
+            CraftingSlot::Grid(row, column) => {
+                (left + column as f32 * step, top - row as f32 * step)
+            }
+            CraftingSlot::Result => (left + (GRID_SIZE + 1) as f32 * step, height / 2.0),
+        }
+    }
+
+    /// Returns the slot under the cursor, given in pixels from the top left of a screen of the
+    /// given size like window events report it
+    pub fn slot_at(cursor: (f32, f32), size: (f32, f32), scale: f32) -> Option<CraftingSlot> {
+        let cursor = (cursor.0, size.1 - cursor.1);
+        let grid = (0..GRID_SIZE)
+            .flat_map(|row| (0..GRID_SIZE).map(move |column| CraftingSlot::Grid(row, column)));
+        grid.chain([CraftingSlot::Result]).find(|slot| {
+            let (x, y) = CraftingView::slot_centre(*slot, size, scale);
+            let half = SLOT_SIZE * scale / 2.0;
+            (cursor.0 - x).abs() <= half && (cursor.1 - y).abs() <= half
+        })
+    }
+
+    pub fn draw(
+        batch: &mut SpriteBatch,
+        font: &Font,
+        world: &World,
+        table: &CraftingTable,
+        recipes: &RecipeBook,
+        size: (f32, f32),
+        scale: f32,
+    ) {
+        batch.fill(([0.0, 0.0], [size.0, size.1]), BACKDROP_COLOUR);
+        for row in 0..GRID_SIZE {
+            for column in 0..GRID_SIZE {
+                let centre =
+                    CraftingView::slot_centre(CraftingSlot::Grid(row, column), size, scale);
+                slot(batch, font, world, centre, table.grid[row][column], scale);
+            }
+        }
+        let result = recipes.find(&table.grid).map(|recipe| recipe.result);
+        let centre = CraftingView::slot_centre(CraftingSlot::Result, size, scale);
+        slot(batch, font, world, centre, result, scale);
+
+        let (x, y) = CraftingView::slot_centre(CraftingSlot::Grid(0, 1), size, scale);
+        let title = [x, y + (SLOT_SIZE / 2.0 + 32.0) * scale];
+        font.draw(
+            batch,
+            "Crafting",
+            title,
+            2.0 * scale,
+            Align::Centre,
+            TEXT_COLOUR,
+        );
+        if let Some(object) = result.and_then(|stack| world.block_type(stack.id)) {
+            let (x, y) = (centre.0, centre.1 - (SLOT_SIZE / 2.0 + 8.0) * scale);
+            font.draw(
+                batch,
+                object.name,
+                [x, y],
+                1.5 * scale,
+                Align::Centre,
+                TEXT_COLOUR,
+            );
+        }
+    }
+}
+
+/// Scale of the debug overlay text
+const DEBUG_SCALE: f32 = 2.0;
+const DEBUG_MARGIN: f32 = 8.0;
+const DEBUG_BACKGROUND: [f32; 4] = [0.0, 0.0, 0.0, 0.5];
+/// Width of one sample in the time graphs, in pixels
+const GRAPH_BAR_WIDTH: f32 = 2.0;
+/// Height of one millisecond in the time graphs, in pixels
+const GRAPH_SCALE: f32 = 3.0;
+const GRAPH_HEIGHT: f32 = 100.0;
+/// Frame time of 60 frames per second, marked in the frame time graph
+const TARGET_FRAME_TIME: f32 = 1000.0 / 60.0;
+
+/// Overlay showing the contents of a [`DebugInfo`] in the top left corner and graphs of the frame
+/// and tick times in the bottom left corner
+pub struct DebugOverlay;
+
+impl DebugOverlay {
+    /// Returns the colour of a bar, from green for fast to red for slow
+    fn bar_colour(milliseconds: f32) -> [f32; 4] {
+        if milliseconds <= TARGET_FRAME_TIME {
+            [0.2, 0.9, 0.2, 0.8]
+        } else if milliseconds <= 2.0 * TARGET_FRAME_TIME {
+            [0.9, 0.9, 0.2, 0.8]
+        } else {
+            [0.9, 0.2, 0.2, 0.8]
+        }
+    }
+
+    fn graph(batch: &mut SpriteBatch, font: &Font, title: &str, times: &TimeHistory, left: f32) {
+        let bottom = DEBUG_MARGIN;
+        let width = HISTORY_LENGTH as f32 * GRAPH_BAR_WIDTH;
+        batch.fill(
+            ([left, bottom], [left + width, bottom + GRAPH_HEIGHT]),
+            DEBUG_BACKGROUND,
+        );
+        for (index, milliseconds) in times.samples().enumerate() {
+            let x = left + index as f32 * GRAPH_BAR_WIDTH;
+            let height = (milliseconds * GRAPH_SCALE).min(GRAPH_HEIGHT);
+            batch.fill(
+                ([x, bottom], [x + GRAPH_BAR_WIDTH, bottom + height]),
+                DebugOverlay::bar_colour(milliseconds),
+            );
+        }
+        let target = bottom + TARGET_FRAME_TIME * GRAPH_SCALE;
+        batch.fill(
+            ([left, target], [left + width, target + 1.0]),
+            [1.0, 1.0, 1.0, 0.6],
+        );
+        let text = format!("{} {:.1} ms", title, times.last());
+        let top = bottom + GRAPH_HEIGHT + 14.0;
+        font.draw(batch, &text, [left, top], 1.5, Align::Left, TEXT_COLOUR);
+    }
+
+    pub fn draw(batch: &mut SpriteBatch, font: &Font, info: &DebugInfo, height: f32) {
+        let line_height = Font::measure("X", DEBUG_SCALE).1 + 4.0;
+        for (index, line) in info.lines().iter().enumerate() {
+            let top = height - DEBUG_MARGIN - index as f32 * line_height;
+            let (width, text_height) = Font::measure(line, DEBUG_SCALE);
+            batch.fill(
+                (
+                    [DEBUG_MARGIN - 2.0, top - text_height - 2.0],
+                    [DEBUG_MARGIN + width + 2.0, top + 2.0],
+                ),
+                DEBUG_BACKGROUND,
+            );
+            let position = [DEBUG_MARGIN, top];
+            font.draw(batch, line, position, DEBUG_SCALE, Align::Left, TEXT_COLOUR);
+        }
+        let graph_width = HISTORY_LENGTH as f32 * GRAPH_BAR_WIDTH;
+        DebugOverlay::graph(batch, font, "Frame", &info.frame_times, DEBUG_MARGIN);
+        let left = 2.0 * DEBUG_MARGIN + graph_width;
+        DebugOverlay::graph(batch, font, "Update", &info.update_times, left);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::hud::{CraftingSlot, CraftingView, Hotbar};
+
+    #[test]
+    fn slots_under_cursor() {
+        let size = (800.0, 600.0);
+        for slot in [
+            CraftingSlot::Grid(0, 0),
+            CraftingSlot::Grid(2, 1),
+            CraftingSlot::Result,
+        ] {
+            let (x, y) = CraftingView::slot_centre(slot, size, 1.0);
+            assert_eq!(
+                CraftingView::slot_at((x + 5.0, size.1 - y - 5.0), size, 1.0),
+                Some(slot)
+            );
+        }
+        // the top left cell is above and left of the centre
+        let (x, y) = CraftingView::slot_centre(CraftingSlot::Grid(0, 0), size, 1.0);
+        assert!(x < 400.0 && y > 300.0);
+        assert_eq!(CraftingView::slot_at((0.0, 0.0), size, 1.0), None);
+    }
+
+    #[test]
+    fn scales_with_the_screen() {
+        let size = (1600.0, 1200.0);
+        let centre = |scale| CraftingView::slot_centre(CraftingSlot::Result, size, scale);
+        // twice as far from the middle of the screen, and twice as large
+        assert_eq!(centre(2.0).0 - 800.0, 2.0 * (centre(1.0).0 - 800.0));
+        let (x, y) = centre(2.0);
+        let cursor = (x + 30.0, size.1 - y);
+        assert_eq!(
+            CraftingView::slot_at(cursor, size, 2.0),
+            Some(CraftingSlot::Result)
+        );
+        let (x, y) = centre(1.0);
+        assert_eq!(
+            CraftingView::slot_at((x + 30.0, size.1 - y), size, 1.0),
+            None
+        );
+
+        assert_eq!(Hotbar::slot_centre(4, 1600.0, 2.0), (800.0, 76.0));
+        assert_eq!(Hotbar::slot_centre(4, 1600.0, 1.0), (800.0, 38.0));
+    }
+}
@@ -0,0 +1,606 @@
+use crate::inventory::HOTBAR_SIZE;
+use glium::glutin::event::{MouseButton, VirtualKeyCode};
+use std::collections::{HashMap, HashSet};
+
+/// Something the player can do. Gameplay code only asks about actions, which the settings bind
+/// to physical inputs.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum Action {
+    MoveForward,
+    MoveBack,
+    MoveLeft,
+    MoveRight,
+    Jump,
+    Sneak,
+    /// Sprint while moving forward, like double tapping forward
+    Sprint,
+    /// Break the targeted block
+    Attack,
+    /// Place the selected block against the targeted block
+    Use,
+    /// Throw a projectile where the camera looks
+    Throw,
+    /// Open or close the crafting grid
+    Crafting,
+    NextSlot,
+    PreviousSlot,
+    /// Select the hotbar slot with this index, from 0
+    SelectSlot(usize),
+    /// Start naming a waypoint at the current position
+    Waypoint,
+    LookUp,
+    LookDown,
+    LookLeft,
+    LookRight,
+}
+
+impl Action {
+    pub const ALL: [Action; 27] = [
+        Action::MoveForward,
+        Action::MoveBack,
+        Action::MoveLeft,
+        Action::MoveRight,
+        Action::Jump,
+        Action::Sneak,
+        Action::Sprint,
+        Action::Attack,
+        Action::Use,
+        Action::Throw,
+        Action::Crafting,
+        Action::NextSlot,
+        Action::PreviousSlot,
+        Action::SelectSlot(0),
+        Action::SelectSlot(1),
+        Action::SelectSlot(2),
+        Action::SelectSlot(3),
+        Action::SelectSlot(4),
+        Action::SelectSlot(5),
+        Action::SelectSlot(6),
+        Action::SelectSlot(7),
+        Action::SelectSlot(8),
+        Action::Waypoint,
+        Action::LookUp,
+        Action::LookDown,
+        Action::LookLeft,
+        Action::LookRight,
+    ];
+
+    /// Returns the key of the action in the settings file
+    pub fn name(self) -> &'static str {
+        match self {
+            Action::MoveForward => "forward",
+            Action::MoveBack => "back",
+            Action::MoveLeft => "left",
+            Action::MoveRight => "right",
+            Action::Jump => "jump",
+            Action::Sneak => "sneak",
+            Action::Sprint => "sprint",
+            Action::Attack => "attack",
+            Action::Use => "use",
+            Action::Throw => "throw",
+            Action::Crafting => "crafting",
+            Action::NextSlot => "next_slot",
+            Action::PreviousSlot => "previous_slot",
+            // slots past the hotbar can't be bound, they are never in the settings file
+            Action::SelectSlot(slot) => SLOT_NAMES.get(slot).copied().unwrap_or("slot"),
+            Action::Waypoint => "waypoint",
+            Action::LookUp => "look_up",
+            Action::LookDown => "look_down",
+            Action::LookLeft => "look_left",
+            Action::LookRight => "look_right",
+        }
+    }
+}
+
+/// Settings file keys of [`Action::SelectSlot`] for each hotbar slot
+const SLOT_NAMES: [&str; HOTBAR_SIZE] = [
+    "slot_1", "slot_2", "slot_3", "slot_4", "slot_5", "slot_6", "slot_7", "slot_8", "slot_9",
+];
+
+/// Buttons of a gamepad, named after their position so they mean the same on every brand
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum GamepadButton {
+    South,
+    East,
+    North,
+    West,
+    LeftBumper,
+    RightBumper,
+    LeftTrigger,
+    RightTrigger,
+    Select,
+    Start,
+    LeftStick,
+    RightStick,
+    DPadUp,
+    DPadDown,
+    DPadLeft,
+    DPadRight,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum Stick {
+    Left,
+    Right,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum StickDirection {
+    Up,
+    Down,
+    Left,
+    Right,
+}
+
+/// A physical input that can be bound to an action
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum Input {
+    /// A key by the character it types in the current keyboard layout, not by where it is on the
+    /// keyboard, so the default bindings only fit QWERTY and other layouts have to rebind them
+    Key(VirtualKeyCode),
+    Mouse(MouseButton),
+    WheelUp,
+    WheelDown,
+    Gamepad(GamepadButton),
+    /// A gamepad stick pushed in a direction, which is held as long as it is pushed that way
+    Stick(Stick, StickDirection),
+}
+
+/// Keys that can be bound, by the name they are written with in the settings file
+const KEY_NAMES: &[(&str, VirtualKeyCode)] = {
+    use VirtualKeyCode::*;
+    &[
+        ("A", A),
+        ("B", B),
+        ("C", C),
+        ("D", D),
+        ("E", E),
+        ("F", F),
+        ("G", G),
+        ("H", H),
+        ("I", I),
+        ("J", J),
+        ("K", K),
+        ("L", L),
+        ("M", M),
+        ("N", N),
+        ("O", O),
+        ("P", P),
+        ("Q", Q),
+        ("R", R),
+        ("S", S),
+        ("T", T),
+        ("U", U),
+        ("V", V),
+        ("W", W),
+        ("X", X),
+        ("Y", Y),
+        ("Z", Z),
+        ("0", Key0),
+        ("1", Key1),
+        ("2", Key2),
+        ("3", Key3),
+        ("4", Key4),
+        ("5", Key5),
+        ("6", Key6),
+        ("7", Key7),
+        ("8", Key8),
+        ("9", Key9),
+        ("F1", F1),
+        ("F2", F2),
+        ("F3", F3),
+        ("F4", F4),
+        ("F5", F5),
+        ("F6", F6),
+        ("F7", F7),
+        ("F8", F8),
+        ("F9", F9),
+        ("F10", F10),
+        ("F11", F11),
+        ("F12", F12),
+        ("Escape", Escape),
+        ("Space", Space),
+        ("Tab", Tab),
+        ("LShift", LShift),
+        ("RShift", RShift),
+        ("LControl", LControl),
+        ("RControl", RControl),
+        ("LAlt", LAlt),
+        ("RAlt", RAlt),
+        ("Up", Up),
+        ("Down", Down),
+        ("Left", Left),
+        ("Right", Right),
+    ]
+};
+
+const MOUSE_NAMES: &[(&str, MouseButton)] = &[
+    ("MouseLeft", MouseButton::Left),
+    ("MouseRight", MouseButton::Right),
+    ("MouseMiddle", MouseButton::Middle),
+];
+
+const GAMEPAD_NAMES: &[(&str, GamepadButton)] = {
+    use GamepadButton::*;
+    &[
+        ("GamepadSouth", South),
+        ("GamepadEast", East),
+        ("GamepadNorth", North),
+        ("GamepadWest", West),
+        ("GamepadLeftBumper", LeftBumper),
+        ("GamepadRightBumper", RightBumper),
+        ("GamepadLeftTrigger", LeftTrigger),
+        ("GamepadRightTrigger", RightTrigger),
+        ("GamepadSelect", Select),
+        ("GamepadStart", Start),
+        ("GamepadLeftStick", LeftStick),
+        ("GamepadRightStick", RightStick),
+        ("GamepadDPadUp", DPadUp),
+        ("GamepadDPadDown", DPadDown),
+        ("GamepadDPadLeft", DPadLeft),
+        ("GamepadDPadRight", DPadRight),
+    ]
+};
+
+const STICK_NAMES: &[(&str, (Stick, StickDirection))] = {
+    use StickDirection::*;
+    &[
+        ("GamepadLeftStickUp", (Stick::Left, Up)),
+        ("GamepadLeftStickDown", (Stick::Left, Down)),
+        ("GamepadLeftStickLeft", (Stick::Left, Left)),
+        ("GamepadLeftStickRight", (Stick::Left, Right)),
+        ("GamepadRightStickUp", (Stick::Right, Up)),
+        ("GamepadRightStickDown", (Stick::Right, Down)),
+        ("GamepadRightStickLeft", (Stick::Right, Left)),
+        ("GamepadRightStickRight", (Stick::Right, Right)),
+    ]
+};
+
+/// Returns the value paired with `name` in `names`, ignoring case
+fn find_by_name<T: Copy>(names: &[(&str, T)], name: &str) -> Option<T> {
+    names
+        .iter()
+        .find(|(n, _)| n.eq_ignore_ascii_case(name))
+        .map(|(_, value)| *value)
+}
+
+fn find_name<T: PartialEq>(names: &[(&'static str, T)], value: &T) -> Option<&'static str> {
+    names.iter().find(|(_, v)| v == value).map(|(n, _)| *n)
+}
+
+impl Input {
+    /// Parses the name of an input in the settings file, like `W`, `LShift`, `MouseLeft`,
+    /// `WheelUp` or `GamepadSouth`
+    pub fn parse(name: &str) -> Option<Input> {
+        if name.eq_ignore_ascii_case("WheelUp") {
+            return Some(Input::WheelUp);
+        }
+        if name.eq_ignore_ascii_case("WheelDown") {
+            return Some(Input::WheelDown);
+        }
+        find_by_name(KEY_NAMES, name)
+            .map(Input::Key)
+            .or_else(|| find_by_name(MOUSE_NAMES, name).map(Input::Mouse))
+            .or_else(|| find_by_name(GAMEPAD_NAMES, name).map(Input::Gamepad))
+            .or_else(|| {
+                let (stick, direction) = find_by_name(STICK_NAMES, name)?;
+                Some(Input::Stick(stick, direction))
+            })
+    }
+
+    /// Returns the name [`Input::parse`] accepts, if the input has one
+    pub fn name(self) -> Option<&'static str> {
+        match self {
+            Input::Key(key) => find_name(KEY_NAMES, &key),
+            Input::Mouse(button) => find_name(MOUSE_NAMES, &button),
+            Input::WheelUp => Some("WheelUp"),
+            Input::WheelDown => Some("WheelDown"),
+            Input::Gamepad(button) => find_name(GAMEPAD_NAMES, &button),
+            Input::Stick(stick, direction) => find_name(STICK_NAMES, &(stick, direction)),
+        }
+    }
+
+    pub fn is_gamepad(self) -> bool {
+        matches!(self, Input::Gamepad(_) | Input::Stick(..))
+    }
+}
+
+/// The inputs bound to each action. An action can have any number of inputs and an input can
+/// trigger several actions.
+#[derive(Debug, Clone, PartialEq)]
+pub struct Bindings {
+    bindings: HashMap<Action, Vec<Input>>,
+}
+
+impl Default for Bindings {
+    fn default() -> Bindings {
+        use GamepadButton as Pad;
+        use StickDirection as Direction;
+        use VirtualKeyCode as Key;
+        let left_stick = |direction| Input::Stick(Stick::Left, direction);
+        let right_stick = |direction| Input::Stick(Stick::Right, direction);
+        let bindings = [
+            (
+                Action::MoveForward,
+                vec![Input::Key(Key::W), left_stick(Direction::Up)],
+            ),
+            (
+                Action::MoveBack,
+                vec![Input::Key(Key::S), left_stick(Direction::Down)],
+            ),
+            (
+                Action::MoveLeft,
+                vec![Input::Key(Key::A), left_stick(Direction::Left)],
+            ),
+            (
+                Action::MoveRight,
+                vec![Input::Key(Key::D), left_stick(Direction::Right)],
+            ),
+            (
+                Action::Jump,
+                vec![Input::Key(Key::Space), Input::Gamepad(Pad::South)],
+            ),
+            (
+                Action::Sneak,
+                vec![Input::Key(Key::LShift), Input::Gamepad(Pad::East)],
+            ),
+            (
+                Action::Sprint,
+                vec![Input::Key(Key::LControl), Input::Gamepad(Pad::LeftStick)],
+            ),
+            (
+                Action::Attack,
+                vec![
+                    Input::Mouse(MouseButton::Left),
+                    Input::Gamepad(Pad::RightTrigger),
+                ],
+            ),
+            (
+                Action::Use,
+                vec![
+                    Input::Mouse(MouseButton::Right),
+                    Input::Gamepad(Pad::LeftTrigger),
+                ],
+            ),
+            (
+                Action::Throw,
+                vec![Input::Key(Key::F), Input::Gamepad(Pad::West)],
+            ),
+            (
+                Action::Crafting,
+                vec![Input::Key(Key::E), Input::Gamepad(Pad::North)],
+            ),
+            (
+                Action::NextSlot,
+                vec![Input::WheelDown, Input::Gamepad(Pad::RightBumper)],
+            ),
+            (
+                Action::PreviousSlot,
+                vec![Input::WheelUp, Input::Gamepad(Pad::LeftBumper)],
+            ),
+            (Action::Waypoint, vec![Input::Key(Key::B)]),
+            (Action::LookUp, vec![right_stick(Direction::Up)]),
+            (Action::LookDown, vec![right_stick(Direction::Down)]),
+            (Action::LookLeft, vec![right_stick(Direction::Left)]),
+            (Action::LookRight, vec![right_stick(Direction::Right)]),
+        ];
+        let slot_keys = [
+            Key::Key1,
+            Key::Key2,
+            Key::Key3,
+            Key::Key4,
+            Key::Key5,
+            Key::Key6,
+            Key::Key7,
+            Key::Key8,
+            Key::Key9,
+        ];
+        let slots = slot_keys
+            .into_iter()
+            .enumerate()
+            .map(|(slot, key)| (Action::SelectSlot(slot), vec![Input::Key(key)]));
+        Bindings {
+            bindings: bindings.into_iter().chain(slots).collect(),
+        }
+    }
+}
+
+impl Bindings {
+    pub fn inputs(&self, action: Action) -> &[Input] {
+        self.bindings.get(&action).map_or(&[], |inputs| inputs)
+    }
+
+    /// Replaces the inputs bound to `action`
+    pub fn bind(&mut self, action: Action, inputs: Vec<Input>) {
+        self.bindings.insert(action, inputs);
+    }
+
+    /// Returns how far each action is pushed by the held inputs, 1 for digital inputs
+    fn strengths(
+        &self,
+        held: &HashSet<Input>,
+        analog: &HashMap<Input, f32>,
+    ) -> HashMap<Action, f32> {
+        let mut strengths = HashMap::new();
+        for action in Action::ALL {
+            let strength = self
+                .inputs(action)
+                .iter()
+                .filter(|input| held.contains(input))
+                .map(|input| analog.get(input).copied().unwrap_or(1.0))
+                .fold(0.0, f32::max);
+            if strength > 0.0 {
+                strengths.insert(action, strength);
+            }
+        }
+        strengths
+    }
+
+    fn triggered_by(&self, inputs: &HashSet<Input>) -> HashSet<Action> {
+        Action::ALL
+            .into_iter()
+            .filter(|action| {
+                self.inputs(*action)
+                    .iter()
+                    .any(|input| inputs.contains(input))
+            })
+            .collect()
+    }
+}
+
+/// Actions that are held down and that were started since the last frame
+#[derive(Debug, Default, Clone, PartialEq)]
+pub struct ActionState {
+    /// How far the held actions are pushed, from 0 to 1
+    held: HashMap<Action, f32>,
+    pressed: HashSet<Action>,
+}
+
+impl ActionState {
+    /// Returns whether any input bound to the action is held down
+    pub fn held(&self, action: Action) -> bool {
+        self.held.contains_key(&action)
+    }
+
+    /// Returns how far the action is pushed, from 0 when it isn't held to 1 when it is held down
+    /// fully. Analog inputs like gamepad sticks push it part of the way.
+    pub fn strength(&self, action: Action) -> f32 {
+        self.held.get(&action).copied().unwrap_or(0.0)
+    }
+
+    /// Returns whether an input bound to the action was pressed since the last frame
+    pub fn pressed(&self, action: Action) -> bool {
+        self.pressed.contains(&action)
+    }
+}
+
+/// Physical inputs that are held down or were pressed since the last frame, fed from window
+/// events
+#[derive(Debug, Default)]
+pub struct InputState {
+    held: HashSet<Input>,
+    pressed: HashSet<Input>,
+    /// How far the held analog inputs are pushed
+    analog: HashMap<Input, f32>,
+}
+
+impl InputState {
+    /// Records that `input` went down. Repeated presses while it is held, like key repeat, are
+    /// ignored.
+    pub fn press(&mut self, input: Input) {
+        if self.held.insert(input) {
+            self.pressed.insert(input);
+        }
+    }
+
+    pub fn release(&mut self, input: Input) {
+        self.held.remove(&input);
+        self.analog.remove(&input);
+    }
+
+    /// Records how far an analog input is pushed, from 0 to 1. It is held while it is above 0.
+    pub fn set_analog(&mut self, input: Input, value: f32) {
+        if value > 0.0 {
+            self.press(input);
+            self.analog.insert(input, value.min(1.0));
+        } else {
+            self.release(input);
+        }
+    }
+
+    /// Releases all gamepad inputs, for when the gamepad is disconnected
+    pub fn release_gamepad(&mut self) {
+        self.held.retain(|input| !input.is_gamepad());
+        self.analog.retain(|input, _| !input.is_gamepad());
+    }
+
+    /// Records an input that has no duration, like a step of the mouse wheel
+    pub fn tap(&mut self, input: Input) {
+        self.pressed.insert(input);
+    }
+
+    /// Forgets all inputs, for when they stop going to the game
+    pub fn clear(&mut self) {
+        self.held.clear();
+        self.pressed.clear();
+        self.analog.clear();
+    }
+
+    /// Returns the state of the actions bound to the inputs and starts a new frame
+    pub fn take_actions(&mut self, bindings: &Bindings) -> ActionState {
+        let state = ActionState {
+            held: bindings.strengths(&self.held, &self.analog),
+            pressed: bindings.triggered_by(&self.pressed),
+        };
+        self.pressed.clear();
+        state
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::input::{Action, Bindings, Input, InputState};
+    use crate::inventory::HOTBAR_SIZE;
+    use glium::glutin::event::{MouseButton, VirtualKeyCode};
+
+    #[test]
+    fn actions_follow_bindings() {
+        let mut bindings = Bindings::default();
+        // an AZERTY layout
+        bindings.bind(Action::MoveForward, vec![Input::Key(VirtualKeyCode::Z)]);
+        bindings.bind(
+            Action::Attack,
+            vec![
+                Input::Mouse(MouseButton::Left),
+                Input::Key(VirtualKeyCode::F),
+            ],
+        );
+        let mut input = InputState::default();
+
+        input.press(Input::Key(VirtualKeyCode::W));
+        input.press(Input::Key(VirtualKeyCode::Z));
+        input.press(Input::Key(VirtualKeyCode::F));
+        let actions = input.take_actions(&bindings);
+        assert!(actions.held(Action::MoveForward));
+        assert!(actions.pressed(Action::MoveForward));
+        assert!(actions.held(Action::Attack));
+        assert!(!actions.held(Action::MoveBack));
+
+        // held actions stay held, but are only pressed once
+        input.press(Input::Key(VirtualKeyCode::Z));
+        input.press(Input::Mouse(MouseButton::Left));
+        input.release(Input::Key(VirtualKeyCode::F));
+        input.tap(Input::WheelDown);
+        let actions = input.take_actions(&bindings);
+        assert!(actions.held(Action::MoveForward));
+        assert!(!actions.pressed(Action::MoveForward));
+        assert!(actions.held(Action::Attack));
+        assert!(actions.pressed(Action::NextSlot));
+        assert!(!actions.held(Action::NextSlot));
+
+        let actions = input.take_actions(&bindings);
+        assert!(!actions.pressed(Action::NextSlot));
+    }
+
+    #[test]
+    fn input_names() {
+        for name in [
+            "W",
+            "1",
+            "F11",
+            "LShift",
+            "MouseLeft",
+            "WheelUp",
+            "GamepadRightTrigger",
+        ] {
+            assert_eq!(Input::parse(name).unwrap().name(), Some(name));
+        }
+        assert_eq!(
+            Input::parse("lshift"),
+            Some(Input::Key(VirtualKeyCode::LShift))
+        );
+        assert_eq!(Input::parse("Hyperspace"), None);
+        assert_eq!(Action::SelectSlot(8).name(), "slot_9");
+        assert_eq!(Action::SelectSlot(HOTBAR_SIZE).name(), "slot");
+    }
+}
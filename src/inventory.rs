@@ -0,0 +1,150 @@
+/// Number of slots in the hotbar, which are the first slots of the inventory
+pub const HOTBAR_SIZE: usize = 9;
+/// Number of slots in the inventory, including the hotbar
+pub const INVENTORY_SIZE: usize = 36;
+pub const MAX_STACK: u8 = 64;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct ItemStack {
+    /// Id of the block type
+    pub id: u8,
+    pub count: u8,
+}
+
+impl ItemStack {
+    pub fn new(id: u8, count: u8) -> ItemStack {
+        ItemStack { id, count }
+    }
+}
+
+/// The items a player carries and which hotbar slot is selected
+#[derive(Debug, Clone, PartialEq)]
+pub struct Inventory {
+    slots: [Option<ItemStack>; INVENTORY_SIZE],
+    selected: usize,
+}
+
+impl Default for Inventory {
+    fn default() -> Inventory {
+        Inventory {
+            slots: [None; INVENTORY_SIZE],
+            selected: 0,
+        }
+    }
+}
+
+impl Inventory {
+    pub fn slots(&self) -> &[Option<ItemStack>] {
+        &self.slots
+    }
+
+    pub fn slot(&self, index: usize) -> Option<ItemStack> {
+        self.slots.get(index).copied().flatten()
+    }
+
+    pub fn set_slot(&mut self, index: usize, stack: Option<ItemStack>) {
+        self.slots[index] = stack.filter(|stack| stack.count > 0);
+    }
+
+    pub fn hotbar(&self) -> &[Option<ItemStack>] {
+        &self.slots[..HOTBAR_SIZE]
+    }
+
+    /// Index of the selected hotbar slot
+    pub fn selected(&self) -> usize {
+        self.selected
+    }
+
+    pub fn select(&mut self, slot: usize) {
+        self.selected = slot.min(HOTBAR_SIZE - 1);
+    }
+
+    /// Moves the selection by `steps` hotbar slots, wrapping around at the ends
+    pub fn scroll(&mut self, steps: i32) {
+        self.selected = (self.selected as i32 + steps).rem_euclid(HOTBAR_SIZE as i32) as usize;
+    }
+
+    pub fn selected_stack(&self) -> Option<ItemStack> {
+        self.slots[self.selected]
+    }
+
+    /// Adds `count` items of block type `id`, topping up existing stacks before filling empty
+    /// slots. Returns how many items didn't fit.
+    pub fn add(&mut self, id: u8, mut count: u8) -> u8 {
+        for stack in self.slots.iter_mut().flatten() {
+            if stack.id == id && stack.count < MAX_STACK {
+                let moved = count.min(MAX_STACK - stack.count);
+                stack.count += moved;
+                count -= moved;
+            }
+        }
+        for slot in self.slots.iter_mut().filter(|slot| slot.is_none()) {
+            if count == 0 {
+                break;
+            }
+            let moved = count.min(MAX_STACK);
+            *slot = Some(ItemStack::new(id, moved));
+            count -= moved;
+        }
+        count
+    }
+
+    /// Removes one item from the selected stack and returns its block type
+    pub fn take_selected(&mut self) -> Option<u8> {
+        let slot = &mut self.slots[self.selected];
+        let stack = slot.as_mut()?;
+        let id = stack.id;
+        stack.count -= 1;
+        if stack.count == 0 {
+            *slot = None;
+        }
+        Some(id)
+    }
+
+    /// Returns the total number of items of block type `id`
+    pub fn count(&self, id: u8) -> u32 {
+        self.slots
+            .iter()
+            .flatten()
+            .filter(|stack| stack.id == id)
+            .map(|stack| stack.count as u32)
+            .sum()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::inventory::{Inventory, ItemStack, HOTBAR_SIZE, INVENTORY_SIZE, MAX_STACK};
+
+    #[test]
+    fn stacks_fill_up() {
+        let mut inventory = Inventory::default();
+        assert_eq!(inventory.add(3, 10), 0);
+        assert_eq!(inventory.add(5, 1), 0);
+        assert_eq!(inventory.add(3, 60), 0);
+        assert_eq!(inventory.slot(0), Some(ItemStack::new(3, MAX_STACK)));
+        assert_eq!(inventory.slot(1), Some(ItemStack::new(5, 1)));
+        assert_eq!(inventory.slot(2), Some(ItemStack::new(3, 6)));
+        assert_eq!(inventory.count(3), 70);
+
+        for _ in 0..INVENTORY_SIZE {
+            inventory.add(7, MAX_STACK);
+        }
+        assert_eq!(inventory.add(7, 5), 5);
+    }
+
+    #[test]
+    fn hotbar_selection() {
+        let mut inventory = Inventory::default();
+        inventory.add(3, 2);
+        assert_eq!(inventory.take_selected(), Some(3));
+        assert_eq!(inventory.take_selected(), Some(3));
+        assert_eq!(inventory.take_selected(), None);
+        inventory.scroll(-1);
+        assert_eq!(inventory.selected(), HOTBAR_SIZE - 1);
+        inventory.scroll(2);
+        assert_eq!(inventory.selected(), 1);
+        inventory.select(20);
+        assert_eq!(inventory.selected(), HOTBAR_SIZE - 1);
+    }
+}
@@ -0,0 +1,39 @@
+pub mod block_update;
+pub mod camera;
+pub mod chunk;
+pub mod crafting;
+pub mod cube;
+pub mod debug;
+pub mod entity;
+pub mod feature;
+pub mod fog;
+pub mod font;
+pub mod gamepad;
+pub mod heightmap;
+pub mod hud;
+pub mod input;
+pub mod inventory;
+pub mod map_render;
+pub mod math;
+pub mod menu;
+pub mod minimap;
+pub mod mining;
+pub mod object;
+pub mod pathfinding;
+pub mod perspective;
+pub mod physics;
+pub mod player;
+pub mod raycast;
+pub mod rng;
+pub mod save;
+pub mod settings;
+pub mod shadow;
+pub mod sky;
+pub mod sprite_batch;
+pub mod structure;
+pub mod ui;
+pub mod vertex;
+pub mod waypoint;
+pub mod window;
+pub mod world;
+pub mod world_gen;
@@ -1,66 +1,378 @@
-use camera::Camera;
 use glium::draw_parameters::PolygonMode;
 use glium::glutin::event::VirtualKeyCode;
 use glium::glutin::window::Fullscreen;
 use glium::{glutin, uniform, Surface};
 use glutin::event::DeviceEvent::MouseMotion;
-use glutin::event::ElementState;
 use glutin::event::Event::DeviceEvent;
+use glutin::event::{ElementState, KeyboardInput};
 use glutin::window::CursorGrabMode;
-use math::Vec3;
-use std::collections::HashSet;
+use raft::camera::Camera;
+use raft::crafting::{CraftingTable, RecipeBook, DEFAULT_RECIPES};
+use raft::cube::Cube;
+use raft::debug::DebugInfo;
+use raft::entity::{Entities, Entity, EntityKind};
+use raft::fog::{Fog, HEIGHT_FOG_BASE, HEIGHT_FOG_FALLOFF, HEIGHT_FOG_THICKNESS};
+use raft::font::{Align, Font};
+#[cfg(feature = "gamepad")]
+use raft::gamepad::{Gamepad, GamepadBackend};
+use raft::heightmap::{import_heightmap, HeightmapOptions};
+use raft::hud::{CraftingSlot, CraftingView, DebugOverlay, Hotbar};
+use raft::input::{Action, ActionState, Input, InputState};
+use raft::inventory::HOTBAR_SIZE;
+use raft::map_render::{render_saved_map, saved_bounds, MapOptions};
+use raft::math::{box_in_view, Mat4, Quat, Vec3};
+use raft::menu::{Menu, MenuAction};
+use raft::minimap::Minimap;
+use raft::mining::{crack_image, Mining, BREAK_STAGES};
+use raft::object::AIR;
+use raft::perspective;
+use raft::physics::Aabb;
+use raft::player::Player;
+use raft::raycast::raycast;
+use raft::save::WorldSave;
+use raft::settings::Settings;
+use raft::shadow::{cascades, MAX_CASCADES};
+use raft::sky::{adjust_time, clock, dome, Sky};
+use raft::sprite_batch::{SpriteBatch, FULL_TEXTURE};
+use raft::ui::{render, UiInput};
+use raft::vertex::Vertex;
+use raft::waypoint::Waypoints;
+use raft::window::WindowState;
+use raft::world::World;
 use std::io::Cursor;
-use world::World;
-
-mod camera;
-mod chunk;
-mod cube;
-mod math;
-mod object;
-mod perspective;
-mod vertex;
-mod view_matrix;
-mod world;
-mod world_gen;
-
-use glium::implement_vertex;
-#[derive(Copy, Clone)]
-struct Vertex2D {
-    position: [f32; 2],
-    tex_coords: [f32; 2],
-}
-implement_vertex!(Vertex2D, position, tex_coords);
-
-fn main() {
+use std::path::{Path, PathBuf};
+use std::process::ExitCode;
+use std::time::Instant;
+
+const USAGE: &str = "Usage:
+    raft [WORLD_DIR]
+        Play the world saved in WORLD_DIR, creating it if it doesn't exist
+    raft import-heightmap HEIGHTMAP WORLD_DIR [--colours COLOUR_MAP] [--min-height N] [--max-height N]
+        Create a world from a grayscale heightmap image and save it to WORLD_DIR
+    raft render-map WORLD_DIR OUTPUT [--from X,Z] [--to X,Z] [--grid] [--no-shading] [--generate]
+        Render a top-down map of the saved world to a PNG image
+    raft set-time WORLD_DIR TIME
+        Set the time of the saved world to TIME ticks, to +TICKS or -TICKS from now, or to the next
+        sunrise, day, noon, sunset, night or midnight";
+
+/// Settings file, relative to the working directory
+const SETTINGS_FILE: &str = "settings.toml";
+/// Side length of the crosshair, in logical pixels
+const CROSSHAIR_SIZE: f32 = 32.0;
+/// Seconds per game tick, in which block updates run
+const TICK: f32 = 0.05;
+/// Most ticks run in one frame, so the game doesn't fall further behind after a slow frame
+const MAX_TICKS_PER_FRAME: u32 = 5;
+
+/// Returns a model matrix that moves a unit cube onto the block at `position`, grown by `margin`
+/// on every side so that it isn't hidden by the block's own faces
+fn block_model((x, y, z): (i32, i32, i32), margin: f32) -> Mat4 {
+    let corner = Vec3(x as f32, y as f32, z as f32) - Vec3(margin, margin, margin);
+    Mat4::from_translation(corner) * Mat4::from_scale(Vec3(1.0, 1.0, 1.0) * (1.0 + 2.0 * margin))
+}
+
+/// Returns the model matrix of the chunk at **chunk position** (x, z)
+fn chunk_model(x: i32, z: i32) -> Mat4 {
+    Mat4::from_translation(Vec3(x as f32 * 16.0, 0.0, z as f32 * 16.0))
+}
+
+/// Returns a model matrix that stretches a unit cube over `aabb`
+fn aabb_model(aabb: &Aabb) -> Mat4 {
+    Mat4::from_translation(aabb.min) * Mat4::from_scale(aabb.max - aabb.min)
+}
+
+/// Returns a model matrix for a dropped item, which bobs up and down and spins around its centre
+fn item_model(entity: &Entity) -> Mat4 {
+    let (size, _) = entity.size();
+    let bob = (entity.age * 2.0).sin() * 0.1 + 0.1;
+    let spin = Quat::from_axis_angle(Vec3(0.0, 1.0, 0.0), entity.age * 1.5);
+    // moves the cube's centre to the origin, spins it and moves it onto the item
+    let centre = Vec3(0.5, 0.0, 0.5);
+    Mat4::from_translation(entity.position + Vec3(0.0, bob, 0.0))
+        * Mat4::from(spin)
+        * Mat4::from_scale(Vec3(size, size, size))
+        * Mat4::from_translation(-centre)
+}
+
+fn main() -> ExitCode {
+    let args: Vec<String> = std::env::args().skip(1).collect();
+    let result = match args.first().map(String::as_str) {
+        Some("import-heightmap") => import_heightmap_command(&args[1..]),
+        Some("render-map") => render_map_command(&args[1..]),
+        Some("set-time") => set_time_command(&args[1..]),
+        Some("help" | "--help" | "-h") => {
+            println!("{}", USAGE);
+            Ok(())
+        }
+        Some(dir) if args.len() == 1 => run(Some(WorldSave::new(dir))),
+        None => run(None),
+        _ => Err(USAGE.to_string()),
+    };
+    match result {
+        Ok(()) => ExitCode::SUCCESS,
+        Err(e) => {
+            eprintln!("{}", e);
+            ExitCode::FAILURE
+        }
+    }
+}
+
+fn import_heightmap_command(args: &[String]) -> Result<(), String> {
+    let mut positional = vec![];
+    let mut colour_map = None;
+    let mut options = HeightmapOptions::default();
+    let mut args = args.iter();
+    while let Some(arg) = args.next() {
+        let mut value = || args.next().ok_or(format!("{} needs a value", arg));
+        match arg.as_str() {
+            "--colours" => colour_map = Some(value()?),
+            "--min-height" => {
+                options.min_height = value()?.parse().map_err(|e| format!("{}: {}", arg, e))?
+            }
+            "--max-height" => {
+                options.max_height = value()?.parse().map_err(|e| format!("{}: {}", arg, e))?
+            }
+            _ => positional.push(arg),
+        }
+    }
+    let [heightmap, world_dir] = positional[..] else {
+        return Err(USAGE.to_string());
+    };
+    let world = import_heightmap(Path::new(heightmap), colour_map.map(Path::new), &options)
+        .map_err(|e| e.to_string())?;
+    WorldSave::new(world_dir)
+        .save(&world)
+        .map_err(|e| format!("failed to save world: {}", e))?;
+    println!("Imported {} chunks into {}", world.chunks.len(), world_dir);
+    Ok(())
+}
+
+fn parse_column(arg: &str, value: Option<&String>) -> Result<(i32, i32), String> {
+    let value = value.ok_or(format!("{} needs a value", arg))?;
+    let parsed = value
+        .split_once(',')
+        .and_then(|(x, z)| Some((x.trim().parse().ok()?, z.trim().parse().ok()?)));
+    parsed.ok_or(format!("{}: expected X,Z but got '{}'", arg, value))
+}
+
+fn render_map_command(args: &[String]) -> Result<(), String> {
+    let mut positional = vec![];
+    let (mut from, mut to) = (None, None);
+    let mut generate = false;
+    let mut options = MapOptions::default();
+    let mut args = args.iter();
+    while let Some(arg) = args.next() {
+        match arg.as_str() {
+            "--from" => from = Some(parse_column(arg, args.next())?),
+            "--to" => to = Some(parse_column(arg, args.next())?),
+            "--grid" => options.chunk_grid = true,
+            "--no-shading" => options.shade_height = false,
+            "--generate" => generate = true,
+            _ => positional.push(arg),
+        }
+    }
+    let [world_dir, output] = positional[..] else {
+        return Err(USAGE.to_string());
+    };
+    let save = WorldSave::new(world_dir);
+    if !save.exists() {
+        return Err(format!("there is no world in {}", world_dir));
+    }
+    let (from, to) = match (from, to) {
+        (Some(from), Some(to)) => (from, to),
+        (from, to) => {
+            let bounds = saved_bounds(&save).map_err(|e| e.to_string())?;
+            let (min, max) = bounds.ok_or("the world has no chunks, pass --from and --to")?;
+            (from.unwrap_or(min), to.unwrap_or(max))
+        }
+    };
+    let map = render_saved_map(&save, from, to, generate, &options)
+        .map_err(|e| format!("failed to read world: {}", e))?;
+    map.save(output)
+        .map_err(|e| format!("failed to write {}: {}", output, e))?;
+    Ok(())
+}
+
+fn set_time_command(args: &[String]) -> Result<(), String> {
+    let [world_dir, change] = args else {
+        return Err(USAGE.to_string());
+    };
+    let save = WorldSave::new(world_dir);
+    if !save.exists() {
+        return Err(format!("there is no world in {}", world_dir));
+    }
+    let mut world = save
+        .load_empty()
+        .map_err(|e| format!("failed to read world: {}", e))?;
+    let time = adjust_time(world.time(), change).ok_or(format!("invalid time '{}'", change))?;
+    world.set_time(time);
+    // the world has no chunks loaded, so this only writes world.dat
+    save.save(&world)
+        .map_err(|e| format!("failed to save world: {}", e))?;
+    let (day, hour, minute) = clock(time);
+    println!(
+        "Set the time to day {}, {:02}:{:02} ({})",
+        day, hour, minute, time
+    );
+    Ok(())
+}
+
+/// Loads the built-in recipes and the recipes in `recipes.txt` in the world directory, if there
+/// are any, checking that all blocks they name exist
+fn load_recipes(save: &Option<WorldSave>, world: &World) -> Result<RecipeBook, String> {
+    let mut recipes = RecipeBook::parse(DEFAULT_RECIPES, world)
+        .map_err(|e| format!("invalid built-in recipe: {}", e))?;
+    if let Some(path) = save.as_ref().map(|save| save.dir().join("recipes.txt")) {
+        if path.is_file() {
+            let text = std::fs::read_to_string(&path)
+                .map_err(|e| format!("failed to read {}: {}", path.display(), e))?;
+            let world_recipes = RecipeBook::parse(&text, world)
+                .map_err(|e| format!("invalid recipe in {}: {}", path.display(), e))?;
+            recipes.extend(world_recipes);
+        }
+    }
+    Ok(recipes)
+}
+
+/// Loads the world, entities, player, waypoints and recipes of a save, or creates a new world if
+/// there is no save or nothing has been saved in it yet
+fn load_game(
+    save: &Option<WorldSave>,
+) -> Result<(World, Entities, Player, Waypoints, RecipeBook), String> {
+    let mut world = match save {
+        Some(save) if save.exists() => save
+            .load()
+            .map_err(|e| format!("failed to load world from {}: {}", save.dir().display(), e))?,
+        _ => World::new(),
+    };
+    for x in -2..=2 {
+        for z in -2..=2 {
+            if !world.chunk_exists(x, z) {
+                world.generate_chunk(x, z);
+            }
+        }
+    }
+    let recipes = load_recipes(save, &world)?;
+    let seed = world.world_gen().seed();
+    let entities = match save {
+        Some(save) => Entities::load(save.dir(), seed).unwrap_or_else(|e| {
+            eprintln!("failed to load entities: {}", e);
+            Entities::new(seed)
+        }),
+        None => Entities::new(seed),
+    };
+    let player = match save {
+        Some(save) => Player::load(save.dir()).unwrap_or_else(|e| {
+            eprintln!("failed to load player: {}", e);
+            Player::default()
+        }),
+        None => Player::default(),
+    };
+    let waypoints = match save {
+        Some(save) => Waypoints::load(save.dir()).unwrap_or_else(|e| {
+            eprintln!("failed to load waypoints: {}", e);
+            Waypoints::default()
+        }),
+        None => Waypoints::default(),
+    };
+    Ok((world, entities, player, waypoints, recipes))
+}
+
+/// Records a key going down or up
+fn record_key(input_state: &mut InputState, input: KeyboardInput) {
+    if let Some(keycode) = input.virtual_keycode {
+        match input.state {
+            ElementState::Pressed => input_state.press(Input::Key(keycode)),
+            ElementState::Released => input_state.release(Input::Key(keycode)),
+        }
+    }
+}
+
+/// Locks and hides the cursor while playing, or frees it for menus
+fn grab_cursor(display: &glium::Display, grab: bool) {
+    let window = display.gl_window();
+    let mode = if grab {
+        CursorGrabMode::Locked
+    } else {
+        CursorGrabMode::None
+    };
+    if let Err(e) = window.window().set_cursor_grab(mode) {
+        eprintln!("failed to change the cursor grab: {}", e);
+    }
+    window.window().set_cursor_visible(!grab);
+}
+
+/// Switches the window to or from fullscreen, if it isn't already
+fn apply_fullscreen(display: &glium::Display, state: &mut WindowState, fullscreen: bool) {
+    if state.fullscreen == fullscreen {
+        return;
+    }
+    state.fullscreen = fullscreen;
+    let window = display.gl_window();
+    let monitor = window.window().current_monitor();
+    let mode = fullscreen.then_some(Fullscreen::Borderless(monitor));
+    window.window().set_fullscreen(mode);
+}
+
+fn save_world(
+    save: &Option<WorldSave>,
+    world: &World,
+    entities: &Entities,
+    waypoints: &Waypoints,
+    player: &Player,
+) {
+    if let Some(save) = save {
+        if let Err(e) = save.save(world) {
+            eprintln!("failed to save world to {}: {}", save.dir().display(), e);
+        }
+        if let Err(e) = entities.save(save.dir(), world) {
+            eprintln!("failed to save entities to {}: {}", save.dir().display(), e);
+        }
+        if let Err(e) = waypoints.save(save.dir()) {
+            eprintln!(
+                "failed to save waypoints to {}: {}",
+                save.dir().display(),
+                e
+            );
+        }
+        if let Err(e) = player.save(save.dir()) {
+            eprintln!("failed to save player to {}: {}", save.dir().display(), e);
+        }
+    }
+}
+
+fn run(mut save: Option<WorldSave>) -> Result<(), String> {
     let event_loop = glutin::event_loop::EventLoop::new();
     let wb = glutin::window::WindowBuilder::new().with_title("Raft");
     let cb = glutin::ContextBuilder::new().with_depth_buffer(24);
     let display = glium::Display::new(wb, cb, &event_loop).unwrap();
-    let monitor_handle = display
-        .gl_window()
-        .window()
-        .available_monitors()
-        .next()
-        .unwrap();
-    let fs = Fullscreen::Borderless(Some(monitor_handle));
-    let fullscreen = false;
-    if fullscreen {
-        display.gl_window().window().set_fullscreen(Some(fs));
-    }
-
-    display.gl_window().window().set_cursor_visible(false);
-    display
-        .gl_window()
-        .window()
-        .set_cursor_grab(CursorGrabMode::Locked)
-        .unwrap();
+    let settings_path = Path::new(SETTINGS_FILE);
+    let mut settings = match Settings::load(settings_path) {
+        Ok((settings, warnings)) => {
+            for warning in warnings {
+                eprintln!("{}: {}", settings_path.display(), warning);
+            }
+            settings
+        }
+        Err(e) => {
+            eprintln!(
+                "failed to load {}, using the default settings: {}",
+                settings_path.display(),
+                e
+            );
+            Settings::default()
+        }
+    };
+    grab_cursor(&display, true);
+    let mut window = WindowState::new(
+        display.get_framebuffer_dimensions(),
+        display.gl_window().window().scale_factor(),
+    );
+    apply_fullscreen(&display, &mut window, settings.fullscreen);
 
     let vertex_shader_src = include_str!("vertex.glsl");
     let fragment_shader_src = include_str!("fragment.glsl");
 
-    let crosshair_vertex_src = include_str!("image_vertex.glsl");
-    let crosshair_fragment_src = include_str!("image_fragment.glsl");
-
     let image = image::load(
         Cursor::new(&include_bytes!("diffuse.jpg")),
         image::ImageFormat::Jpeg,
@@ -92,44 +404,211 @@ fn main() {
     let image_dimensions = image.dimensions();
     let image =
         glium::texture::RawImage2d::from_raw_rgba_reversed(&image.into_raw(), image_dimensions);
-    let crosshair_tex = glium::texture::Texture2d::new(&display, image).unwrap();
+    let mut sprites = SpriteBatch::new(&display);
+    let crosshair_tex =
+        sprites.add_texture(glium::texture::Texture2d::new(&display, image).unwrap());
+    let font = Font::new(&display, &mut sprites);
 
-    let crosshair_program =
-        glium::Program::from_source(&display, crosshair_vertex_src, crosshair_fragment_src, None)
-            .unwrap();
+    let crack_textures: Vec<glium::texture::Texture2d> = (0..BREAK_STAGES)
+        .map(|stage| {
+            let image = crack_image(stage);
+            let dimensions = image.dimensions();
+            let image = glium::texture::RawImage2d::from_raw_rgba(image.into_raw(), dimensions);
+            glium::texture::Texture2d::new(&display, image).unwrap()
+        })
+        .collect();
+    let overlay_program = glium::Program::from_source(
+        &display,
+        include_str!("overlay_vertex.glsl"),
+        include_str!("overlay_fragment.glsl"),
+        None,
+    )
+    .unwrap();
+    let outline_buffer = glium::VertexBuffer::new(&display, &Cube::edges()).unwrap();
+    let crack_buffer = glium::VertexBuffer::new(&display, &Cube::faces()).unwrap();
+    let entity_buffer = glium::VertexBuffer::new(&display, &Cube::faces()).unwrap();
 
     let program =
         glium::Program::from_source(&display, vertex_shader_src, fragment_shader_src, None)
             .unwrap();
 
-    let mut pressed_keys = HashSet::new();
+    let sky_program = glium::Program::from_source(
+        &display,
+        include_str!("sky_vertex.glsl"),
+        include_str!("sky_fragment.glsl"),
+        None,
+    )
+    .unwrap();
+    let sky_buffer = glium::VertexBuffer::new(&display, &dome(16, 32)).unwrap();
+
+    let shadow_program = glium::Program::from_source(
+        &display,
+        include_str!("shadow_vertex.glsl"),
+        include_str!("shadow_fragment.glsl"),
+        None,
+    )
+    .unwrap();
+    // recreated when the number of cascades or their resolution changes
+    let mut shadow_maps = glium::texture::DepthTexture2dArray::empty(&display, 1, 1, 1).unwrap();
+
+    let colour_program = glium::Program::from_source(
+        &display,
+        include_str!("colour_vertex.glsl"),
+        include_str!("colour_fragment.glsl"),
+        None,
+    )
+    .unwrap();
+
+    let mut input_state = InputState::default();
+    #[cfg(feature = "gamepad")]
+    let mut gamepad_backend = GamepadBackend::new()
+        .map_err(|e| eprintln!("gamepads are not available: {}", e))
+        .ok();
+    #[cfg(feature = "gamepad")]
+    let mut gamepad = Gamepad::default();
 
     let mut camera = Camera::default();
     let mut polygon_lines = false;
 
-    let mut world = World::new();
-    world.generate_chunk(0, 0);
-    camera.position = Vec3(8.0, 100.0, 8.0);
-    //world.create_empty_chunk(0, 0);
+    let (mut world, mut entities, mut player, mut waypoints, mut recipes) = load_game(&save)?;
+    camera.position = player.position.unwrap_or(Vec3(8.0, 100.0, 8.0));
+
+    let mut beacons: Option<glium::VertexBuffer<Vertex>> = None;
+    // name of the waypoint that is being typed in, if any
+    let mut waypoint_name: Option<String> = None;
+    let mut waypoint_requested = false;
+    let mut minimap = Minimap::default();
+    let mut crafting_table = CraftingTable::default();
+    let mut crafting_open = false;
+    let mut cursor_position = (0.0, 0.0);
+    let mut mining = Mining::default();
+    let mut last_frame = Instant::now();
+    // seconds of game time that haven't been ticked yet
+    let mut tick_time = 0.0;
+    let mut debug_info = DebugInfo::default();
+    let mut debug_open = false;
+    // worlds are selected from the directory the current world is in
+    let worlds_dir = match save.as_ref().and_then(|save| save.dir().parent()) {
+        Some(parent) if parent != Path::new("") => parent.to_path_buf(),
+        Some(_) => PathBuf::from("."),
+        None => PathBuf::from("worlds"),
+    };
+    let mut menu = Menu::new(worlds_dir);
+    let mut ui_input = UiInput::default();
 
     event_loop.run(move|ev, _, control_flow| {
         let mut target = display.draw();
 
-        target.clear_color_and_depth((0.0, 0.0, 1.0, 1.0), 1.0);
+        let now = Instant::now();
+        let dt = (now - last_frame).as_secs_f32();
+        last_frame = now;
 
-        camera.handle_keys(&pressed_keys, &mut world);
-
-        if pressed_keys.contains(&VirtualKeyCode::Escape) {
-            *control_flow = glutin::event_loop::ControlFlow::Exit; //TODO: why does this not work
-            std::process::exit(0);
+        camera.sensitivity = settings.mouse_sensitivity;
+        #[cfg(feature = "gamepad")]
+        if let Some(backend) = &mut gamepad_backend {
+            for event in backend.poll() {
+                if window.focused {
+                    gamepad.handle(event, &settings.gamepad, &mut input_state);
+                }
+            }
+        }
+        let actions = input_state.take_actions(&settings.bindings);
+        if actions.pressed(Action::Crafting) && !menu.is_open() {
+            crafting_open = !crafting_open;
+            if crafting_open {
+                // the mouse buttons go to the crafting grid until it is closed
+                input_state.clear();
+            } else {
+                crafting_table.clear(&mut player.inventory);
+            }
+            grab_cursor(&display, !crafting_open);
+        }
+        // the game is paused while the menu is open, and the player stands still while crafting
+        let playing = !menu.is_open() && !crafting_open;
+        if !menu.is_open() {
+            let idle = ActionState::default();
+            camera.handle_actions(if playing { &actions } else { &idle }, dt, &world);
+            entities.tick(&mut world, dt);
+            tick_time += dt;
+            let mut ticks = 0;
+            while tick_time >= TICK && ticks < MAX_TICKS_PER_FRAME {
+                world.tick();
+                tick_time -= TICK;
+                ticks += 1;
+            }
+            tick_time = tick_time.min(TICK);
+            entities.spawn_mobs(&world, camera.position, dt);
+            entities.pick_up(&camera.aabb(), &mut player.inventory);
         }
+        if playing && actions.pressed(Action::Throw) {
+            entities.throw(camera.position - Vec3(0.0, 0.125, 0.0), camera.get_direction());
+        }
+        if playing {
+            let look_speed = settings.gamepad.look_speed * dt;
+            let yaw = actions.strength(Action::LookRight) - actions.strength(Action::LookLeft);
+            let pitch = actions.strength(Action::LookDown) - actions.strength(Action::LookUp);
+            camera.turn(yaw * look_speed, pitch * look_speed);
+        }
+        let mut hit = raycast(&world, camera.position, camera.get_direction(), settings.reach);
+        let targeted = hit.and_then(|hit| {
+            let (x, y, z) = hit.block;
+            let object = world.block_type(world.block_id_at(x, y, z)?)?;
+            Some((hit.block, object))
+        });
+        if let Some((x, y, z)) = mining.update(targeted, playing && actions.held(Action::Attack), dt) {
+            if let Some(id) = world.block_id_at(x, y, z) {
+                entities.drop_block(&world, (x, y, z), id);
+            }
+            world.set_block(x, y, z, AIR);
+            minimap.invalidate_chunk(x.div_euclid(16), z.div_euclid(16));
+            // the broken block is no longer there to outline
+            hit = None;
+        }
+        if let Some(hit) = hit.filter(|_| playing && actions.pressed(Action::Use)) {
+            let (x, y, z) = hit.adjacent();
+            let inside_player = camera.aabb().intersects(&Aabb::block((x, y, z)));
+            if !inside_player && world.block_id_at(x, y, z) == Some(AIR) {
+                if let Some(id) = player.inventory.take_selected() {
+                    world.set_block(x, y, z, id);
+                    minimap.invalidate_chunk(x.div_euclid(16), z.div_euclid(16));
+                }
+            }
+        }
+        if playing && actions.pressed(Action::NextSlot) {
+            player.inventory.scroll(1);
+        }
+        if playing && actions.pressed(Action::PreviousSlot) {
+            player.inventory.scroll(-1);
+        }
+        for slot in 0..HOTBAR_SIZE {
+            if playing && actions.pressed(Action::SelectSlot(slot)) {
+                player.inventory.select(slot);
+            }
+        }
+        if playing && actions.pressed(Action::Waypoint) {
+            waypoint_requested = true;
+        }
+        // starts once the input is released, so that the character it types doesn't end up in the name
+        if waypoint_requested && !actions.held(Action::Waypoint) {
+            waypoint_requested = false;
+            waypoint_name = Some(String::new());
+            input_state.clear();
+        }
+        debug_info.update_times.push(now.elapsed().as_secs_f32() * 1000.0);
+        debug_info.frame_times.push(dt * 1000.0);
 
-        let perspective = perspective::create_perspective(&target);
+        let fov = perspective::vertical_fov(settings.fov.to_radians(), settings.fov_axis, window.aspect()) * camera.fov_scale();
+        let perspective = perspective::create_perspective(fov, window.aspect(), settings.zfar);
 
-        let light = Vec3(1.4, -0.4, -0.7);
+        let sky = Sky::at(world.time(), tick_time / TICK);
+        let [r, g, b] = sky.horizon;
+        target.clear_color_and_depth((r, g, b, 1.0), 1.0);
+        let scattering = if settings.scattering { sky.scattering() } else { [0.0; 3] };
+        let fog = Fog::new(settings.fog, settings.fog_start, settings.zfar);
 
         let direction = camera.get_direction();
-        let view = view_matrix::view_matrix(&[camera.position.0, camera.position.1, camera.position.2], &[direction.0, direction.1, direction.2], &[0.0, 1.0, 0.0]);
+        let view = Mat4::look_to(camera.position, direction, Vec3(0.0, 1.0, 0.0));
+        target.draw(&sky_buffer, glium::index::NoIndices(glium::index::PrimitiveType::TrianglesList), &sky_program, &uniform! { perspective: perspective, view: view, zenith: sky.zenith, horizon: sky.horizon, sun: sky.sun.tuple(), moon: sky.moon.tuple(), sky_axis: sky.axis().tuple(), sky_angle: sky.angle, stars: sky.stars, scattering: scattering }, &Default::default()).unwrap();
 
         let params = glium::DrawParameters {
             depth: glium::Depth {
@@ -150,64 +629,327 @@ fn main() {
             .. Default::default()
         };
 
+        let cascades = if settings.shadows {
+            let view_angle = (fov, window.aspect());
+            cascades(&settings.shadow_splits, camera.position, direction, view_angle, sky.light(), settings.shadow_resolution)
+        } else {
+            vec![]
+        };
+        // shaders still need a shadow map when shadows are off, so they get a tiny one
+        let (size, layers) = match cascades.len() {
+            0 => (1, 1),
+            count => (settings.shadow_resolution, count as u32),
+        };
+        if shadow_maps.dimensions() != (size, size) || shadow_maps.array_size() != layers {
+            shadow_maps = glium::texture::DepthTexture2dArray::empty(&display, size, size, layers).unwrap();
+        }
+        let shadow_params = glium::DrawParameters {
+            depth: glium::Depth {
+                test: glium::draw_parameters::DepthTest::IfLess,
+                write: true,
+                .. Default::default()
+            },
+            .. Default::default()
+        };
+        for (layer, cascade) in cascades.iter().enumerate() {
+            let mut shadow_frame = glium::framebuffer::SimpleFrameBuffer::depth_only(&display, shadow_maps.main_level().layer(layer as u32).unwrap()).unwrap();
+            shadow_frame.clear_depth(1.0);
+            for ((x, z), chunk) in world.chunks.iter_mut() {
+                if chunk.mesh.is_none() {
+                    chunk.prepare(&display);
+                }
+                let (vertices, indices) = chunk.mesh.as_ref().unwrap();
+                shadow_frame.draw(vertices, indices, &shadow_program, &uniform! { light_matrix: cascade.matrix, model: chunk_model(*x, *z) }, &shadow_params).unwrap();
+            }
+        }
+        let mut light_matrices = [Mat4::IDENTITY; MAX_CASCADES];
+        let (mut cascade_ends, mut cascade_texels) = ([0.0f32; MAX_CASCADES], [0.0f32; MAX_CASCADES]);
+        for (i, cascade) in cascades.iter().enumerate() {
+            light_matrices[i] = cascade.matrix;
+            cascade_ends[i] = cascade.end;
+            cascade_texels[i] = cascade.texel;
+        }
+        let shadow_sampler = shadow_maps.sampled()
+            .depth_texture_comparison(Some(glium::uniforms::DepthTextureComparison::LessOrEqual))
+            .magnify_filter(glium::uniforms::MagnifySamplerFilter::Linear)
+            .minify_filter(glium::uniforms::MinifySamplerFilter::Linear)
+            .wrap_function(glium::uniforms::SamplerWrapFunction::Clamp);
+
+        debug_info.rendered_chunks = 0;
+        debug_info.vertices = 0;
+        let view_projection = perspective * view;
         for ((x, z), chunk) in world.chunks.iter_mut() {
-            let model = [
-                [1.0, 0.0, 0.0, 0.0],
-                [0.0, 1.0, 0.0, 0.0],
-                [0.0, 0.0, 1.0, 0.0],
-                [*x as f32 * 16.0, 0.0, *z as f32 * 16.0, 1.0f32]
-            ];
+            let corner = Vec3(*x as f32 * 16.0, 0.0, *z as f32 * 16.0);
+            if !box_in_view(view_projection, corner, corner + Vec3(16.0, 256.0, 16.0)) {
+                continue;
+            }
+            let model = chunk_model(*x, *z);
             if chunk.mesh.is_none() {
                 chunk.prepare(&display);
             }
-            target.draw(&chunk.mesh.as_ref().unwrap().0, &chunk.mesh.as_ref().unwrap().1, &program, &uniform! { perspective: perspective, model: model, view: view, u_light: light.tuple(), diffuse_tex: &diffuse_texture, normal_tex: &normal_map }, &params).unwrap();
+            let uniforms = uniform! { perspective: perspective, model: model, view: view, u_light: sky.light().tuple(), u_skylight: sky.skylight, diffuse_tex: &diffuse_texture, normal_tex: &normal_map, camera: camera.position.tuple(), fog_mode: fog.shader_mode(), fog_start: fog.start, fog_end: fog.end, fog_density: fog.density, height_fog_base: HEIGHT_FOG_BASE, height_fog_falloff: HEIGHT_FOG_FALLOFF, height_fog_thickness: HEIGHT_FOG_THICKNESS, horizon: sky.horizon, sun: sky.sun.tuple(), scattering: scattering, shadow_maps: shadow_sampler, cascade_ends: cascade_ends, cascade_texels: cascade_texels, cascade_count: cascades.len() as i32 }
+                .add("light_matrices[0]", light_matrices[0])
+                .add("light_matrices[1]", light_matrices[1])
+                .add("light_matrices[2]", light_matrices[2])
+                .add("light_matrices[3]", light_matrices[3]);
+            target.draw(&chunk.mesh.as_ref().unwrap().0, &chunk.mesh.as_ref().unwrap().1, &program, &uniforms, &params).unwrap();
+            debug_info.rendered_chunks += 1;
+            debug_info.vertices += chunk.mesh.as_ref().unwrap().0.len();
+        }
+        debug_info.loaded_chunks = world.chunks.len();
+        debug_info.time = world.time();
+        debug_info.window_size = window.size;
+        debug_info.scale_factor = window.scale_factor;
+        debug_info.position = camera.position;
+        debug_info.direction = direction;
+        debug_info.angles = (camera.yaw(), camera.pitch());
+        debug_info.looked_at = hit.and_then(|hit| {
+            let (x, y, z) = hit.block;
+            let object = world.block_type(world.block_id_at(x, y, z)?)?;
+            Some((hit.block, object.name.to_string()))
+        });
+
+        if let Some(hit) = hit {
+            let overlay_params = glium::DrawParameters {
+                depth: glium::Depth {
+                    test: glium::draw_parameters::DepthTest::IfLess,
+                    write: false,
+                    .. Default::default()
+                },
+                blend: glium::Blend::alpha_blending(),
+                line_width: Some(2.0),
+                .. Default::default()
+            };
+            let (x, y, z) = hit.block;
+            let stage = world.block_id_at(x, y, z).and_then(|id| world.block_type(id)).and_then(|object| mining.stage(object));
+            if let Some((_, stage)) = stage.filter(|(block, _)| *block == hit.block) {
+                let crack = crack_textures[stage].sampled().magnify_filter(glium::uniforms::MagnifySamplerFilter::Nearest);
+                target.draw(&crack_buffer, glium::index::NoIndices(glium::index::PrimitiveType::TrianglesList), &overlay_program, &uniform! { perspective: perspective, view: view, model: block_model(hit.block, 0.001), tex: crack, tint: (1.0f32, 1.0f32, 1.0f32, 1.0f32) }, &overlay_params).unwrap();
+            }
+            target.draw(&outline_buffer, glium::index::NoIndices(glium::index::PrimitiveType::LinesList), &colour_program, &uniform! { perspective: perspective, view: view, model: block_model(hit.block, 0.002), colour: (0.0f32, 0.0f32, 0.0f32, 0.7f32) }, &overlay_params).unwrap();
+        }
+
+        for (_, entity) in entities.iter() {
+            let block_colour = |id| {
+                let [r, g, b] = world.block_type(id).map_or([255; 3], |object| object.colour).map(|c| c as f32 / 255.0);
+                (r, g, b, 1.0f32)
+            };
+            let (model, colour) = match entity.kind {
+                EntityKind::Item(stack) => (item_model(entity), block_colour(stack.id)),
+                EntityKind::Projectile => (aabb_model(&entity.aabb()), (0.4, 0.4, 0.4, 1.0)),
+                EntityKind::Mob(_) => (aabb_model(&entity.aabb()), (0.9, 0.5, 0.6, 1.0)),
+                EntityKind::FallingBlock(id) => (aabb_model(&entity.aabb()), block_colour(id)),
+            };
+            target.draw(&entity_buffer, glium::index::NoIndices(glium::index::PrimitiveType::TrianglesList), &colour_program, &uniform! { perspective: perspective, view: view, model: model, colour: colour }, &params).unwrap();
+        }
+
+        if beacons.is_none() {
+            let vertices: Vec<Vertex> = waypoints.waypoints.iter().flat_map(|w| w.beacon()).collect();
+            beacons = Some(glium::VertexBuffer::new(&display, &vertices).unwrap());
+        }
+        let beacon_params = glium::DrawParameters {
+            depth: glium::Depth {
+                test: glium::draw_parameters::DepthTest::IfLess,
+                write: false,
+                .. Default::default()
+            },
+            blend: glium::Blend::alpha_blending(),
+            .. Default::default()
+        };
+        let mut first_vertex = 0;
+        for waypoint in &waypoints.waypoints {
+            let vertex_count = waypoint.beacon().len();
+            let slice = beacons.as_ref().unwrap().slice(first_vertex..first_vertex + vertex_count).unwrap();
+            let [r, g, b] = waypoint.colour.map(|c| c as f32 / 255.0);
+            target.draw(slice, glium::index::NoIndices(glium::index::PrimitiveType::TrianglesList), &colour_program, &uniform! { perspective: perspective, view: view, model: Mat4::IDENTITY, colour: (r, g, b, 0.5f32) }, &beacon_params).unwrap();
+            first_vertex += vertex_count;
         }
 
-        let crosshair_shape = vec![
-            Vertex2D { position: [ -0.05, -0.05], tex_coords: [0.0, 0.0] },
-            Vertex2D { position: [ -0.05, 0.05], tex_coords: [0.0, 1.0] },
-            Vertex2D { position: [ 0.05, -0.05], tex_coords: [1.0, 0.0] },
-            Vertex2D { position: [ 0.05, 0.05], tex_coords: [1.0, 1.0] },
-            Vertex2D { position: [ 0.05, -0.05], tex_coords: [1.0, 0.0] },
-            Vertex2D { position: [ -0.05, 0.05], tex_coords: [0.0, 1.0] },
-        ];
-        let crosshair_buffer = glium::VertexBuffer::new(&display, &crosshair_shape).unwrap();
-        target.draw(&crosshair_buffer, glium::index::NoIndices(glium::index::PrimitiveType::TrianglesList), &crosshair_program, &uniform! {tex: &crosshair_tex}, &params).unwrap();
+        let screen = window.screen();
+        let ui_scale = window.ui_scale();
+        let (centre_x, centre_y) = (screen.0 / 2.0, screen.1 / 2.0);
+        let half_crosshair = CROSSHAIR_SIZE * ui_scale / 2.0;
+        let crosshair = ([centre_x - half_crosshair, centre_y - half_crosshair], [centre_x + half_crosshair, centre_y + half_crosshair]);
+        sprites.rect(crosshair_tex, crosshair, FULL_TEXTURE, [1.0; 4]);
+        Hotbar::draw(&mut sprites, &font, &world, &player.inventory, screen.0, ui_scale);
+        minimap.draw(&display, &mut sprites, &world, camera.position, camera.yaw(), &waypoints, screen);
+        if crafting_open {
+            CraftingView::draw(&mut sprites, &font, &world, &crafting_table, &recipes, screen, ui_scale);
+        }
+        if let Some(name) = &waypoint_name {
+            let prompt = format!("Waypoint name: {}_\nPress Enter to create it", name);
+            font.draw(&mut sprites, &prompt, [centre_x, centre_y - 40.0 * ui_scale], 2.0 * ui_scale, Align::Centre, [1.0; 4]);
+        }
+        if debug_open {
+            DebugOverlay::draw(&mut sprites, &font, &debug_info, screen.1);
+        }
+        let (commands, menu_action) = menu.build(&ui_input, screen, ui_scale, &mut settings);
+        apply_fullscreen(&display, &mut window, settings.fullscreen);
+        ui_input.end_frame();
+        render(&commands, &mut sprites, &font);
+        sprites.draw(&display, &mut target);
 
         target.finish().unwrap();
         let next_frame_time = std::time::Instant::now() + std::time::Duration::from_nanos(16_666_667);
         *control_flow = glutin::event_loop::ControlFlow::WaitUntil(next_frame_time);
+        match menu_action {
+            Some(MenuAction::Resume) => grab_cursor(&display, true),
+            Some(MenuAction::SaveSettings) => {
+                if let Err(e) = settings.save(settings_path) {
+                    eprintln!("failed to save {}: {}", settings_path.display(), e);
+                }
+            }
+            Some(MenuAction::SaveAndQuit) => {
+                player.position = Some(camera.position);
+                save_world(&save, &world, &entities, &waypoints, &player);
+                *control_flow = glutin::event_loop::ControlFlow::Exit;
+            }
+            Some(MenuAction::OpenWorld(dir)) => {
+                player.position = Some(camera.position);
+                save_world(&save, &world, &entities, &waypoints, &player);
+                let new_save = Some(WorldSave::new(dir));
+                match load_game(&new_save) {
+                    Ok(game) => {
+                        (world, entities, player, waypoints, recipes) = game;
+                        save = new_save;
+                        camera.position = player.position.unwrap_or(Vec3(8.0, 100.0, 8.0));
+                        minimap.clear();
+                        mining.reset();
+                        beacons = None;
+                    }
+                    Err(e) => eprintln!("{}", e),
+                }
+                grab_cursor(&display, true);
+            }
+            None => (),
+        }
         match ev {
-            DeviceEvent { event: MouseMotion { delta }, .. } => {
+            DeviceEvent { event: MouseMotion { delta }, .. } if window.focused && !crafting_open && !menu.is_open() => {
                 camera.rotate(delta);
             },
             glutin::event::Event::WindowEvent { event, .. } => match event {
-                glutin::event::WindowEvent::MouseInput { button, state, .. } => {
-                    println!("handling click");
-                    let mut vec = camera.position;
-                    let direction = camera.get_direction();
-                    loop {
-                        vec += direction;
-                        println!("{}", vec);
-                        if let Some(block) = world.block_at(vec.0 as i32, vec.1 as i32, vec.2 as i32) {
-                            println!("Hit {}, {}, {}", vec.0 as i32, vec.1 as i32, vec.2 as i32);
-                            break;
+                glutin::event::WindowEvent::Resized(size) => window.size = (size.width, size.height),
+                glutin::event::WindowEvent::ScaleFactorChanged { scale_factor, new_inner_size } => {
+                    window.scale_factor = scale_factor;
+                    window.size = (new_inner_size.width, new_inner_size.height);
+                }
+                glutin::event::WindowEvent::Focused(focused) => {
+                    window.focused = focused;
+                    // keys held when the focus left would never be released
+                    input_state.clear();
+                    grab_cursor(&display, focused && !menu.is_open() && !crafting_open);
+                }
+                glutin::event::WindowEvent::CursorMoved { position, .. } => {
+                    cursor_position = (position.x as f32, position.y as f32);
+                    ui_input.cursor = window.ui_cursor(cursor_position);
+                }
+                glutin::event::WindowEvent::KeyboardInput { .. } | glutin::event::WindowEvent::MouseInput { .. } | glutin::event::WindowEvent::MouseWheel { .. } if !window.focused => (),
+                glutin::event::WindowEvent::KeyboardInput { input, .. } if input.state == ElementState::Pressed && input.virtual_keycode == Some(VirtualKeyCode::F11) => {
+                    settings.fullscreen = !settings.fullscreen;
+                    apply_fullscreen(&display, &mut window, settings.fullscreen);
+                    if let Err(e) = settings.save(settings_path) {
+                        eprintln!("failed to save {}: {}", settings_path.display(), e);
+                    }
+                }
+                glutin::event::WindowEvent::MouseInput { state, button: glutin::event::MouseButton::Left, .. } if menu.is_open() => {
+                    match state {
+                        ElementState::Pressed => ui_input.press(),
+                        ElementState::Released => ui_input.release(),
+                    }
+                }
+                glutin::event::WindowEvent::MouseInput { .. } if menu.is_open() => (),
+                glutin::event::WindowEvent::MouseWheel { delta, .. } if menu.is_open() => {
+                    ui_input.scroll += match delta {
+                        glutin::event::MouseScrollDelta::LineDelta(_, y) => y,
+                        glutin::event::MouseScrollDelta::PixelDelta(position) => position.y.signum() as f32,
+                    };
+                }
+                glutin::event::WindowEvent::ReceivedCharacter(c) if menu.is_open() => ui_input.typed.push(c),
+                glutin::event::WindowEvent::KeyboardInput { input, .. } if menu.is_open() && input.state == ElementState::Pressed => {
+                    match input.virtual_keycode {
+                        Some(VirtualKeyCode::Escape) if menu.back() == Some(MenuAction::Resume) => grab_cursor(&display, true),
+                        Some(VirtualKeyCode::Back) => ui_input.backspace += 1,
+                        Some(VirtualKeyCode::Return) => ui_input.enter = true,
+                        _ => (),
+                    }
+                }
+                glutin::event::WindowEvent::KeyboardInput { .. } if menu.is_open() => (),
+                glutin::event::WindowEvent::MouseInput { state: ElementState::Pressed, button, .. } if crafting_open => {
+                    match (CraftingView::slot_at(cursor_position, window.screen(), window.ui_scale()), button) {
+                        (Some(CraftingSlot::Grid(row, column)), glutin::event::MouseButton::Left) => crafting_table.place(row, column, &mut player.inventory),
+                        (Some(CraftingSlot::Grid(row, column)), glutin::event::MouseButton::Right) => crafting_table.take(row, column, &mut player.inventory),
+                        (Some(CraftingSlot::Result), _) => {
+                            crafting_table.craft(&recipes, &mut player.inventory);
                         }
+                        _ => (),
+                    }
+                }
+                glutin::event::WindowEvent::MouseInput { .. } if crafting_open => (),
+                glutin::event::WindowEvent::MouseInput { state, button, .. } => match state {
+                    ElementState::Pressed => input_state.press(Input::Mouse(button)),
+                    ElementState::Released => input_state.release(Input::Mouse(button)),
+                },
+                glutin::event::WindowEvent::MouseWheel { delta, .. } => {
+                    let steps = match delta {
+                        glutin::event::MouseScrollDelta::LineDelta(_, y) => y,
+                        glutin::event::MouseScrollDelta::PixelDelta(position) => position.y as f32,
+                    };
+                    if steps > 0.0 {
+                        input_state.tap(Input::WheelUp);
+                    } else if steps < 0.0 {
+                        input_state.tap(Input::WheelDown);
                     }
                 }
                 glutin::event::WindowEvent::CloseRequested => {
+                    player.position = Some(camera.position);
+                    save_world(&save, &world, &entities, &waypoints, &player);
                     *control_flow = glutin::event_loop::ControlFlow::Exit;
                 },
+                glutin::event::WindowEvent::ReceivedCharacter(c) => {
+                    if let Some(name) = &mut waypoint_name {
+                        if !c.is_control() {
+                            name.push(c);
+                        }
+                    }
+                }
+                glutin::event::WindowEvent::KeyboardInput { input, .. } if waypoint_name.is_some() => {
+                    match (input.virtual_keycode, input.state) {
+                        (Some(VirtualKeyCode::Back), ElementState::Pressed) => {
+                            waypoint_name.as_mut().unwrap().pop();
+                        }
+                        (Some(VirtualKeyCode::Escape), ElementState::Pressed) => waypoint_name = None,
+                        (Some(VirtualKeyCode::Return), ElementState::Pressed) => {
+                            waypoints.add(&waypoint_name.take().unwrap(), camera.position);
+                            beacons = None;
+                        }
+                        _ => (),
+                    }
+                }
+                glutin::event::WindowEvent::KeyboardInput { input, .. } if crafting_open && input.state == ElementState::Pressed && input.virtual_keycode == Some(VirtualKeyCode::Escape) => {
+                    crafting_open = false;
+                    crafting_table.clear(&mut player.inventory);
+                    grab_cursor(&display, true);
+                }
+                // only actions, which can close the grid again, while crafting
+                glutin::event::WindowEvent::KeyboardInput { input, .. } if crafting_open => record_key(&mut input_state, input),
                 glutin::event::WindowEvent::KeyboardInput { input, .. } => {
                     if let Some(keycode) = input.virtual_keycode {
+                        if keycode == VirtualKeyCode::Escape && input.state == ElementState::Pressed {
+                            menu.open();
+                            input_state.clear();
+                            grab_cursor(&display, false);
+                            return;
+                        }
+                        if keycode == VirtualKeyCode::F3 && input.state == ElementState::Pressed {
+                            debug_open = !debug_open;
+                        }
+                        if keycode == VirtualKeyCode::F4 && input.state == ElementState::Pressed {
+                            println!("{}\n", debug_info);
+                        }
                         if keycode == VirtualKeyCode::F1 {
                             polygon_lines = !polygon_lines;
                         }
-                        if input.state == ElementState::Pressed {
-                            pressed_keys.insert(keycode);
-                        } else {
-                            pressed_keys.remove(&keycode);
-                        }
+                        record_key(&mut input_state, input);
                     }
                 },
                 _ => (),
@@ -0,0 +1,159 @@
+use crate::save::WorldSave;
+use crate::world::World;
+use image::{Rgb, RgbImage};
+use std::io;
+
+/// Colour of columns whose chunk doesn't exist
+const BACKGROUND: [u8; 3] = [0, 0, 0];
+/// Colour of blocks that have no definition
+const UNKNOWN: [u8; 3] = [255, 0, 255];
+const GRID: [u8; 3] = [255, 255, 255];
+
+/// World columns from min (inclusive) to max (exclusive)
+pub type ColumnRange = ((i32, i32), (i32, i32));
+
+pub struct MapOptions {
+    /// Darken low and brighten high terrain, and shade slopes facing north
+    pub shade_height: bool,
+    /// Draw a line along the border of every chunk
+    pub chunk_grid: bool,
+}
+
+impl Default for MapOptions {
+    fn default() -> MapOptions {
+        MapOptions {
+            shade_height: true,
+            chunk_grid: false,
+        }
+    }
+}
+
+fn scale(colour: [u8; 3], factor: f32) -> [u8; 3] {
+    colour.map(|c| (c as f32 * factor).clamp(0.0, 255.0) as u8)
+}
+
+fn blend(a: [u8; 3], b: [u8; 3], t: f32) -> [u8; 3] {
+    [0, 1, 2].map(|i| (a[i] as f32 * (1.0 - t) + b[i] as f32 * t) as u8)
+}
+
+/// Renders a top-down map of world columns `min` (inclusive) to `max` (exclusive) with one pixel
+/// per column. Image x is world x and image y is world z.
+pub fn render_map(
+    world: &World,
+    min: (i32, i32),
+    max: (i32, i32),
+    options: &MapOptions,
+) -> RgbImage {
+    let width = (max.0 - min.0).max(0) as u32;
+    let depth = (max.1 - min.1).max(0) as u32;
+    let top = |x: i32, z: i32| {
+        world
+            .chunk_at_pos(x, z)
+            .and_then(|chunk| chunk.top_block(x.rem_euclid(16) as usize, z.rem_euclid(16) as usize))
+    };
+
+    let mut image = RgbImage::from_pixel(width, depth, Rgb(BACKGROUND));
+    for (px, pz, pixel) in image.enumerate_pixels_mut() {
+        let (x, z) = (min.0 + px as i32, min.1 + pz as i32);
+        let Some((height, id)) = top(x, z) else {
+            continue;
+        };
+        let mut colour = world.block_type(id).map_or(UNKNOWN, |object| object.colour);
+        if options.shade_height {
+            let mut factor = 0.7 + 0.5 * height as f32 / 255.0;
+            if let Some((north, _)) = top(x, z - 1) {
+                factor += match height.cmp(&north) {
+                    std::cmp::Ordering::Greater => 0.1,
+                    std::cmp::Ordering::Less => -0.1,
+                    std::cmp::Ordering::Equal => 0.0,
+                };
+            }
+            colour = scale(colour, factor);
+        }
+        if options.chunk_grid && (x.rem_euclid(16) == 0 || z.rem_euclid(16) == 0) {
+            colour = blend(colour, GRID, 0.5);
+        }
+        *pixel = Rgb(colour);
+    }
+    image
+}
+
+/// Renders a map like [`render_map`], loading only the chunks in the region from the save.
+/// If `generate` is set, chunks that haven't been saved are generated from the world's seed.
+pub fn render_saved_map(
+    save: &WorldSave,
+    min: (i32, i32),
+    max: (i32, i32),
+    generate: bool,
+    options: &MapOptions,
+) -> io::Result<RgbImage> {
+    let mut world = save.load_empty()?;
+    // one more row to the north, for slope shading
+    for chunk_x in min.0.div_euclid(16)..=(max.0 - 1).div_euclid(16) {
+        for chunk_z in (min.1 - 1).div_euclid(16)..=(max.1 - 1).div_euclid(16) {
+            if let Some(chunk) = save.load_chunk(chunk_x, chunk_z)? {
+                world.chunks.insert((chunk_x, chunk_z), chunk);
+            } else if generate {
+                world.generate_chunk(chunk_x, chunk_z);
+            }
+        }
+    }
+    Ok(render_map(&world, min, max, options))
+}
+
+/// Returns the region covering all saved chunks of a world, as (min, max) world columns
+pub fn saved_bounds(save: &WorldSave) -> io::Result<Option<ColumnRange>> {
+    let positions = save.chunk_positions()?;
+    let Some(first) = positions.first() else {
+        return Ok(None);
+    };
+    let (mut min, mut max) = (*first, *first);
+    for (x, z) in positions {
+        min = (min.0.min(x), min.1.min(z));
+        max = (max.0.max(x), max.1.max(z));
+    }
+    Ok(Some((
+        (min.0 * 16, min.1 * 16),
+        ((max.0 + 1) * 16, (max.1 + 1) * 16),
+    )))
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::map_render::{render_map, MapOptions, BACKGROUND};
+    use crate::object::{GRASS, STONE};
+    use crate::world::World;
+
+    #[test]
+    fn colours_and_grid() {
+        let mut world = World::new();
+        world.create_empty_chunk(0, 0);
+        world.chunks.get_mut(&(0, 0)).unwrap().blocks[3][10][4] = GRASS;
+        let options = MapOptions {
+            shade_height: false,
+            chunk_grid: false,
+        };
+        let map = render_map(&world, (0, 0), (20, 16), &options);
+        assert_eq!(map.dimensions(), (20, 16));
+        assert_eq!(
+            map.get_pixel(3, 4).0,
+            world.block_type(GRASS).unwrap().colour
+        );
+        assert_eq!(
+            map.get_pixel(5, 5).0,
+            world.block_type(STONE).unwrap().colour
+        );
+        assert_eq!(map.get_pixel(17, 5).0, BACKGROUND);
+
+        let options = MapOptions {
+            shade_height: true,
+            chunk_grid: true,
+        };
+        world.chunks.get_mut(&(0, 0)).unwrap().blocks[6][50][6] = STONE;
+        let map = render_map(&world, (0, 0), (16, 16), &options);
+        assert_ne!(map.get_pixel(0, 5).0, map.get_pixel(1, 5).0);
+        // higher blocks are brighter, the block south of a cliff is in its shadow
+        assert!(map.get_pixel(6, 6).0[0] > map.get_pixel(8, 8).0[0]);
+        assert!(map.get_pixel(6, 7).0[0] < map.get_pixel(8, 8).0[0]);
+    }
+}
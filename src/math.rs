@@ -1,27 +1,127 @@
+use glium::uniforms::{AsUniformValue, UniformValue};
 pub(crate) use std::fmt::Display;
 use std::fmt::Formatter;
-use std::ops::{Add, AddAssign, Sub, SubAssign};
+use std::ops::{Add, AddAssign, Div, Mul, MulAssign, Neg, Sub, SubAssign};
 
 const FLOAT_MIN_DIFF: f32 = 0.001;
 
-#[derive(Debug, Clone, Copy)]
+#[derive(Debug, Clone, Copy, Default)]
+pub struct Vec2(pub f32, pub f32);
+
+#[derive(Debug, Clone, Copy, Default)]
 pub struct Vec3(pub f32, pub f32, pub f32);
 
-impl Vec3 {
-    pub fn len(&self) -> f32 {
-        (self.0 * self.0 + self.1 * self.1 + self.2 * self.2).sqrt()
-    }
+#[derive(Debug, Clone, Copy, Default)]
+pub struct Vec4(pub f32, pub f32, pub f32, pub f32);
 
-    pub fn scale(&self, scale: f32) -> Vec3 {
-        Vec3(self.0 * scale, self.1 * scale, self.2 * scale)
-    }
+/// Rotation as a unit quaternion (x, y, z, w), where (x, y, z) is the axis scaled by the sine
+/// of half the angle and w is the cosine of half the angle
+#[derive(Debug, Clone, Copy)]
+pub struct Quat(pub f32, pub f32, pub f32, pub f32);
 
-    #[must_use]
-    pub fn normalize(&self) -> Vec3 {
-        let len = self.len();
-        Vec3(self.0 / len, self.1 / len, self.2 / len)
-    }
+/// Column-major 3 by 3 matrix, so `self.0[column][row]`
+#[derive(Debug, Clone, Copy)]
+pub struct Mat3(pub [[f32; 3]; 3]);
+
+/// Column-major 4 by 4 matrix, so `self.0[column][row]`, like OpenGL expects them
+#[derive(Debug, Clone, Copy)]
+pub struct Mat4(pub [[f32; 4]; 4]);
+
+/// Implements the operations all vectors share, on the fields `$field` of the tuple struct
+/// `$name`, which is passed to shaders as the uniform type `$uniform`
+macro_rules! vector {
+    ($name:ident, $uniform:ident, $($field:tt),+) => {
+        impl $name {
+            pub fn dot(&self, rhs: $name) -> f32 {
+                0.0 $(+ self.$field * rhs.$field)+
+            }
+
+            pub fn len(&self) -> f32 {
+                self.dot(*self).sqrt()
+            }
+
+            pub fn scale(&self, scale: f32) -> $name {
+                $name($(self.$field * scale),+)
+            }
+
+            #[must_use]
+            pub fn normalize(&self) -> $name {
+                self.scale(1.0 / self.len())
+            }
+
+            /// Returns the point `t` of the way from `self` to `rhs`
+            pub fn lerp(&self, rhs: $name, t: f32) -> $name {
+                $name($(self.$field + (rhs.$field - self.$field) * t),+)
+            }
+        }
+
+        impl Add for $name {
+            type Output = $name;
+            fn add(self, rhs: $name) -> $name {
+                $name($(self.$field + rhs.$field),+)
+            }
+        }
+
+        impl AddAssign for $name {
+            fn add_assign(&mut self, rhs: $name) {
+                *self = *self + rhs;
+            }
+        }
+
+        impl Sub for $name {
+            type Output = $name;
+            fn sub(self, rhs: $name) -> $name {
+                $name($(self.$field - rhs.$field),+)
+            }
+        }
+
+        impl SubAssign for $name {
+            fn sub_assign(&mut self, rhs: $name) {
+                *self = *self - rhs;
+            }
+        }
+
+        impl Mul<f32> for $name {
+            type Output = $name;
+            fn mul(self, rhs: f32) -> $name {
+                self.scale(rhs)
+            }
+        }
+
+        impl Div<f32> for $name {
+            type Output = $name;
+            fn div(self, rhs: f32) -> $name {
+                self.scale(1.0 / rhs)
+            }
+        }
+
+        impl Neg for $name {
+            type Output = $name;
+            fn neg(self) -> $name {
+                self.scale(-1.0)
+            }
+        }
+
+        impl PartialEq for $name {
+            fn eq(&self, rhs: &$name) -> bool {
+                true $(&& (self.$field - rhs.$field).abs() < FLOAT_MIN_DIFF)+
+            }
+        }
 
+        impl AsUniformValue for $name {
+            fn as_uniform_value(&self) -> UniformValue<'_> {
+                UniformValue::$uniform([$(self.$field),+])
+            }
+        }
+    };
+}
+
+vector!(Vec2, Vec2, 0, 1);
+vector!(Vec3, Vec3, 0, 1, 2);
+vector!(Vec4, Vec4, 0, 1, 2, 3);
+vector!(Quat, Vec4, 0, 1, 2, 3);
+
+impl Vec3 {
     #[must_use]
     pub fn rotate(&self, yaw: f32, pitch: f32, roll: f32) -> Vec3 {
         let mat: [[f32; 3]; 3] = [
@@ -46,6 +146,23 @@ impl Vec3 {
     pub fn tuple(&self) -> (f32, f32, f32) {
         (self.0, self.1, self.2)
     }
+
+    pub fn array(&self) -> [f32; 3] {
+        [self.0, self.1, self.2]
+    }
+
+    pub fn cross(&self, rhs: Vec3) -> Vec3 {
+        Vec3(
+            self.1 * rhs.2 - self.2 * rhs.1,
+            self.2 * rhs.0 - self.0 * rhs.2,
+            self.0 * rhs.1 - self.1 * rhs.0,
+        )
+    }
+
+    /// Returns the vector with `w` added as its fourth component
+    pub fn extend(&self, w: f32) -> Vec4 {
+        Vec4(self.0, self.1, self.2, w)
+    }
 }
 
 impl Display for Vec3 {
@@ -54,47 +171,466 @@ impl Display for Vec3 {
     }
 }
 
-impl Sub for Vec3 {
+impl Vec4 {
+    /// Returns the first three components
+    pub fn xyz(&self) -> Vec3 {
+        Vec3(self.0, self.1, self.2)
+    }
+}
+
+impl Quat {
+    pub const IDENTITY: Quat = Quat(0.0, 0.0, 0.0, 1.0);
+
+    /// Returns the rotation by `angle` radians around `axis`, counterclockwise when looking
+    /// along the axis towards the origin
+    pub fn from_axis_angle(axis: Vec3, angle: f32) -> Quat {
+        let Vec3(x, y, z) = axis.normalize().scale((angle / 2.0).sin());
+        Quat(x, y, z, (angle / 2.0).cos())
+    }
+
+    /// Returns the rotation the other way round
+    pub fn inverse(&self) -> Quat {
+        let conjugate = Quat(-self.0, -self.1, -self.2, self.3);
+        conjugate.scale(1.0 / self.dot(*self))
+    }
+
+    /// Returns the rotation `t` of the way from `self` to `rhs`, turning at a constant speed
+    /// the short way round
+    pub fn slerp(&self, rhs: Quat, t: f32) -> Quat {
+        let mut cos = self.dot(rhs);
+        // q and -q are the same rotation, this one goes the short way
+        let rhs = if cos < 0.0 {
+            cos = -cos;
+            -rhs
+        } else {
+            rhs
+        };
+        if cos > 0.9995 {
+            return self.lerp(rhs, t).normalize();
+        }
+        let angle = cos.acos();
+        let sin = angle.sin();
+        self.scale(((1.0 - t) * angle).sin() / sin) + rhs.scale((t * angle).sin() / sin)
+    }
+}
+
+impl Mul for Quat {
+    type Output = Quat;
+    /// Returns the rotation that applies `rhs` first and then `self`
+    fn mul(self, rhs: Quat) -> Quat {
+        let (a, b) = (self, rhs);
+        Quat(
+            a.3 * b.0 + a.0 * b.3 + a.1 * b.2 - a.2 * b.1,
+            a.3 * b.1 - a.0 * b.2 + a.1 * b.3 + a.2 * b.0,
+            a.3 * b.2 + a.0 * b.1 - a.1 * b.0 + a.2 * b.3,
+            a.3 * b.3 - a.0 * b.0 - a.1 * b.1 - a.2 * b.2,
+        )
+    }
+}
+
+impl Mul<Vec3> for Quat {
     type Output = Vec3;
-    fn sub(self, rhs: Self) -> Self::Output {
-        Vec3(self.0 - rhs.0, self.1 - rhs.1, self.2 - rhs.2)
+    fn mul(self, rhs: Vec3) -> Vec3 {
+        let axis = Vec3(self.0, self.1, self.2);
+        let t = axis.cross(rhs).scale(2.0);
+        rhs + t.scale(self.3) + axis.cross(t)
+    }
+}
+
+impl Mat3 {
+    pub const IDENTITY: Mat3 = Mat3([[1.0, 0.0, 0.0], [0.0, 1.0, 0.0], [0.0, 0.0, 1.0]]);
+
+    #[must_use]
+    pub fn transpose(&self) -> Mat3 {
+        Mat3([0, 1, 2].map(|column| [0, 1, 2].map(|row| self.0[row][column])))
+    }
+
+    pub fn determinant(&self) -> f32 {
+        let [a, b, c] = self.0.map(|column| Vec3(column[0], column[1], column[2]));
+        a.dot(b.cross(c))
+    }
+
+    /// Returns the inverse, or `None` if the matrix has none
+    pub fn inverse(&self) -> Option<Mat3> {
+        let determinant = self.determinant();
+        if determinant.abs() < f32::EPSILON {
+            return None;
+        }
+        let [a, b, c] = self.0.map(|column| Vec3(column[0], column[1], column[2]));
+        // the rows of the inverse are the cross products of the columns
+        let rows = [b.cross(c), c.cross(a), a.cross(b)].map(|row| row.scale(1.0 / determinant));
+        Some(Mat3(rows.map(|row| row.array())).transpose())
+    }
+}
+
+impl From<Quat> for Mat3 {
+    fn from(rotation: Quat) -> Mat3 {
+        let column = |axis: Vec3| (rotation * axis).array();
+        Mat3([
+            column(Vec3(1.0, 0.0, 0.0)),
+            column(Vec3(0.0, 1.0, 0.0)),
+            column(Vec3(0.0, 0.0, 1.0)),
+        ])
     }
 }
 
-impl SubAssign for Vec3 {
-    fn sub_assign(&mut self, rhs: Self) {
-        self.0 -= rhs.0;
-        self.1 -= rhs.1;
-        self.2 -= rhs.2;
+impl From<Mat4> for Mat3 {
+    /// Returns the upper left corner, which holds the rotation and scale
+    fn from(matrix: Mat4) -> Mat3 {
+        Mat3([0, 1, 2].map(|column| [0, 1, 2].map(|row| matrix.0[column][row])))
     }
 }
 
-impl AddAssign for Vec3 {
-    fn add_assign(&mut self, rhs: Self) {
-        self.0 += rhs.0;
-        self.1 += rhs.1;
-        self.2 += rhs.2;
+impl Mul for Mat3 {
+    type Output = Mat3;
+    fn mul(self, rhs: Mat3) -> Mat3 {
+        Mat3(
+            rhs.0
+                .map(|column| (self * Vec3(column[0], column[1], column[2])).array()),
+        )
     }
 }
 
-impl Add for Vec3 {
+impl Mul<Vec3> for Mat3 {
     type Output = Vec3;
-    fn add(self, rhs: Vec3) -> Self::Output {
-        Vec3(self.0 + rhs.0, self.1 + rhs.1, self.2 + rhs.2)
+    fn mul(self, rhs: Vec3) -> Vec3 {
+        let [x, y, z] = [0, 1, 2]
+            .map(|row| self.0[0][row] * rhs.0 + self.0[1][row] * rhs.1 + self.0[2][row] * rhs.2);
+        Vec3(x, y, z)
+    }
+}
+
+impl PartialEq for Mat3 {
+    fn eq(&self, rhs: &Mat3) -> bool {
+        let close = |a: &f32, b: &f32| (a - b).abs() < FLOAT_MIN_DIFF;
+        self.0
+            .iter()
+            .flatten()
+            .zip(rhs.0.iter().flatten())
+            .all(|(a, b)| close(a, b))
+    }
+}
+
+impl AsUniformValue for Mat3 {
+    fn as_uniform_value(&self) -> UniformValue<'_> {
+        UniformValue::Mat3(self.0)
+    }
+}
+
+impl Mat4 {
+    pub const IDENTITY: Mat4 = Mat4([
+        [1.0, 0.0, 0.0, 0.0],
+        [0.0, 1.0, 0.0, 0.0],
+        [0.0, 0.0, 1.0, 0.0],
+        [0.0, 0.0, 0.0, 1.0],
+    ]);
+
+    pub fn from_translation(offset: Vec3) -> Mat4 {
+        let mut matrix = Mat4::IDENTITY;
+        matrix.0[3] = offset.extend(1.0).into();
+        matrix
+    }
+
+    pub fn from_scale(scale: Vec3) -> Mat4 {
+        let mut matrix = Mat4::IDENTITY;
+        for (axis, factor) in scale.array().into_iter().enumerate() {
+            matrix.0[axis][axis] = factor;
+        }
+        matrix
+    }
+
+    /// Returns the view matrix of a camera at `position` looking along `direction`, with `up`
+    /// pointing up on the screen. The view looks along +z in view space, with +x to the right
+    /// and +y up.
+    pub fn look_to(position: Vec3, direction: Vec3, up: Vec3) -> Mat4 {
+        let forward = direction.normalize();
+        let right = up.cross(forward).normalize();
+        let up = forward.cross(right);
+        let rotation = Mat3([right.array(), up.array(), forward.array()]).transpose();
+        let mut view = Mat4::from(rotation);
+        view.0[3] = (rotation * -position).extend(1.0).into();
+        view
+    }
+
+    /// Returns the view matrix of a camera at `position` looking at `target`, see
+    /// [`Mat4::look_to`]
+    pub fn look_at(position: Vec3, target: Vec3, up: Vec3) -> Mat4 {
+        Mat4::look_to(position, target - position, up)
+    }
+
+    /// Returns the projection matrix for the vertical field of view `fov` in radians, the width
+    /// divided by the height `aspect` and the clipping planes at `near` and `far`, for view
+    /// matrices like [`Mat4::look_to`]
+    pub fn perspective(fov: f32, aspect: f32, near: f32, far: f32) -> Mat4 {
+        let f = 1.0 / (fov / 2.0).tan();
+        Mat4([
+            [f / aspect, 0.0, 0.0, 0.0],
+            [0.0, f, 0.0, 0.0],
+            [0.0, 0.0, (far + near) / (far - near), 1.0],
+            [0.0, 0.0, -(2.0 * far * near) / (far - near), 0.0],
+        ])
+    }
+
+    /// Returns the projection matrix of a box, mapping it to -1 to 1 on every axis with `near`
+    /// going to -1, for view matrices like [`Mat4::look_to`]
+    pub fn orthographic(left: f32, right: f32, bottom: f32, top: f32, near: f32, far: f32) -> Mat4 {
+        Mat4([
+            [2.0 / (right - left), 0.0, 0.0, 0.0],
+            [0.0, 2.0 / (top - bottom), 0.0, 0.0],
+            [0.0, 0.0, 2.0 / (far - near), 0.0],
+            [
+                -(right + left) / (right - left),
+                -(top + bottom) / (top - bottom),
+                -(far + near) / (far - near),
+                1.0,
+            ],
+        ])
+    }
+
+    #[must_use]
+    pub fn transpose(&self) -> Mat4 {
+        Mat4([0, 1, 2, 3].map(|column| [0, 1, 2, 3].map(|row| self.0[row][column])))
+    }
+
+    /// Returns the inverse, or `None` if the matrix has none
+    pub fn inverse(&self) -> Option<Mat4> {
+        // the matrix is split into 2 by 2 determinants of the top and bottom two rows
+        let m = self.transpose().0;
+        let s = [
+            m[0][0] * m[1][1] - m[1][0] * m[0][1],
+            m[0][0] * m[1][2] - m[1][0] * m[0][2],
+            m[0][0] * m[1][3] - m[1][0] * m[0][3],
+            m[0][1] * m[1][2] - m[1][1] * m[0][2],
+            m[0][1] * m[1][3] - m[1][1] * m[0][3],
+            m[0][2] * m[1][3] - m[1][2] * m[0][3],
+        ];
+        let c = [
+            m[2][0] * m[3][1] - m[3][0] * m[2][1],
+            m[2][0] * m[3][2] - m[3][0] * m[2][2],
+            m[2][0] * m[3][3] - m[3][0] * m[2][3],
+            m[2][1] * m[3][2] - m[3][1] * m[2][2],
+            m[2][1] * m[3][3] - m[3][1] * m[2][3],
+            m[2][2] * m[3][3] - m[3][2] * m[2][3],
+        ];
+        let determinant =
+            s[0] * c[5] - s[1] * c[4] + s[2] * c[3] + s[3] * c[2] - s[4] * c[1] + s[5] * c[0];
+        if determinant.abs() < f32::EPSILON {
+            return None;
+        }
+        let inverse = [
+            [
+                m[1][1] * c[5] - m[1][2] * c[4] + m[1][3] * c[3],
+                -m[0][1] * c[5] + m[0][2] * c[4] - m[0][3] * c[3],
+                m[3][1] * s[5] - m[3][2] * s[4] + m[3][3] * s[3],
+                -m[2][1] * s[5] + m[2][2] * s[4] - m[2][3] * s[3],
+            ],
+            [
+                -m[1][0] * c[5] + m[1][2] * c[2] - m[1][3] * c[1],
+                m[0][0] * c[5] - m[0][2] * c[2] + m[0][3] * c[1],
+                -m[3][0] * s[5] + m[3][2] * s[2] - m[3][3] * s[1],
+                m[2][0] * s[5] - m[2][2] * s[2] + m[2][3] * s[1],
+            ],
+            [
+                m[1][0] * c[4] - m[1][1] * c[2] + m[1][3] * c[0],
+                -m[0][0] * c[4] + m[0][1] * c[2] - m[0][3] * c[0],
+                m[3][0] * s[4] - m[3][1] * s[2] + m[3][3] * s[0],
+                -m[2][0] * s[4] + m[2][1] * s[2] - m[2][3] * s[0],
+            ],
+            [
+                -m[1][0] * c[3] + m[1][1] * c[1] - m[1][2] * c[0],
+                m[0][0] * c[3] - m[0][1] * c[1] + m[0][2] * c[0],
+                -m[3][0] * s[3] + m[3][1] * s[1] - m[3][2] * s[0],
+                m[2][0] * s[3] - m[2][1] * s[1] + m[2][2] * s[0],
+            ],
+        ];
+        Some(Mat4(inverse.map(|row| row.map(|value| value / determinant))).transpose())
+    }
+
+    /// Transforms the point `point`, dividing by w
+    pub fn transform_point(&self, point: Vec3) -> Vec3 {
+        let Vec4(x, y, z, w) = *self * point.extend(1.0);
+        Vec3(x / w, y / w, z / w)
+    }
+
+    /// Transforms the direction `vector`, which isn't moved by translations
+    pub fn transform_vector(&self, vector: Vec3) -> Vec3 {
+        (*self * vector.extend(0.0)).xyz()
+    }
+}
+
+impl From<Mat3> for Mat4 {
+    fn from(matrix: Mat3) -> Mat4 {
+        let mut result = Mat4::IDENTITY;
+        for (column, values) in matrix.0.iter().enumerate() {
+            result.0[column][..3].copy_from_slice(values);
+        }
+        result
+    }
+}
+
+impl From<Quat> for Mat4 {
+    fn from(rotation: Quat) -> Mat4 {
+        Mat4::from(Mat3::from(rotation))
+    }
+}
+
+impl From<Vec4> for [f32; 4] {
+    fn from(vector: Vec4) -> [f32; 4] {
+        [vector.0, vector.1, vector.2, vector.3]
+    }
+}
+
+impl From<Mat4> for [[f32; 4]; 4] {
+    fn from(matrix: Mat4) -> [[f32; 4]; 4] {
+        matrix.0
     }
 }
 
-impl PartialEq for Vec3 {
-    fn eq(&self, rhs: &Vec3) -> bool {
-        (self.0 - rhs.0).abs() < FLOAT_MIN_DIFF
-            && (self.1 - rhs.1).abs() < FLOAT_MIN_DIFF
-            && (self.2 - rhs.2).abs() < FLOAT_MIN_DIFF
+impl Mul for Mat4 {
+    type Output = Mat4;
+    /// Returns the matrix that applies `rhs` first and then `self`
+    fn mul(self, rhs: Mat4) -> Mat4 {
+        let column = |values: [f32; 4]| self * Vec4(values[0], values[1], values[2], values[3]);
+        Mat4(rhs.0.map(|values| column(values).into()))
     }
 }
 
+impl MulAssign for Mat4 {
+    fn mul_assign(&mut self, rhs: Mat4) {
+        *self = *self * rhs;
+    }
+}
+
+impl Mul<Vec4> for Mat4 {
+    type Output = Vec4;
+    fn mul(self, rhs: Vec4) -> Vec4 {
+        let [x, y, z, w] = [0, 1, 2, 3].map(|row| {
+            self.0[0][row] * rhs.0
+                + self.0[1][row] * rhs.1
+                + self.0[2][row] * rhs.2
+                + self.0[3][row] * rhs.3
+        });
+        Vec4(x, y, z, w)
+    }
+}
+
+impl PartialEq for Mat4 {
+    fn eq(&self, rhs: &Mat4) -> bool {
+        let close = |a: &f32, b: &f32| (a - b).abs() < FLOAT_MIN_DIFF;
+        self.0
+            .iter()
+            .flatten()
+            .zip(rhs.0.iter().flatten())
+            .all(|(a, b)| close(a, b))
+    }
+}
+
+impl AsUniformValue for Mat4 {
+    fn as_uniform_value(&self) -> UniformValue<'_> {
+        UniformValue::Mat4(self.0)
+    }
+}
+
+/// Returns the corners of the part of the view from `position` along `direction` that lies
+/// between the distances `near` and `far`, for the vertical field of view `fov` in radians and
+/// the width divided by the height `aspect`
+pub fn frustum_corners(
+    position: Vec3,
+    direction: Vec3,
+    fov: f32,
+    aspect: f32,
+    near: f32,
+    far: f32,
+) -> [Vec3; 8] {
+    let forward = direction.normalize();
+    let right = Vec3(0.0, 1.0, 0.0).cross(forward).normalize();
+    let up = forward.cross(right);
+    let tan = (fov / 2.0).tan();
+    let mut corners = [Vec3::default(); 8];
+    for (i, corner) in corners.iter_mut().enumerate() {
+        let distance = if i < 4 { near } else { far };
+        let x = if i & 1 == 0 { -1.0 } else { 1.0 } * distance * tan * aspect;
+        let y = if i & 2 == 0 { -1.0 } else { 1.0 } * distance * tan;
+        *corner = position + forward * distance + right * x + up * y;
+    }
+    corners
+}
+
+/// Returns whether any of the box from `min` to `max` may be seen through `matrix`, a projection
+/// times a view. Boxes that are outside the view but close to one of its corners also count as
+/// seen.
+pub fn box_in_view(matrix: Mat4, min: Vec3, max: Vec3) -> bool {
+    let corners = [0, 1, 2, 3, 4, 5, 6, 7].map(|i| {
+        let pick = |bit: usize, min: f32, max: f32| if i & bit == 0 { min } else { max };
+        let corner = Vec3(
+            pick(1, min.0, max.0),
+            pick(2, min.1, max.1),
+            pick(4, min.2, max.2),
+        );
+        matrix * corner.extend(1.0)
+    });
+    // in clip space the view is where -w <= x, y, z <= w, so the box is hidden if all its corners
+    // are beyond the same one of those planes
+    let planes: [fn(&Vec4) -> bool; 6] = [
+        |c| c.0 < -c.3,
+        |c| c.0 > c.3,
+        |c| c.1 < -c.3,
+        |c| c.1 > c.3,
+        |c| c.2 < -c.3,
+        |c| c.2 > c.3,
+    ];
+    !planes.iter().any(|outside| corners.iter().all(outside))
+}
+
+/// Returns the centre and radius of a sphere around all `points`
+pub fn bounding_sphere(points: &[Vec3]) -> (Vec3, f32) {
+    let sum = points
+        .iter()
+        .fold(Vec3::default(), |sum, point| sum + *point);
+    let centre = sum / points.len() as f32;
+    let radius = points
+        .iter()
+        .map(|point| (*point - centre).len())
+        .fold(0.0, f32::max);
+    (centre, radius)
+}
+
+/// Returns the matrix that projects the world onto a square shadow map of `resolution` texels
+/// for light coming from `light`, the direction towards the light. It covers a sphere around
+/// `points` and everything up to `depth` further towards the light that can cast shadows into
+/// it. The map moves in whole texels, so that shadows don't shimmer while the camera moves.
+pub fn light_matrix(light: Vec3, points: &[Vec3], resolution: u32, depth: f32) -> Mat4 {
+    let (centre, radius) = bounding_sphere(points);
+    // rounded up, so the size doesn't flicker with rounding errors as the view turns
+    let radius = (radius * 16.0).ceil() / 16.0;
+    let up = if light.1.abs() > 0.99 {
+        Vec3(0.0, 0.0, 1.0)
+    } else {
+        Vec3(0.0, 1.0, 0.0)
+    };
+    let view = Mat4::look_to(Vec3::default(), -light, up);
+    let centre = view.transform_point(centre);
+    let texel = 2.0 * radius / resolution as f32;
+    let x = (centre.0 / texel).floor() * texel;
+    let y = (centre.1 / texel).floor() * texel;
+    let projection = Mat4::orthographic(
+        x - radius,
+        x + radius,
+        y - radius,
+        y + radius,
+        centre.2 - radius - depth,
+        centre.2 + radius,
+    );
+    projection * view
+}
+
 #[cfg(test)]
 mod tests {
-    use crate::math::Vec3;
+    use crate::math::{
+        box_in_view, frustum_corners, light_matrix, Mat3, Mat4, Quat, Vec2, Vec3, Vec4,
+    };
+    use glium::uniforms::{AsUniformValue, UniformValue};
+    use std::f32::consts::PI;
 
     #[test]
     fn vec3_test_add_1() {
@@ -130,4 +666,230 @@ mod tests {
         );
         assert_eq!(a, Vec3(1.0, 2.0, 3.0));
     }
+
+    #[test]
+    fn vector_operations() {
+        assert_eq!(Vec2(3.0, 4.0).len(), 5.0);
+        assert_eq!(Vec2(1.0, 2.0).dot(Vec2(3.0, -1.0)), 1.0);
+        assert_eq!(-Vec2(1.0, -2.0), Vec2(-1.0, 2.0));
+        assert_eq!(Vec2(0.0, 2.0).lerp(Vec2(4.0, 4.0), 0.25), Vec2(1.0, 2.5));
+
+        let (x, y) = (Vec3(1.0, 0.0, 0.0), Vec3(0.0, 1.0, 0.0));
+        assert_eq!(x.cross(y), Vec3(0.0, 0.0, 1.0));
+        assert_eq!(y.cross(x), Vec3(0.0, 0.0, -1.0));
+        assert_eq!(x.dot(y), 0.0);
+        assert_eq!(Vec3(1.0, 2.0, 3.0) * 2.0, Vec3(2.0, 4.0, 6.0));
+        assert_eq!(Vec3(1.0, 2.0, 3.0) / 2.0, Vec3(0.5, 1.0, 1.5));
+        assert_eq!(Vec3(0.0, 3.0, 4.0).normalize(), Vec3(0.0, 0.6, 0.8));
+        let mut a = Vec3(1.0, 1.0, 1.0);
+        a -= Vec3(1.0, 0.0, 0.0);
+        a += Vec3(0.0, 0.0, 1.0);
+        assert_eq!(a, Vec3(0.0, 1.0, 2.0));
+
+        let v = Vec3(1.0, 2.0, 3.0).extend(1.0);
+        assert_eq!(v, Vec4(1.0, 2.0, 3.0, 1.0));
+        assert_eq!(v.xyz(), Vec3(1.0, 2.0, 3.0));
+        assert_eq!(v.dot(v), 15.0);
+        assert_eq!(v - v, Vec4::default());
+        assert_ne!(Vec4(0.0, 0.0, 0.0, 1.0), Vec4(0.0, 0.0, 0.0, 1.1));
+    }
+
+    #[test]
+    fn quaternion_rotations() {
+        let quarter = Quat::from_axis_angle(Vec3(0.0, 1.0, 0.0), PI / 2.0);
+        assert_eq!(quarter * Vec3(0.0, 0.0, 1.0), Vec3(1.0, 0.0, 0.0));
+        assert_eq!(
+            quarter * quarter * Vec3(0.0, 0.0, 1.0),
+            Vec3(0.0, 0.0, -1.0)
+        );
+        assert_eq!(quarter * quarter.inverse(), Quat::IDENTITY);
+        assert_eq!(Quat::IDENTITY * Vec3(1.0, 2.0, 3.0), Vec3(1.0, 2.0, 3.0));
+
+        // applies the right rotation first
+        let up = Quat::from_axis_angle(Vec3(1.0, 0.0, 0.0), -PI / 2.0);
+        assert_eq!((quarter * up) * Vec3(0.0, 0.0, 1.0), Vec3(0.0, 1.0, 0.0));
+        assert_eq!((up * quarter) * Vec3(0.0, 0.0, 1.0), Vec3(1.0, 0.0, 0.0));
+
+        // the matrices rotate the same way
+        let rotation = quarter * up;
+        let point = Vec3(1.0, -2.0, 0.5);
+        assert_eq!(Mat3::from(rotation) * point, rotation * point);
+        assert_eq!(
+            Mat4::from(rotation).transform_point(point),
+            rotation * point
+        );
+
+        // halfway between no rotation and a quarter turn is an eighth of a turn
+        let eighth = Quat::from_axis_angle(Vec3(0.0, 1.0, 0.0), PI / 4.0);
+        assert_eq!(Quat::IDENTITY.slerp(quarter, 0.5), eighth);
+        assert_eq!(Quat::IDENTITY.slerp(-quarter, 0.5), eighth);
+        assert_eq!(Quat::IDENTITY.slerp(quarter, 0.0), Quat::IDENTITY);
+        assert_eq!(Quat::IDENTITY.slerp(quarter, 1.0), quarter);
+    }
+
+    #[test]
+    fn mat3_operations() {
+        let m = Mat3([[2.0, 0.0, 1.0], [1.0, 3.0, 0.0], [0.0, 1.0, 4.0]]);
+        assert_eq!(m.determinant(), 25.0);
+        assert_eq!(m * m.inverse().unwrap(), Mat3::IDENTITY);
+        assert_eq!(m.inverse().unwrap() * m, Mat3::IDENTITY);
+        assert_eq!(m.transpose().0[0], [2.0, 1.0, 0.0]);
+        assert_eq!(m * Vec3(1.0, 0.0, 0.0), Vec3(2.0, 0.0, 1.0));
+        assert_eq!(Mat3([[1.0, 2.0, 3.0]; 3]).inverse(), None);
+        assert_eq!(Mat3::from(Mat4::from(m)), m);
+    }
+
+    #[test]
+    fn mat4_operations() {
+        let translation = Mat4::from_translation(Vec3(1.0, 2.0, 3.0));
+        let scale = Mat4::from_scale(Vec3(2.0, 2.0, 4.0));
+        let rotation = Mat4::from(Quat::from_axis_angle(Vec3(1.0, 1.0, 0.0), 1.0));
+        let m = translation * rotation * scale;
+        assert_eq!(
+            translation.transform_point(Vec3(0.0, 0.0, 0.0)),
+            Vec3(1.0, 2.0, 3.0)
+        );
+        assert_eq!(
+            translation.transform_vector(Vec3(1.0, 0.0, 0.0)),
+            Vec3(1.0, 0.0, 0.0)
+        );
+        assert_eq!(
+            scale.transform_point(Vec3(1.0, 1.0, 1.0)),
+            Vec3(2.0, 2.0, 4.0)
+        );
+        assert_eq!(m * m.inverse().unwrap(), Mat4::IDENTITY);
+        assert_eq!(m.inverse().unwrap() * m, Mat4::IDENTITY);
+        assert_eq!(
+            m.inverse()
+                .unwrap()
+                .transform_point(m.transform_point(Vec3(5.0, -1.0, 2.0))),
+            Vec3(5.0, -1.0, 2.0)
+        );
+        assert_eq!(m.transpose().transpose(), m);
+        assert_eq!(Mat4::from_scale(Vec3(1.0, 0.0, 1.0)).inverse(), None);
+        let mut n = Mat4::IDENTITY;
+        n *= translation;
+        assert_eq!(n, translation);
+
+        assert!(matches!(
+            m.as_uniform_value(),
+            UniformValue::Mat4(values) if values == m.0
+        ));
+        assert!(matches!(
+            Vec3(1.0, 2.0, 3.0).as_uniform_value(),
+            UniformValue::Vec3([1.0, 2.0, 3.0])
+        ));
+        assert!(matches!(
+            Quat::IDENTITY.as_uniform_value(),
+            UniformValue::Vec4([0.0, 0.0, 0.0, 1.0])
+        ));
+        assert_eq!(<[[f32; 4]; 4]>::from(Mat4::IDENTITY), Mat4::IDENTITY.0);
+    }
+
+    #[test]
+    fn view_and_projection_matrices() {
+        let view = Mat4::look_to(
+            Vec3(1.0, 2.0, 3.0),
+            Vec3(0.0, 0.0, 2.0),
+            Vec3(0.0, 1.0, 0.0),
+        );
+        assert_eq!(
+            view.transform_point(Vec3(1.0, 2.0, 3.0)),
+            Vec3(0.0, 0.0, 0.0)
+        );
+        assert_eq!(
+            view.transform_point(Vec3(1.0, 2.0, 8.0)),
+            Vec3(0.0, 0.0, 5.0)
+        );
+        assert_eq!(
+            view.transform_point(Vec3(1.0, 4.0, 3.0)),
+            Vec3(0.0, 2.0, 0.0)
+        );
+        // looking along +x, -z is to the right
+        let view = Mat4::look_at(
+            Vec3(0.0, 0.0, 0.0),
+            Vec3(5.0, 0.0, 0.0),
+            Vec3(0.0, 1.0, 0.0),
+        );
+        assert_eq!(
+            view.transform_point(Vec3(5.0, 0.0, -1.0)),
+            Vec3(1.0, 0.0, 5.0)
+        );
+
+        let projection = Mat4::perspective(PI / 2.0, 2.0, 0.1, 100.0);
+        assert_eq!(projection.transform_point(Vec3(0.0, 0.0, 0.1)).2, -1.0);
+        assert_eq!(projection.transform_point(Vec3(0.0, 0.0, 100.0)).2, 1.0);
+        // the corners of the screen, 10 blocks away
+        assert_eq!(
+            projection.transform_point(Vec3(20.0, 10.0, 10.0)),
+            Vec3(1.0, 1.0, projection.transform_point(Vec3(0.0, 0.0, 10.0)).2)
+        );
+
+        let projection = Mat4::orthographic(-2.0, 2.0, 0.0, 10.0, 1.0, 5.0);
+        assert_eq!(
+            projection.transform_point(Vec3(-2.0, 0.0, 1.0)),
+            Vec3(-1.0, -1.0, -1.0)
+        );
+        assert_eq!(
+            projection.transform_point(Vec3(2.0, 10.0, 5.0)),
+            Vec3(1.0, 1.0, 1.0)
+        );
+        // applies the view first
+        let both = projection * view;
+        let point = Vec3(2.0, 7.0, 6.0);
+        assert_eq!(
+            both.transform_point(point),
+            projection.transform_point(view.transform_point(point))
+        );
+    }
+
+    #[test]
+    fn boxes_in_view() {
+        let view = Mat4::look_to(
+            Vec3(0.0, 0.0, 0.0),
+            Vec3(0.0, 0.0, 1.0),
+            Vec3(0.0, 1.0, 0.0),
+        );
+        let matrix = Mat4::perspective(PI / 2.0, 1.0, 0.1, 100.0) * view;
+        let block = |x: f32, z: f32| Vec3(x, -0.5, z);
+        let seen = |min: Vec3| box_in_view(matrix, min, min + Vec3(1.0, 1.0, 1.0));
+        assert!(seen(block(-0.5, 10.0)));
+        // behind, beside, beyond the far plane and around the camera
+        assert!(!seen(block(-0.5, -10.0)));
+        assert!(!seen(block(20.0, 10.0)));
+        assert!(!seen(block(-0.5, 200.0)));
+        assert!(seen(block(-0.5, -0.5)));
+        // partly inside
+        assert!(box_in_view(
+            matrix,
+            Vec3(-50.0, -1.0, 5.0),
+            Vec3(-4.0, 1.0, 6.0)
+        ));
+    }
+
+    #[test]
+    fn light_matrix_covers_the_view() {
+        let position = Vec3(10.0, 70.0, -5.0);
+        let direction = Vec3(1.0, -0.3, 0.5);
+        let corners = frustum_corners(position, direction, 1.2, 16.0 / 9.0, 4.0, 20.0);
+        assert!(((corners[0] - position).dot(direction.normalize()) - 4.0).abs() < 1e-4);
+        assert!(((corners[7] - position).dot(direction.normalize()) - 20.0).abs() < 1e-4);
+
+        let light = Vec3(0.3, 0.9, 0.2).normalize();
+        let matrix = light_matrix(light, &corners, 1024, 100.0);
+        for corner in corners {
+            let Vec3(x, y, z) = matrix.transform_point(corner);
+            assert!(x.abs() <= 1.0 && y.abs() <= 1.0 && z.abs() <= 1.0);
+            // blocks between the corner and the light cast shadows on it
+            let caster = matrix.transform_point(corner + light * 50.0);
+            assert!(caster.2 >= -1.0 && caster.2 < z);
+        }
+
+        // moving the view a little moves the map by whole texels
+        let moved = corners.map(|corner| corner + Vec3(0.01, 0.0, 0.0));
+        let shift = |matrix: Mat4| matrix.transform_point(Vec3(0.0, 0.0, 0.0));
+        let texels = (shift(light_matrix(light, &moved, 1024, 100.0)) - shift(matrix)) * 512.0;
+        assert!((texels.0 - texels.0.round()).abs() < 0.01);
+        assert!((texels.1 - texels.1.round()).abs() < 0.01);
+    }
 }
@@ -0,0 +1,267 @@
+use crate::settings::{Settings, FOV_RANGE, SENSITIVITY_RANGE};
+use crate::ui::{DrawCommand, Ui, UiInput, UiState};
+use std::fs;
+use std::path::{Path, PathBuf};
+
+const MENU_WIDTH: f32 = 400.0;
+const WORLD_LIST_ROWS: usize = 6;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Screen {
+    Pause,
+    Settings,
+    WorldSelection,
+}
+
+/// Something the game has to do because of what was clicked in the menu
+#[derive(Debug, Clone, PartialEq)]
+pub enum MenuAction {
+    Resume,
+    SaveAndQuit,
+    /// Save the current world and play the world in this directory, creating it if needed
+    OpenWorld(PathBuf),
+    /// Write the settings, which have already been changed, to the settings file
+    SaveSettings,
+}
+
+/// Returns the names of the directories in `dir` that contain a saved world, sorted
+pub fn list_worlds(dir: &Path) -> Vec<String> {
+    let Ok(entries) = fs::read_dir(dir) else {
+        return vec![];
+    };
+    let mut worlds: Vec<String> = entries
+        .flatten()
+        .filter(|entry| entry.path().join("world.dat").is_file())
+        .filter_map(|entry| entry.file_name().into_string().ok())
+        .collect();
+    worlds.sort();
+    worlds
+}
+
+/// Returns whether `name` can be used as the directory name of a new world
+fn valid_world_name(name: &str) -> bool {
+    let name = name.trim();
+    !name.is_empty() && name != "." && name != ".." && !name.contains(['/', '\\'])
+}
+
+/// The pause menu and the screens that can be reached from it
+pub struct Menu {
+    screen: Option<Screen>,
+    ui: UiState,
+    /// Directory the world selection lists worlds from and creates new worlds in
+    worlds_dir: PathBuf,
+    worlds: Vec<String>,
+    selected_world: Option<usize>,
+    new_world: String,
+    /// Whether the settings were changed since they were last saved
+    unsaved_settings: bool,
+}
+
+impl Menu {
+    pub fn new(worlds_dir: impl Into<PathBuf>) -> Menu {
+        Menu {
+            screen: None,
+            ui: UiState::default(),
+            worlds_dir: worlds_dir.into(),
+            worlds: vec![],
+            selected_world: None,
+            new_world: String::new(),
+            unsaved_settings: false,
+        }
+    }
+
+    pub fn is_open(&self) -> bool {
+        self.screen.is_some()
+    }
+
+    pub fn screen(&self) -> Option<Screen> {
+        self.screen
+    }
+
+    pub fn open(&mut self) {
+        self.screen = Some(Screen::Pause);
+    }
+
+    fn show(&mut self, screen: Screen) {
+        if screen == Screen::WorldSelection {
+            self.worlds = list_worlds(&self.worlds_dir);
+            self.selected_world = None;
+        }
+        self.screen = Some(screen);
+    }
+
+    /// Goes back one screen, closing the menu from the pause screen
+    pub fn back(&mut self) -> Option<MenuAction> {
+        match self.screen? {
+            Screen::Pause => {
+                self.screen = None;
+                Some(MenuAction::Resume)
+            }
+            Screen::Settings | Screen::WorldSelection => {
+                self.screen = Some(Screen::Pause);
+                None
+            }
+        }
+    }
+
+    /// Lays out the current screen for this frame on a screen of `size` pixels with `scale`
+    /// pixels per logical pixel. Returns what to draw and what the player asked for, if anything.
+    pub fn build(
+        &mut self,
+        input: &UiInput,
+        size: (f32, f32),
+        scale: f32,
+        settings: &mut Settings,
+    ) -> (Vec<DrawCommand>, Option<MenuAction>) {
+        let Some(screen) = self.screen else {
+            return (vec![], None);
+        };
+        let mut ui = Ui::new(&mut self.ui, input, size, MENU_WIDTH, scale);
+        ui.backdrop(size);
+        let mut action = None;
+        let mut next = None;
+        match screen {
+            Screen::Pause => {
+                ui.title("Paused");
+                if ui.button("Resume") {
+                    action = Some(MenuAction::Resume);
+                }
+                if ui.button("Settings") {
+                    next = Some(Screen::Settings);
+                }
+                if ui.button("Select world") {
+                    next = Some(Screen::WorldSelection);
+                }
+                if ui.button("Save and quit") {
+                    action = Some(MenuAction::SaveAndQuit);
+                }
+            }
+            Screen::Settings => {
+                ui.title("Settings");
+                let mut changed = ui.toggle("Fullscreen", &mut settings.fullscreen);
+                let fov = format!("Field of view: {:.0}", settings.fov);
+                changed |= ui.slider("fov", &fov, &mut settings.fov, FOV_RANGE);
+                if ui.button(&format!("Field of view axis: {}", settings.fov_axis.name())) {
+                    settings.fov_axis = settings.fov_axis.cycle();
+                    changed = true;
+                }
+                if ui.button(&format!("Fog: {}", settings.fog.name())) {
+                    settings.fog = settings.fog.cycle();
+                    changed = true;
+                }
+                changed |= ui.toggle("Light scattering", &mut settings.scattering);
+                changed |= ui.toggle("Shadows", &mut settings.shadows);
+                // shown as a multiple of the default
+                let mut sensitivity = settings.mouse_sensitivity * 1000.0;
+                let text = format!("Mouse sensitivity: {:.2}", sensitivity);
+                let range = SENSITIVITY_RANGE.start() * 1000.0..=SENSITIVITY_RANGE.end() * 1000.0;
+                if ui.slider("sensitivity", &text, &mut sensitivity, range) {
+                    settings.mouse_sensitivity = sensitivity / 1000.0;
+                    changed = true;
+                }
+                self.unsaved_settings |= changed;
+                if ui.button("Back") {
+                    next = Some(Screen::Pause);
+                }
+            }
+            Screen::WorldSelection => {
+                ui.title("Select world");
+                ui.list(
+                    "worlds",
+                    &self.worlds,
+                    &mut self.selected_world,
+                    WORLD_LIST_ROWS,
+                );
+                if ui.button("Play selected world") {
+                    if let Some(index) = self.selected_world {
+                        let dir = self.worlds_dir.join(&self.worlds[index]);
+                        action = Some(MenuAction::OpenWorld(dir));
+                    }
+                }
+                ui.label("New world name:");
+                let entered = ui.text_input("new world", &mut self.new_world);
+                if (ui.button("Create world") || entered) && valid_world_name(&self.new_world) {
+                    let dir = self.worlds_dir.join(self.new_world.trim());
+                    action = Some(MenuAction::OpenWorld(dir));
+                    self.new_world.clear();
+                }
+                if ui.button("Back") {
+                    next = Some(Screen::Pause);
+                }
+            }
+        }
+        let commands = ui.finish();
+        // saved once a slider is let go of rather than on every step while it is dragged
+        if self.unsaved_settings && !input.mouse_down && action.is_none() {
+            self.unsaved_settings = false;
+            action = Some(MenuAction::SaveSettings);
+        }
+        if let Some(screen) = next {
+            self.show(screen);
+        }
+        if matches!(
+            action,
+            Some(MenuAction::Resume | MenuAction::OpenWorld(_) | MenuAction::SaveAndQuit)
+        ) {
+            self.screen = None;
+        }
+        (commands, action)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::menu::{list_worlds, valid_world_name, Menu, MenuAction, Screen};
+    use crate::settings::Settings;
+    use crate::ui::UiInput;
+
+    #[test]
+    fn navigation() {
+        let mut menu = Menu::new("worlds");
+        assert!(!menu.is_open());
+        menu.open();
+        menu.show(Screen::Settings);
+        assert_eq!(menu.back(), None);
+        assert_eq!(menu.screen(), Some(Screen::Pause));
+        assert_eq!(menu.back(), Some(MenuAction::Resume));
+        assert!(!menu.is_open());
+    }
+
+    #[test]
+    fn saves_settings_after_dragging() {
+        let mut menu = Menu::new("worlds");
+        menu.open();
+        menu.show(Screen::Settings);
+        let mut settings = Settings::default();
+        let mut input = UiInput {
+            mouse_down: true,
+            ..UiInput::default()
+        };
+        menu.unsaved_settings = true;
+        let (_, action) = menu.build(&input, (800.0, 600.0), 1.0, &mut settings);
+        assert_eq!(action, None);
+        input.release();
+        let (_, action) = menu.build(&input, (800.0, 600.0), 1.0, &mut settings);
+        assert_eq!(action, Some(MenuAction::SaveSettings));
+        input.end_frame();
+        let (_, action) = menu.build(&input, (800.0, 600.0), 1.0, &mut settings);
+        assert_eq!(action, None);
+    }
+
+    #[test]
+    fn worlds_in_directory() {
+        let dir = std::env::temp_dir().join(format!("raft-menu-test-{}", std::process::id()));
+        for name in ["b", "a", "not a world"] {
+            std::fs::create_dir_all(dir.join(name)).unwrap();
+        }
+        std::fs::write(dir.join("a").join("world.dat"), "seed 1\n").unwrap();
+        std::fs::write(dir.join("b").join("world.dat"), "seed 2\n").unwrap();
+        assert_eq!(list_worlds(&dir), ["a", "b"]);
+        assert!(list_worlds(&dir.join("missing")).is_empty());
+        std::fs::remove_dir_all(&dir).unwrap();
+
+        assert!(valid_world_name(" My world "));
+        assert!(!valid_world_name(".."));
+        assert!(!valid_world_name("a/b"));
+    }
+}
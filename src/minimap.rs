@@ -0,0 +1,215 @@
+use crate::map_render::{render_map, MapOptions};
+use crate::math::Vec3;
+use crate::sprite_batch::{SpriteBatch, TextureId};
+use crate::waypoint::Waypoints;
+use crate::world::World;
+use glium::texture::RawImage2d;
+use glium::{Display, Texture2d};
+use image::{Rgb, RgbImage};
+use std::collections::hash_map::Entry;
+use std::collections::HashMap;
+
+/// How many blocks the minimap shows in each direction from the player
+pub const MINIMAP_RADIUS: f32 = 32.0;
+/// Radius of the sampled area, large enough to cover the corners of the rotated minimap
+const SAMPLE_RADIUS: i32 = 46;
+/// Side length of the minimap on screen, in pixels
+const MINIMAP_SIZE: f32 = 200.0;
+const MARGIN: f32 = 16.0;
+const MARKER_SIZE: f32 = 8.0;
+const BACKGROUND: [u8; 3] = [20, 20, 20];
+
+/// Top-down colours of the loaded chunks, sampled once per chunk
+#[derive(Default)]
+pub struct MinimapCache {
+    chunks: HashMap<(i32, i32), RgbImage>,
+}
+
+impl MinimapCache {
+    /// Samples chunks that aren't cached yet and forgets chunks that have been unloaded.
+    /// Returns whether anything changed.
+    pub fn update(&mut self, world: &World) -> bool {
+        let before = self.chunks.len();
+        self.chunks.retain(|pos, _| world.chunks.contains_key(pos));
+        let mut changed = self.chunks.len() != before;
+        for (chunk_x, chunk_z) in world.chunks.keys() {
+            if let Entry::Vacant(entry) = self.chunks.entry((*chunk_x, *chunk_z)) {
+                let min = (chunk_x * 16, chunk_z * 16);
+                let max = (min.0 + 16, min.1 + 16);
+                entry.insert(render_map(world, min, max, &MapOptions::default()));
+                changed = true;
+            }
+        }
+        changed
+    }
+
+    /// Makes the chunk at **chunk position** (x, z) be sampled again on the next update
+    pub fn invalidate(&mut self, chunk_x: i32, chunk_z: i32) {
+        self.chunks.remove(&(chunk_x, chunk_z));
+    }
+
+    /// Composes the colours of the columns from (x - radius, z - radius) to (x + radius, z + radius)
+    /// into one image. Image x is world x and image y is world z.
+    pub fn image(&self, x: i32, z: i32, radius: i32) -> RgbImage {
+        RgbImage::from_fn(2 * radius as u32, 2 * radius as u32, |px, pz| {
+            let (wx, wz) = (x - radius + px as i32, z - radius + pz as i32);
+            match self.chunks.get(&(wx.div_euclid(16), wz.div_euclid(16))) {
+                Some(colours) => {
+                    *colours.get_pixel(wx.rem_euclid(16) as u32, wz.rem_euclid(16) as u32)
+                }
+                None => Rgb(BACKGROUND),
+            }
+        })
+    }
+}
+
+/// Converts the horizontal world offset (dx, dz) into (right, up) on a minimap that is rotated
+/// so that the direction of `yaw` points up
+pub fn rotate_to_view(dx: f32, dz: f32, yaw: f32) -> (f32, f32) {
+    (
+        dx * yaw.cos() - dz * yaw.sin(),
+        dx * yaw.sin() + dz * yaw.cos(),
+    )
+}
+
+/// Returns the world offset that appears at (right, up) on the rotated minimap
+fn rotate_to_world(right: f32, up: f32, yaw: f32) -> (f32, f32) {
+    (
+        right * yaw.cos() + up * yaw.sin(),
+        -right * yaw.sin() + up * yaw.cos(),
+    )
+}
+
+/// HUD minimap in the top right corner of the screen, with markers for the player and all waypoints
+#[derive(Default)]
+pub struct Minimap {
+    cache: MinimapCache,
+    texture: Option<TextureId>,
+    /// Whether the texture has to be composed again
+    stale: bool,
+    centre: (i32, i32),
+}
+
+impl Minimap {
+    /// Forgets all sampled chunks, for when another world is loaded
+    pub fn clear(&mut self) {
+        self.cache = MinimapCache::default();
+        self.stale = true;
+    }
+
+    pub fn invalidate_chunk(&mut self, chunk_x: i32, chunk_z: i32) {
+        self.cache.invalidate(chunk_x, chunk_z);
+        self.stale = true;
+    }
+
+    #[allow(clippy::too_many_arguments)]
+    pub fn draw(
+        &mut self,
+        display: &Display,
+        batch: &mut SpriteBatch,
+        world: &World,
+        position: Vec3,
+        yaw: f32,
+        waypoints: &Waypoints,
+        (width, height): (f32, f32),
+    ) {
+        let centre = (position.0.floor() as i32, position.2.floor() as i32);
+        if self.cache.update(world) || centre != self.centre || self.stale || self.texture.is_none()
+        {
+            let image = self.cache.image(centre.0, centre.1, SAMPLE_RADIUS);
+            let dimensions = image.dimensions();
+            let raw = RawImage2d::from_raw_rgb(image.into_raw(), dimensions);
+            let texture = Texture2d::new(display, raw).unwrap();
+            match self.texture {
+                Some(id) => batch.set_texture(id, texture),
+                None => self.texture = Some(batch.add_texture(texture)),
+            }
+            self.centre = centre;
+            self.stale = false;
+        }
+
+        let half = MINIMAP_SIZE / 2.0;
+        let screen_centre = (width - MARGIN - half, height - MARGIN - half);
+        let corners = [(-1.0, -1.0), (-1.0, 1.0), (1.0, -1.0), (1.0, 1.0)];
+
+        // position of the player inside the sampled image, in blocks
+        let origin = (
+            position.0 - (centre.0 - SAMPLE_RADIUS) as f32,
+            position.2 - (centre.1 - SAMPLE_RADIUS) as f32,
+        );
+        let size = 2.0 * SAMPLE_RADIUS as f32;
+        let tex_coords = corners.map(|(sx, sy)| {
+            let (dx, dz) = rotate_to_world(sx * MINIMAP_RADIUS, sy * MINIMAP_RADIUS, yaw);
+            [(origin.0 + dx) / size, (origin.1 + dz) / size]
+        });
+        let positions =
+            corners.map(|(sx, sy)| [screen_centre.0 + sx * half, screen_centre.1 + sy * half]);
+        batch.quad(self.texture.unwrap(), positions, tex_coords, [1.0; 4]);
+
+        let mut markers = vec![((0.0, 0.0), [255, 255, 255])];
+        for waypoint in &waypoints.waypoints {
+            let (right, up) = rotate_to_view(
+                waypoint.position.0 - position.0,
+                waypoint.position.2 - position.2,
+                yaw,
+            );
+            // waypoints outside of the map stick to its border
+            let scale = MINIMAP_RADIUS / right.abs().max(up.abs()).max(MINIMAP_RADIUS);
+            markers.push((
+                (right * scale / MINIMAP_RADIUS, up * scale / MINIMAP_RADIUS),
+                waypoint.colour,
+            ));
+        }
+        for ((x, y), colour) in markers {
+            let (x, y) = (screen_centre.0 + x * half, screen_centre.1 + y * half);
+            let [r, g, b] = colour.map(|c| c as f32 / 255.0);
+            batch.fill(
+                (
+                    [x - MARKER_SIZE / 2.0, y - MARKER_SIZE / 2.0],
+                    [x + MARKER_SIZE / 2.0, y + MARKER_SIZE / 2.0],
+                ),
+                [r, g, b, 1.0],
+            );
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::minimap::{rotate_to_view, rotate_to_world, MinimapCache};
+    use crate::world::World;
+    use std::f32::consts::PI;
+
+    fn assert_close((a, b): (f32, f32), (c, d): (f32, f32)) {
+        assert!(
+            (a - c).abs() < 0.001 && (b - d).abs() < 0.001,
+            "{:?} != {:?}",
+            (a, b),
+            (c, d)
+        );
+    }
+
+    #[test]
+    fn facing_direction_points_up() {
+        // yaw 0 faces +z, a quarter turn faces +x
+        assert_close(rotate_to_view(0.0, 1.0, 0.0), (0.0, 1.0));
+        assert_close(rotate_to_view(1.0, 0.0, 0.5 * PI), (0.0, 1.0));
+        assert_close(rotate_to_view(0.0, -1.0, 0.5 * PI), (1.0, 0.0));
+        let (x, z) = rotate_to_world(3.0, -2.0, 1.2);
+        assert_close(rotate_to_view(x, z, 1.2), (3.0, -2.0));
+    }
+
+    #[test]
+    fn cache_follows_loaded_chunks() {
+        let mut world = World::new();
+        world.create_empty_chunk(0, 0);
+        let mut cache = MinimapCache::default();
+        assert!(cache.update(&world));
+        assert!(!cache.update(&world));
+        let image = cache.image(0, 0, 4);
+        assert_eq!(image.get_pixel(0, 0).0, super::BACKGROUND);
+        assert_ne!(image.get_pixel(5, 5).0, super::BACKGROUND);
+        world.chunks.clear();
+        assert!(cache.update(&world));
+    }
+}
@@ -0,0 +1,127 @@
+use crate::object::Object;
+use crate::rng::Rng;
+use image::{Rgba, RgbaImage};
+
+/// Number of crack overlay stages shown while a block is being mined
+pub const BREAK_STAGES: usize = 10;
+
+/// Progress of breaking the targeted block while the attack button is held
+#[derive(Debug, Default)]
+pub struct Mining {
+    target: Option<(i32, i32, i32)>,
+    /// Seconds spent mining the target so far
+    elapsed: f32,
+}
+
+impl Mining {
+    /// Advances mining of `target` by `dt` seconds if `held` is set. Switching targets or releasing
+    /// the button starts over. Returns the position of the block once it is broken.
+    pub fn update(
+        &mut self,
+        target: Option<((i32, i32, i32), &Object)>,
+        held: bool,
+        dt: f32,
+    ) -> Option<(i32, i32, i32)> {
+        let Some((position, object)) = target.filter(|_| held) else {
+            self.reset();
+            return None;
+        };
+        if self.target != Some(position) {
+            self.target = Some(position);
+            self.elapsed = 0.0;
+        }
+        self.elapsed += dt;
+        if self.elapsed >= object.hardness {
+            self.reset();
+            return Some(position);
+        }
+        None
+    }
+
+    pub fn reset(&mut self) {
+        self.target = None;
+        self.elapsed = 0.0;
+    }
+
+    /// Returns the block being mined and its crack stage from 0 to [`BREAK_STAGES`] - 1
+    pub fn stage(&self, object: &Object) -> Option<((i32, i32, i32), usize)> {
+        let target = self.target?;
+        let progress = if object.hardness > 0.0 {
+            self.elapsed / object.hardness
+        } else {
+            1.0
+        };
+        let stage = (progress * BREAK_STAGES as f32) as usize;
+        Some((target, stage.min(BREAK_STAGES - 1)))
+    }
+}
+
+/// Side length of the crack overlay textures, in pixels
+const CRACK_SIZE: u32 = 16;
+const CRACK_COLOUR: Rgba<u8> = Rgba([20, 20, 20, 200]);
+
+/// Draws the crack overlay of the given stage. Every stage contains the cracks of the stages
+/// before it and adds some more.
+pub fn crack_image(stage: usize) -> RgbaImage {
+    let mut image = RgbaImage::new(CRACK_SIZE, CRACK_SIZE);
+    let mut rng = Rng::new(0xc4ac);
+    let size = CRACK_SIZE as i32;
+    for _ in 0..=stage {
+        let (mut x, mut y) = (rng.range(0, size), rng.range(0, size));
+        for _ in 0..rng.range(6, 12) {
+            image.put_pixel(x as u32, y as u32, CRACK_COLOUR);
+            x = (x + rng.range(-1, 2)).clamp(0, size - 1);
+            y = (y + rng.range(-1, 2)).clamp(0, size - 1);
+        }
+    }
+    image
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::mining::{crack_image, Mining, BREAK_STAGES};
+    use crate::object::{Drops, Movement, Object};
+
+    #[test]
+    fn takes_hardness_seconds() {
+        let stone = Object {
+            name: "stone",
+            colour: [0, 0, 0],
+            hardness: 1.0,
+            movement: Movement::Solid,
+            drops: Drops::Itself,
+            on_update: None,
+            random_ticks: false,
+            gravity: false,
+        };
+        let mut mining = Mining::default();
+        assert_eq!(mining.update(Some(((1, 2, 3), &stone)), true, 0.45), None);
+        assert_eq!(mining.stage(&stone), Some(((1, 2, 3), 4)));
+        assert_eq!(mining.update(Some(((1, 2, 3), &stone)), true, 0.45), None);
+        assert_eq!(
+            mining.update(Some(((1, 2, 3), &stone)), true, 0.2),
+            Some((1, 2, 3))
+        );
+        assert_eq!(mining.stage(&stone), None);
+
+        // looking away or letting go starts over
+        mining.update(Some(((1, 2, 3), &stone)), true, 0.9);
+        mining.update(Some(((1, 2, 4), &stone)), true, 0.2);
+        assert_eq!(mining.stage(&stone), Some(((1, 2, 4), 2)));
+        mining.update(Some(((1, 2, 4), &stone)), false, 0.2);
+        assert_eq!(mining.stage(&stone), None);
+    }
+
+    #[test]
+    fn cracks_grow() {
+        let cracked = |stage| {
+            crack_image(stage)
+                .pixels()
+                .filter(|pixel| pixel.0[3] > 0)
+                .count()
+        };
+        assert!(cracked(0) > 0);
+        assert!(cracked(BREAK_STAGES - 1) > cracked(0));
+        assert_eq!(crack_image(3), crack_image(3));
+    }
+}
@@ -1 +1,190 @@
-pub struct Object {}
+use crate::block_update::{grass_update, UpdateFn};
+use crate::inventory::ItemStack;
+use crate::rng::Rng;
+use std::collections::HashMap;
+
+/// How a block affects the player, entities and paths moving through it
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Movement {
+    /// Stops everything
+    Solid,
+    /// Can be walked through, like flowers
+    Open,
+    /// Stops the player and entities, but mobs that can open doors find paths through it
+    Door,
+    /// Can be walked through and climbed
+    Ladder,
+}
+
+/// Entry of a drop table, dropping between `min` and `max` items of block type `id` with
+/// probability `chance`
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct ItemDrop {
+    pub id: u8,
+    pub min: u8,
+    pub max: u8,
+    pub chance: f32,
+}
+
+/// What a block leaves behind when it is broken
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum Drops {
+    /// One of the block itself
+    Itself,
+    /// Every entry is rolled on its own, so a block can drop nothing or several kinds of items
+    Table(&'static [ItemDrop]),
+}
+
+/// Definition of a block type
+pub struct Object {
+    /// Name of the block type in data files, like recipes
+    pub name: &'static str,
+    /// Colour of the block on maps, also used to match colours when importing colour maps
+    pub colour: [u8; 3],
+    /// Seconds it takes to break the block by hand
+    pub hardness: f32,
+    pub movement: Movement,
+    pub drops: Drops,
+    /// Called when the block is updated, see [`crate::block_update`]
+    pub on_update: Option<UpdateFn>,
+    /// Whether the block gets random ticks, which are sent to `on_update`
+    pub random_ticks: bool,
+    /// Whether the block falls when there is nothing below it, like sand
+    pub gravity: bool,
+}
+
+impl Object {
+    /// Rolls the drop table of the block, whose id is `id`
+    pub fn roll_drops(&self, id: u8, rng: &mut Rng) -> Vec<ItemStack> {
+        let table = match self.drops {
+            Drops::Itself => return vec![ItemStack::new(id, 1)],
+            Drops::Table(table) => table,
+        };
+        let mut stacks = vec![];
+        for drop in table {
+            if rng.chance(drop.chance) {
+                let count = rng.range(drop.min as i32, drop.max as i32 + 1);
+                if count > 0 {
+                    stacks.push(ItemStack::new(drop.id, count as u8));
+                }
+            }
+        }
+        stacks
+    }
+}
+
+pub const AIR: u8 = 0;
+pub const STONE: u8 = 1;
+pub const DIRT: u8 = 2;
+pub const GRASS: u8 = 3;
+pub const COAL_ORE: u8 = 4;
+pub const IRON_ORE: u8 = 5;
+pub const GOLD_ORE: u8 = 6;
+pub const DIAMOND_ORE: u8 = 7;
+pub const LOG: u8 = 8;
+pub const LEAVES: u8 = 9;
+pub const FLOWER: u8 = 10;
+pub const PLANKS: u8 = 11;
+pub const COBBLESTONE: u8 = 12;
+pub const DOOR: u8 = 13;
+pub const LADDER: u8 = 14;
+pub const SAND: u8 = 15;
+pub const GRAVEL: u8 = 16;
+
+const fn always(id: u8) -> ItemDrop {
+    ItemDrop {
+        id,
+        min: 1,
+        max: 1,
+        chance: 1.0,
+    }
+}
+
+const STONE_DROPS: &[ItemDrop] = &[always(COBBLESTONE)];
+const GRASS_DROPS: &[ItemDrop] = &[always(DIRT)];
+const LEAVES_DROPS: &[ItemDrop] = &[ItemDrop {
+    chance: 0.25,
+    ..always(LEAVES)
+}];
+
+/// Returns the definitions of all built-in block types by id
+pub fn default_block_types() -> HashMap<u8, Object> {
+    let block = |name, colour, hardness| Object {
+        name,
+        colour,
+        hardness,
+        movement: Movement::Solid,
+        drops: Drops::Itself,
+        on_update: None,
+        random_ticks: false,
+        gravity: false,
+    };
+    HashMap::from([
+        (
+            STONE,
+            Object {
+                drops: Drops::Table(STONE_DROPS),
+                ..block("stone", [125, 125, 125], 1.5)
+            },
+        ),
+        (DIRT, block("dirt", [134, 96, 67], 0.5)),
+        (
+            GRASS,
+            Object {
+                drops: Drops::Table(GRASS_DROPS),
+                on_update: Some(grass_update),
+                random_ticks: true,
+                ..block("grass", [95, 159, 53], 0.6)
+            },
+        ),
+        (COAL_ORE, block("coal_ore", [60, 60, 60], 3.0)),
+        (IRON_ORE, block("iron_ore", [216, 175, 147], 3.0)),
+        (GOLD_ORE, block("gold_ore", [252, 238, 75], 3.0)),
+        (DIAMOND_ORE, block("diamond_ore", [93, 236, 245], 3.0)),
+        (LOG, block("log", [102, 81, 51], 2.0)),
+        (
+            LEAVES,
+            Object {
+                drops: Drops::Table(LEAVES_DROPS),
+                ..block("leaves", [60, 120, 30], 0.2)
+            },
+        ),
+        (
+            FLOWER,
+            Object {
+                movement: Movement::Open,
+                ..block("flower", [230, 40, 40], 0.0)
+            },
+        ),
+        (PLANKS, block("planks", [162, 130, 78], 2.0)),
+        (COBBLESTONE, block("cobblestone", [100, 100, 100], 2.0)),
+        (
+            DOOR,
+            Object {
+                movement: Movement::Door,
+                ..block("door", [140, 105, 60], 2.0)
+            },
+        ),
+        (
+            LADDER,
+            Object {
+                movement: Movement::Ladder,
+                ..block("ladder", [150, 120, 70], 0.4)
+            },
+        ),
+        (
+            SAND,
+            Object {
+                gravity: true,
+                ..block("sand", [219, 207, 163], 0.5)
+            },
+        ),
+        (
+            GRAVEL,
+            Object {
+                gravity: true,
+                ..block("gravel", [136, 126, 126], 0.6)
+            },
+        ),
+    ])
+}
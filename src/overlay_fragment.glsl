@@ -0,0 +1,12 @@
+#version 150
+
+in vec2 v_tex_coords;
+
+out vec4 color;
+
+uniform sampler2D tex;
+uniform vec4 tint;
+
+void main() {
+    color = texture(tex, v_tex_coords) * tint;
+}
@@ -0,0 +1,14 @@
+#version 150
+in vec3 position;
+in vec2 tex_coords;
+
+out vec2 v_tex_coords;
+
+uniform mat4 perspective;
+uniform mat4 view;
+uniform mat4 model;
+
+void main() {
+    gl_Position = perspective * view * model * vec4(position, 1.0);
+    v_tex_coords = tex_coords;
+}
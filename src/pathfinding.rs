@@ -0,0 +1,423 @@
+use crate::math::Vec3;
+use crate::object::Movement;
+use crate::physics::movement_at;
+use crate::world::World;
+use std::cmp::Ordering;
+use std::collections::{BinaryHeap, HashMap};
+
+/// Most nodes a search expands before it gives up, however many ticks it runs for
+pub const MAX_NODES: usize = 4096;
+
+/// Position of the lowest block an entity takes up, with its feet at the bottom of the block
+pub type Cell = (i32, i32, i32);
+
+/// What an entity can do when moving between cells
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct PathOptions {
+    pub width: f32,
+    pub height: f32,
+    /// Blocks the entity can jump up
+    pub jump_height: i32,
+    /// Blocks the entity is willing to drop down
+    pub max_fall: i32,
+    pub open_doors: bool,
+    pub climb_ladders: bool,
+}
+
+impl PathOptions {
+    /// Returns the number of blocks the entity takes up across and upwards
+    fn size(&self) -> (i32, i32) {
+        (
+            (self.width.ceil() as i32).max(1),
+            (self.height.ceil() as i32).max(1),
+        )
+    }
+
+    fn passes(&self, movement: Option<Movement>) -> bool {
+        match movement {
+            None | Some(Movement::Open) => true,
+            Some(Movement::Ladder) => true,
+            Some(Movement::Door) => self.open_doors,
+            Some(Movement::Solid) => false,
+        }
+    }
+
+    /// Returns whether the entity fits into `cell`. Cells in chunks that aren't loaded don't fit.
+    pub fn fits(&self, world: &World, (x, y, z): Cell) -> bool {
+        let (width, height) = self.size();
+        (0..width).all(|dx| {
+            (0..width).all(|dz| {
+                (0..height).all(|dy| {
+                    let (bx, by, bz) = (x + dx, y + dy, z + dz);
+                    world.chunk_exists(bx.div_euclid(16), bz.div_euclid(16))
+                        && (0..256).contains(&by)
+                        && self.passes(movement_at(world, bx, by, bz))
+                })
+            })
+        })
+    }
+
+    fn on_ladder(&self, world: &World, (x, y, z): Cell) -> bool {
+        self.climb_ladders && movement_at(world, x, y, z) == Some(Movement::Ladder)
+    }
+
+    /// Returns whether the entity can stand in `cell`, on a block or holding on to a ladder
+    pub fn can_stand(&self, world: &World, (x, y, z): Cell) -> bool {
+        let (width, _) = self.size();
+        let supported = (0..width).any(|dx| {
+            (0..width).any(|dz| {
+                matches!(
+                    movement_at(world, x + dx, y - 1, z + dz),
+                    Some(Movement::Solid | Movement::Door)
+                )
+            })
+        });
+        self.fits(world, (x, y, z)) && (supported || self.on_ladder(world, (x, y, z)))
+    }
+
+    /// Returns the cells the entity can move to from `cell` and the cost of each move
+    fn neighbours(&self, world: &World, (x, y, z): Cell) -> Vec<(Cell, f32)> {
+        let mut neighbours = vec![];
+        for (dx, dz) in [(1, 0), (-1, 0), (0, 1), (0, -1)] {
+            let side = (x + dx, y, z + dz);
+            if self.fits(world, side) {
+                // walks ahead or drops down to the first block below
+                let landing = (0..=self.max_fall)
+                    .map(|fall| (side.0, side.1 - fall, side.2))
+                    .take_while(|cell| self.fits(world, *cell))
+                    .find(|cell| self.can_stand(world, *cell));
+                if let Some(landing) = landing {
+                    let fall = (y - landing.1) as f32;
+                    neighbours.push((landing, 1.0 + fall * 0.1));
+                }
+            } else {
+                // jumps up, as long as there is room above
+                let step = (1..=self.jump_height)
+                    .take_while(|up| self.fits(world, (x, y + up, z)))
+                    .map(|up| (side.0, side.1 + up, side.2))
+                    .find(|cell| self.fits(world, *cell));
+                if let Some(step) = step.filter(|cell| self.can_stand(world, *cell)) {
+                    neighbours.push((step, 1.0 + (step.1 - y) as f32 * 0.5));
+                }
+            }
+        }
+        if self.on_ladder(world, (x, y, z)) {
+            for dy in [1, -1] {
+                let cell = (x, y + dy, z);
+                if self.can_stand(world, cell) || self.on_ladder(world, cell) {
+                    neighbours.push((cell, 1.0));
+                }
+            }
+        }
+        neighbours
+    }
+
+    /// Returns the position of the bottom centre of the entity when it is in `cell`
+    pub fn centre(&self, (x, y, z): Cell) -> Vec3 {
+        let half = self.size().0 as f32 / 2.0;
+        Vec3(x as f32 + half, y as f32, z as f32 + half)
+    }
+
+    /// Returns whether the entity can walk in a straight line from `from` to `to` without
+    /// jumping, falling or climbing
+    fn straight(&self, world: &World, from: Cell, to: Cell) -> bool {
+        if from.1 != to.1 {
+            return false;
+        }
+        let (start, end) = (self.centre(from), self.centre(to));
+        // a little less than half the width, so that boxes exactly as wide as a block fit
+        let half = self.width / 2.0 - 0.01;
+        let steps = ((end - start).len() * 4.0).ceil() as i32;
+        (0..=steps).all(|step| {
+            let Vec3(x, _, z) = start + (end - start).scale(step as f32 / steps.max(1) as f32);
+            [(-half, -half), (-half, half), (half, -half), (half, half)]
+                .iter()
+                .all(|(dx, dz)| {
+                    let cell = ((x + dx).floor() as i32, from.1, (z + dz).floor() as i32);
+                    let column = PathOptions {
+                        width: 1.0,
+                        ..*self
+                    };
+                    column.can_stand(world, cell) && !self.on_ladder(world, cell)
+                })
+        })
+    }
+
+    /// Removes the cells that the entity can walk past in a straight line
+    fn smooth(&self, world: &World, cells: &[Cell]) -> Vec<Cell> {
+        let mut smoothed = cells[..1].to_vec();
+        let mut anchor = cells[0];
+        for pair in cells.windows(2).skip(1) {
+            let (previous, cell) = (pair[0], pair[1]);
+            if !self.straight(world, anchor, cell) {
+                smoothed.push(previous);
+                anchor = previous;
+            }
+        }
+        if cells.len() > 1 {
+            smoothed.push(cells[cells.len() - 1]);
+        }
+        smoothed
+    }
+}
+
+/// Path through the world, as the bottom centres of the entity at its turning points. The first
+/// point is the start.
+#[derive(Debug, Clone, PartialEq)]
+pub struct Path {
+    pub points: Vec<Vec3>,
+    /// Every cell along the path, before it was smoothed
+    pub cells: Vec<Cell>,
+}
+
+/// State of a [`PathSearch`]
+#[derive(Debug, Clone, PartialEq)]
+pub enum SearchState {
+    /// Ran out of budget for this tick
+    Searching,
+    Found(Path),
+    /// There is no path, or finding it took more than [`MAX_NODES`]
+    NotFound,
+}
+
+/// Cell in the open set, ordered so that the heap returns the lowest estimated cost first
+#[derive(Debug, Clone, Copy, PartialEq)]
+struct Open {
+    estimate: f32,
+    cell: Cell,
+}
+
+impl Eq for Open {}
+
+impl Ord for Open {
+    fn cmp(&self, other: &Open) -> Ordering {
+        other
+            .estimate
+            .total_cmp(&self.estimate)
+            .then_with(|| self.cell.cmp(&other.cell))
+    }
+}
+
+impl PartialOrd for Open {
+    fn partial_cmp(&self, other: &Open) -> Option<Ordering> {
+        Some(self.cmp(other))
+    }
+}
+
+/// A* search for a path between two cells that can be spread over several ticks
+#[derive(Debug, Clone)]
+pub struct PathSearch {
+    goal: Cell,
+    options: PathOptions,
+    open: BinaryHeap<Open>,
+    /// Cheapest known cost to reach a cell, and the cell it is reached from
+    costs: HashMap<Cell, (f32, Cell)>,
+    expanded: usize,
+}
+
+impl PathSearch {
+    pub fn new(start: Cell, goal: Cell, options: PathOptions) -> PathSearch {
+        let mut search = PathSearch {
+            goal,
+            options,
+            open: BinaryHeap::new(),
+            costs: HashMap::new(),
+            expanded: 0,
+        };
+        search.costs.insert(start, (0.0, start));
+        search.open.push(Open {
+            estimate: search.heuristic(start),
+            cell: start,
+        });
+        search
+    }
+
+    /// Never overestimates, as every move costs at least 1 per block walked
+    fn heuristic(&self, (x, _, z): Cell) -> f32 {
+        ((x - self.goal.0).abs() + (z - self.goal.2).abs()) as f32
+    }
+
+    /// Expands up to `budget` nodes, taking the ones it used from the budget
+    pub fn step(&mut self, world: &World, budget: &mut usize) -> SearchState {
+        while *budget > 0 {
+            let Some(Open { cell, .. }) = self.open.pop() else {
+                return SearchState::NotFound;
+            };
+            if cell == self.goal {
+                return SearchState::Found(self.path(world));
+            }
+            if self.expanded >= MAX_NODES {
+                return SearchState::NotFound;
+            }
+            *budget -= 1;
+            self.expanded += 1;
+            let cost = self.costs[&cell].0;
+            for (next, step) in self.options.neighbours(world, cell) {
+                let cost = cost + step;
+                if self
+                    .costs
+                    .get(&next)
+                    .is_some_and(|(known, _)| *known <= cost)
+                {
+                    continue;
+                }
+                self.costs.insert(next, (cost, cell));
+                self.open.push(Open {
+                    estimate: cost + self.heuristic(next),
+                    cell: next,
+                });
+            }
+        }
+        SearchState::Searching
+    }
+
+    fn path(&self, world: &World) -> Path {
+        let mut cells = vec![self.goal];
+        while let Some((_, from)) = self.costs.get(cells.last().unwrap()) {
+            if from == cells.last().unwrap() {
+                break;
+            }
+            cells.push(*from);
+        }
+        cells.reverse();
+        let points = self
+            .options
+            .smooth(world, &cells)
+            .into_iter()
+            .map(|cell| self.options.centre(cell))
+            .collect();
+        Path { points, cells }
+    }
+}
+
+/// Finds a path in one go, for searches that don't need to be spread over ticks
+pub fn find_path(world: &World, start: Cell, goal: Cell, options: PathOptions) -> Option<Path> {
+    let mut search = PathSearch::new(start, goal, options);
+    let mut budget = usize::MAX;
+    match search.step(world, &mut budget) {
+        SearchState::Found(path) => Some(path),
+        _ => None,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::object::{DOOR, LADDER, STONE};
+    use crate::pathfinding::{find_path, Cell, PathOptions, PathSearch, SearchState};
+    use crate::world::World;
+
+    const MOB: PathOptions = PathOptions {
+        width: 0.9,
+        height: 0.9,
+        jump_height: 1,
+        max_fall: 3,
+        open_doors: false,
+        climb_ladders: true,
+    };
+
+    /// Builds a world from a map of the chunk at the origin seen from above, with rows along x.
+    /// `.` is floor, `#` a high wall, `=` a block to jump on, `D` a door in a wall, `L` a ladder
+    /// against a `T`, a tower three blocks high. Everything outside the map is wall. Returns the
+    /// world and the cells of `S` and `G`.
+    fn maze(rows: &[&str]) -> (World, Cell, Cell) {
+        let mut world = World::new();
+        // empty chunks have a stone floor at y = 0
+        world.create_empty_chunk(0, 0);
+        let (mut start, mut goal) = ((0, 1, 0), (0, 1, 0));
+        for x in 0..16 {
+            for z in 0..16 {
+                let tile = rows
+                    .get(z as usize)
+                    .and_then(|row| row.chars().nth(x as usize))
+                    .unwrap_or('#');
+                let column: &[u8] = match tile {
+                    '#' => &[STONE; 6],
+                    '=' => &[STONE],
+                    'D' => &[DOOR, STONE, STONE, STONE, STONE, STONE],
+                    'L' => &[LADDER; 3],
+                    'T' => &[STONE; 3],
+                    _ => &[],
+                };
+                for (y, id) in column.iter().enumerate() {
+                    world.set_block(x, y as i32 + 1, z, *id);
+                }
+                match tile {
+                    'S' => start = (x, 1, z),
+                    'G' => goal = (x, 1, z),
+                    _ => (),
+                }
+            }
+        }
+        (world, start, goal)
+    }
+
+    #[test]
+    fn solves_maze_over_ticks() {
+        let (world, start, goal) = maze(&["S.#....", "#.#.##.", "#...#G."]);
+        let path = find_path(&world, start, goal, MOB).unwrap();
+        // the shortest way round the walls
+        assert_eq!(path.cells.len(), 14);
+        assert_eq!(path.cells[0], start);
+        assert_eq!(*path.cells.last().unwrap(), goal);
+        for pair in path.cells.windows(2) {
+            let ((x, _, z), (next_x, _, next_z)) = (pair[0], pair[1]);
+            assert_eq!((next_x - x).abs() + (next_z - z).abs(), 1);
+        }
+        // straight corridors are walked in one go
+        assert!(path.points.len() < path.cells.len());
+        assert_eq!(path.points[0], MOB.centre(start));
+
+        // the same path, a few nodes per tick
+        let mut search = PathSearch::new(start, goal, MOB);
+        let mut ticks = 0;
+        let found = loop {
+            ticks += 1;
+            match search.step(&world, &mut 3) {
+                SearchState::Searching => continue,
+                state => break state,
+            }
+        };
+        assert!(ticks > 1);
+        assert_eq!(found, SearchState::Found(path));
+
+        let (world, start, goal) = maze(&["S.#.G"]);
+        assert_eq!(find_path(&world, start, goal, MOB), None);
+    }
+
+    #[test]
+    fn jumps_falls_doors_and_ladders() {
+        let (world, start, goal) = maze(&["S=G"]);
+        assert!(find_path(&world, start, goal, MOB).is_some());
+        let grounded = PathOptions {
+            jump_height: 0,
+            ..MOB
+        };
+        assert_eq!(find_path(&world, start, goal, grounded), None);
+
+        let (world, start, goal) = maze(&["S.D.G"]);
+        assert_eq!(find_path(&world, start, goal, MOB), None);
+        let opener = PathOptions {
+            open_doors: true,
+            ..MOB
+        };
+        assert!(find_path(&world, start, goal, opener).is_some());
+
+        // up the ladder onto the tower
+        let (world, start, _) = maze(&["S.LT"]);
+        let goal = (3, 4, 0);
+        let path = find_path(&world, start, goal, MOB).unwrap();
+        assert!(path.cells.contains(&(2, 3, 0)));
+        let climbless = PathOptions {
+            climb_ladders: false,
+            ..MOB
+        };
+        assert_eq!(find_path(&world, start, goal, climbless), None);
+        // and back down, by dropping past the ladder if it isn't too far
+        assert!(find_path(&world, goal, start, climbless).is_some());
+        let careful = PathOptions {
+            max_fall: 2,
+            ..climbless
+        };
+        assert_eq!(find_path(&world, goal, start, careful), None);
+    }
+}
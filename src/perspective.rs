@@ -1,18 +1,75 @@
-use glium::Frame;
-use glium::Surface;
-
-pub fn create_perspective(target: &Frame) -> [[f32; 4]; 4] {
-    let (width, height) = target.get_dimensions();
-    let aspect_ratio = height as f32 / width as f32;
-    let fov: f32 = std::f32::consts::PI / 3.0;
-    let zfar = 1024.0;
-    let znear = 0.1;
-    let f = 1.0 / (fov / 2.0).tan();
-
-    [
-        [f * aspect_ratio, 0.0, 0.0, 0.0],
-        [0.0, f, 0.0, 0.0],
-        [0.0, 0.0, (zfar + znear) / (zfar - znear), 1.0],
-        [0.0, 0.0, -(2.0 * zfar * znear) / (zfar - znear), 0.0],
-    ]
+use crate::math::Mat4;
+
+/// Distance of the near clipping plane in blocks
+const ZNEAR: f32 = 0.1;
+
+/// Which side of the window the field of view setting measures
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum FovAxis {
+    /// From the bottom to the top, so wider windows show more at the sides
+    Vertical,
+    /// From the left to the right, so taller windows show more above and below
+    Horizontal,
+}
+
+impl FovAxis {
+    pub const ALL: [FovAxis; 2] = [FovAxis::Vertical, FovAxis::Horizontal];
+
+    pub fn name(&self) -> &'static str {
+        match self {
+            FovAxis::Vertical => "vertical",
+            FovAxis::Horizontal => "horizontal",
+        }
+    }
+
+    pub fn from_name(name: &str) -> Option<FovAxis> {
+        FovAxis::ALL.into_iter().find(|axis| axis.name() == name)
+    }
+
+    /// Returns the other axis
+    pub fn cycle(&self) -> FovAxis {
+        match self {
+            FovAxis::Vertical => FovAxis::Horizontal,
+            FovAxis::Horizontal => FovAxis::Vertical,
+        }
+    }
+}
+
+/// Returns the vertical field of view in radians of a view whose field of view along `axis` is
+/// `fov` radians, for the width of the window divided by its height `aspect`
+pub fn vertical_fov(fov: f32, axis: FovAxis, aspect: f32) -> f32 {
+    match axis {
+        FovAxis::Vertical => fov,
+        FovAxis::Horizontal => 2.0 * ((fov / 2.0).tan() / aspect).atan(),
+    }
+}
+
+/// Returns the projection matrix for the vertical field of view `fov`, in radians, the width of
+/// the window divided by its height `aspect` and the far clipping plane at `zfar`
+pub fn create_perspective(fov: f32, aspect: f32, zfar: f32) -> Mat4 {
+    Mat4::perspective(fov, aspect, ZNEAR, zfar)
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::math::Vec3;
+    use crate::perspective::{create_perspective, vertical_fov, FovAxis};
+    use std::f32::consts::PI;
+
+    #[test]
+    fn fov_along_either_axis() {
+        assert_eq!(vertical_fov(1.0, FovAxis::Vertical, 2.0), 1.0);
+        assert!((vertical_fov(1.0, FovAxis::Horizontal, 1.0) - 1.0).abs() < 1e-6);
+
+        // a horizontal field of view of 90 degrees reaches the edges of a 2:1 window
+        let fov = vertical_fov(PI / 2.0, FovAxis::Horizontal, 2.0);
+        let projection = create_perspective(fov, 2.0, 100.0);
+        let edge = projection.transform_point(Vec3(10.0, 0.0, 10.0));
+        assert!((edge.0 - 1.0).abs() < 1e-4);
+        let top = projection.transform_point(Vec3(0.0, 5.0, 10.0));
+        assert!((top.1 - 1.0).abs() < 1e-4);
+
+        assert_eq!(FovAxis::from_name("horizontal"), Some(FovAxis::Horizontal));
+        assert_eq!(FovAxis::Horizontal.cycle(), FovAxis::Vertical);
+    }
 }
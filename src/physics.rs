@@ -0,0 +1,212 @@
+use crate::math::Vec3;
+use crate::object::{Movement, AIR};
+use crate::world::World;
+
+/// Distance below a box that is checked for ground
+const GROUND_PROBE: f32 = 0.001;
+
+/// Axis aligned bounding box in world coordinates
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct Aabb {
+    pub min: Vec3,
+    pub max: Vec3,
+}
+
+impl Aabb {
+    /// Returns a box of the given size standing on `bottom`, the centre of its bottom face
+    pub fn standing(bottom: Vec3, width: f32, height: f32) -> Aabb {
+        let half = width / 2.0;
+        Aabb {
+            min: Vec3(bottom.0 - half, bottom.1, bottom.2 - half),
+            max: Vec3(bottom.0 + half, bottom.1 + height, bottom.2 + half),
+        }
+    }
+
+    /// Returns the box of the block at `position`
+    pub fn block((x, y, z): (i32, i32, i32)) -> Aabb {
+        Aabb {
+            min: Vec3(x as f32, y as f32, z as f32),
+            max: Vec3(x as f32 + 1.0, y as f32 + 1.0, z as f32 + 1.0),
+        }
+    }
+
+    #[must_use]
+    pub fn offset(&self, by: Vec3) -> Aabb {
+        Aabb {
+            min: self.min + by,
+            max: self.max + by,
+        }
+    }
+
+    /// Returns whether the boxes overlap. Boxes that only touch don't.
+    pub fn intersects(&self, other: &Aabb) -> bool {
+        self.min.0 < other.max.0
+            && self.max.0 > other.min.0
+            && self.min.1 < other.max.1
+            && self.max.1 > other.min.1
+            && self.min.2 < other.max.2
+            && self.max.2 > other.min.2
+    }
+
+    /// Returns the positions of the blocks the box overlaps
+    fn blocks(&self) -> impl Iterator<Item = (i32, i32, i32)> {
+        let min = (
+            self.min.0.floor() as i32,
+            self.min.1.floor() as i32,
+            self.min.2.floor() as i32,
+        );
+        let max = (
+            self.max.0.ceil() as i32,
+            self.max.1.ceil() as i32,
+            self.max.2.ceil() as i32,
+        );
+        (min.0..max.0).flat_map(move |x| {
+            (min.1..max.1).flat_map(move |y| (min.2..max.2).map(move |z| (x, y, z)))
+        })
+    }
+}
+
+/// Returns how the block at the position affects movement, or `None` for air and blocks in chunks
+/// that aren't loaded
+pub fn movement_at(world: &World, x: i32, y: i32, z: i32) -> Option<Movement> {
+    let id = world.block_id_at(x, y, z).filter(|id| *id != AIR)?;
+    Some(
+        world
+            .block_type(id)
+            .map_or(Movement::Solid, |object| object.movement),
+    )
+}
+
+/// Returns whether the block at the position stops movement. Blocks in chunks that aren't loaded
+/// don't.
+pub fn is_solid(world: &World, x: i32, y: i32, z: i32) -> bool {
+    matches!(
+        movement_at(world, x, y, z),
+        Some(Movement::Solid | Movement::Door)
+    )
+}
+
+/// Returns whether the box overlaps a solid block
+pub fn colliding(world: &World, aabb: &Aabb) -> bool {
+    aabb.blocks().any(|(x, y, z)| is_solid(world, x, y, z))
+}
+
+/// Returns whether the box rests on a solid block
+pub fn on_ground(world: &World, aabb: &Aabb) -> bool {
+    colliding(world, &aabb.offset(Vec3(0.0, -GROUND_PROBE, 0.0)))
+}
+
+/// Returns whether the box overlaps a ladder
+pub fn on_ladder(world: &World, aabb: &Aabb) -> bool {
+    aabb.blocks()
+        .any(|(x, y, z)| movement_at(world, x, y, z) == Some(Movement::Ladder))
+}
+
+/// Result of [`move_aabb`]
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct Collision {
+    /// How far the box actually moved
+    pub motion: Vec3,
+    /// Whether the movement was stopped along the x, y and z axes
+    pub stopped: (bool, bool, bool),
+}
+
+/// Returns the component of `v` along `axis`, 0 for x, 1 for y and 2 for z
+fn component(v: Vec3, axis: usize) -> f32 {
+    [v.0, v.1, v.2][axis]
+}
+
+fn along(axis: usize, distance: f32) -> Vec3 {
+    let mut v = [0.0; 3];
+    v[axis] = distance;
+    Vec3(v[0], v[1], v[2])
+}
+
+/// Returns how far the box can move along one axis before it hits a solid block, up to
+/// `distance`. Blocks the box already overlaps are ignored so it can't get stuck in them.
+fn sweep(world: &World, aabb: &Aabb, axis: usize, distance: f32) -> f32 {
+    if distance == 0.0 {
+        return 0.0;
+    }
+    let moved = aabb.offset(along(axis, distance));
+    let swept = Aabb {
+        min: Vec3(
+            aabb.min.0.min(moved.min.0),
+            aabb.min.1.min(moved.min.1),
+            aabb.min.2.min(moved.min.2),
+        ),
+        max: Vec3(
+            aabb.max.0.max(moved.max.0),
+            aabb.max.1.max(moved.max.1),
+            aabb.max.2.max(moved.max.2),
+        ),
+    };
+    let mut allowed = distance;
+    for block in swept.blocks() {
+        let (x, y, z) = block;
+        let solid = Aabb::block(block);
+        if !is_solid(world, x, y, z) || !swept.intersects(&solid) || aabb.intersects(&solid) {
+            continue;
+        }
+        if distance > 0.0 {
+            allowed = allowed.min(component(solid.min, axis) - component(aabb.max, axis));
+        } else {
+            allowed = allowed.max(component(solid.max, axis) - component(aabb.min, axis));
+        }
+    }
+    allowed
+}
+
+/// Moves the box by `motion`, one axis at a time starting with y, stopping at solid blocks. Used
+/// by the player and by entities.
+pub fn move_aabb(world: &World, aabb: &Aabb, motion: Vec3) -> Collision {
+    let mut current = *aabb;
+    let mut moved = [0.0; 3];
+    let mut stopped = [false; 3];
+    for axis in [1, 0, 2] {
+        let wanted = component(motion, axis);
+        let distance = sweep(world, &current, axis, wanted);
+        current = current.offset(along(axis, distance));
+        moved[axis] = distance;
+        stopped[axis] = distance != wanted;
+    }
+    Collision {
+        motion: Vec3(moved[0], moved[1], moved[2]),
+        stopped: (stopped[0], stopped[1], stopped[2]),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::math::Vec3;
+    use crate::object::STONE;
+    use crate::physics::{colliding, move_aabb, on_ground, Aabb};
+    use crate::world::World;
+
+    #[test]
+    fn stops_at_blocks() {
+        let mut world = World::new();
+        // empty chunks have a stone floor at y = 0
+        world.create_empty_chunk(0, 0);
+        world.set_block(3, 1, 2, STONE);
+
+        // falls onto the floor
+        let aabb = Aabb::standing(Vec3(2.5, 3.0, 2.5), 0.6, 1.8);
+        let collision = move_aabb(&world, &aabb, Vec3(0.0, -5.0, 0.0));
+        assert_eq!(collision.motion, Vec3(0.0, -2.0, 0.0));
+        assert_eq!(collision.stopped, (false, true, false));
+        let landed = aabb.offset(collision.motion);
+        assert!(on_ground(&world, &landed));
+        assert!(!colliding(&world, &landed));
+
+        // walks into the wall on the side, but slides along it
+        let collision = move_aabb(&world, &landed, Vec3(1.0, 0.0, 0.2));
+        assert!((collision.motion.0 - 0.2).abs() < 1e-5);
+        assert!((collision.motion.2 - 0.2).abs() < 1e-5);
+        assert_eq!(collision.stopped, (true, false, false));
+
+        // nothing to stand on in the air
+        let above = Aabb::standing(Vec3(0.5, 1.5, 0.5), 0.6, 1.8);
+        assert!(!on_ground(&world, &above));
+    }
+}
@@ -0,0 +1,104 @@
+use crate::inventory::{Inventory, ItemStack, INVENTORY_SIZE, MAX_STACK};
+use crate::math::Vec3;
+use crate::save::write_atomic;
+use std::fs;
+use std::io;
+use std::path::Path;
+
+/// The state of the player that is kept between sessions. Saved as `player.dat` in the world
+/// directory with one `key value` pair per line, like `world.dat`:
+///
+/// - `position x y z`
+/// - `selected n`, the selected hotbar slot
+/// - `slot n id count`, one line per inventory slot that isn't empty
+#[derive(Debug, Default, Clone, PartialEq)]
+pub struct Player {
+    /// Position of the camera, `None` if the player hasn't been saved yet
+    pub position: Option<Vec3>,
+    pub inventory: Inventory,
+}
+
+impl Player {
+    pub fn to_text(&self) -> String {
+        let mut text = String::new();
+        if let Some(Vec3(x, y, z)) = self.position {
+            text += &format!("position {} {} {}\n", x, y, z);
+        }
+        text += &format!("selected {}\n", self.inventory.selected());
+        for (index, stack) in self.inventory.slots().iter().enumerate() {
+            if let Some(stack) = stack {
+                text += &format!("slot {} {} {}\n", index, stack.id, stack.count);
+            }
+        }
+        text
+    }
+
+    /// Parses a player written by [`Player::to_text`]. Unknown keys and lines that can't be
+    /// parsed are skipped, and stacks larger than [`MAX_STACK`] are cut down to it.
+    pub fn from_text(text: &str) -> Player {
+        let mut player = Player::default();
+        for line in text.lines() {
+            let Some((key, value)) = line.split_once(' ') else {
+                continue;
+            };
+            let numbers: Vec<f32> = value
+                .split_whitespace()
+                .filter_map(|n| n.parse().ok())
+                .collect();
+            match (key, &numbers[..]) {
+                ("position", &[x, y, z]) => player.position = Some(Vec3(x, y, z)),
+                ("selected", &[n]) => player.inventory.select(n as usize),
+                ("slot", &[n, id, count])
+                    if (n as usize) < INVENTORY_SIZE
+                        && (0.0..=u8::MAX as f32).contains(&id)
+                        && count >= 1.0 =>
+                {
+                    let stack = ItemStack::new(id as u8, (count as u8).min(MAX_STACK));
+                    player.inventory.set_slot(n as usize, Some(stack));
+                }
+                _ => (),
+            }
+        }
+        player
+    }
+
+    /// Loads the player saved in the world directory `dir`, if there is one
+    pub fn load(dir: &Path) -> io::Result<Player> {
+        match fs::read_to_string(dir.join("player.dat")) {
+            Ok(text) => Ok(Player::from_text(&text)),
+            Err(e) if e.kind() == io::ErrorKind::NotFound => Ok(Player::default()),
+            Err(e) => Err(e),
+        }
+    }
+
+    pub fn save(&self, dir: &Path) -> io::Result<()> {
+        fs::create_dir_all(dir)?;
+        write_atomic(&dir.join("player.dat"), self.to_text())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::inventory::{ItemStack, MAX_STACK};
+    use crate::math::Vec3;
+    use crate::player::Player;
+
+    #[test]
+    fn text_round_trip() {
+        let mut player = Player {
+            position: Some(Vec3(1.5, 80.0, -20.25)),
+            ..Player::default()
+        };
+        player.inventory.add(3, 70);
+        player.inventory.select(4);
+
+        let loaded = Player::from_text(&(player.to_text() + "slot 99 1 1\nhealth 20\n"));
+        assert_eq!(loaded, player);
+        assert_eq!(Player::from_text(""), Player::default());
+
+        let edited = Player::from_text("slot 0 3 1000\nslot 1 300 5\nslot 2 3 -1\n");
+        assert_eq!(edited.inventory.slot(0), Some(ItemStack::new(3, MAX_STACK)));
+        assert_eq!(edited.inventory.slot(1), None);
+        assert_eq!(edited.inventory.slot(2), None);
+    }
+}
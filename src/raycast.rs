@@ -0,0 +1,109 @@
+use crate::math::Vec3;
+use crate::object::AIR;
+use crate::world::World;
+
+/// A block hit by a ray
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct RaycastHit {
+    /// Position of the block in world coordinates
+    pub block: (i32, i32, i32),
+    /// Normal of the face the ray entered through, zero if the ray started inside the block
+    pub normal: (i32, i32, i32),
+    /// Distance from the origin to the point where the ray entered the block
+    pub distance: f32,
+}
+
+impl RaycastHit {
+    /// Returns the position next to the hit face, where a block placed against it would go
+    pub fn adjacent(&self) -> (i32, i32, i32) {
+        (
+            self.block.0 + self.normal.0,
+            self.block.1 + self.normal.1,
+            self.block.2 + self.normal.2,
+        )
+    }
+}
+
+/// Returns the first block that isn't air along the ray from `origin` in `direction`, visiting
+/// every block the ray passes through up to `max_distance`. Blocks in missing chunks count as air.
+pub fn raycast(
+    world: &World,
+    origin: Vec3,
+    direction: Vec3,
+    max_distance: f32,
+) -> Option<RaycastHit> {
+    let direction = direction.normalize();
+    let origin = [origin.0, origin.1, origin.2];
+    let direction = [direction.0, direction.1, direction.2];
+    let mut block = origin.map(|c| c.floor() as i32);
+    let step = direction.map(|d| if d > 0.0 { 1 } else { -1 });
+    // distance along the ray between two block borders on each axis
+    let delta = direction.map(|d| {
+        if d == 0.0 {
+            f32::INFINITY
+        } else {
+            1.0 / d.abs()
+        }
+    });
+    // distance along the ray to the next block border on each axis
+    let mut next = [0, 1, 2].map(|i| {
+        let border = if step[i] > 0 {
+            block[i] as f32 + 1.0 - origin[i]
+        } else {
+            origin[i] - block[i] as f32
+        };
+        border * delta[i]
+    });
+
+    let mut normal = [0; 3];
+    let mut distance = 0.0;
+    while distance <= max_distance {
+        if world
+            .block_id_at(block[0], block[1], block[2])
+            .is_some_and(|id| id != AIR)
+        {
+            return Some(RaycastHit {
+                block: (block[0], block[1], block[2]),
+                normal: (normal[0], normal[1], normal[2]),
+                distance,
+            });
+        }
+        let axis = (0..3).min_by(|a, b| next[*a].total_cmp(&next[*b])).unwrap();
+        distance = next[axis];
+        next[axis] += delta[axis];
+        block[axis] += step[axis];
+        normal = [0; 3];
+        normal[axis] = -step[axis];
+    }
+    None
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::math::Vec3;
+    use crate::object::{AIR, DIRT};
+    use crate::raycast::raycast;
+    use crate::world::World;
+
+    #[test]
+    fn hits_first_solid_block() {
+        let mut world = World::new();
+        world.create_empty_chunk(0, 0);
+        world.create_empty_chunk(-1, 0);
+        world.chunks.get_mut(&(-1, 0)).unwrap().blocks[13][5][2] = DIRT;
+
+        let hit = raycast(&world, Vec3(0.5, 5.5, 2.5), Vec3(-1.0, 0.0, 0.0), 10.0).unwrap();
+        assert_eq!(hit.block, (-3, 5, 2));
+        assert_eq!(hit.normal, (1, 0, 0));
+        assert!((hit.distance - 2.5).abs() < 0.001);
+        assert_eq!(hit.adjacent(), (-2, 5, 2));
+
+        assert!(raycast(&world, Vec3(0.5, 5.5, 2.5), Vec3(-1.0, 0.0, 0.0), 2.0).is_none());
+        // the floor of the empty chunk
+        let hit = raycast(&world, Vec3(4.2, 10.0, 4.7), Vec3(0.3, -1.0, 0.2), 20.0).unwrap();
+        assert_eq!(hit.normal, (0, 1, 0));
+        assert_eq!(hit.block.1, 0);
+        assert_ne!(world.block_id_at(hit.block.0, 1, hit.block.2), None);
+        assert_eq!(world.block_id_at(hit.block.0, 1, hit.block.2), Some(AIR));
+    }
+}
@@ -0,0 +1,57 @@
+/// Small deterministic pseudo random number generator (SplitMix64).
+///
+/// World generation must produce the same output for the same seed on every platform and
+/// with every dependency version, so we don't rely on an external crate for this.
+#[derive(Debug, Clone)]
+pub struct Rng {
+    state: u64,
+}
+
+/// Mixes the bits of `value`, used to derive independent seeds from coordinates
+pub fn mix(mut value: u64) -> u64 {
+    value = (value ^ (value >> 30)).wrapping_mul(0xbf58_476d_1ce4_e5b9);
+    value = (value ^ (value >> 27)).wrapping_mul(0x94d0_49bb_1331_11eb);
+    value ^ (value >> 31)
+}
+
+/// Hashes a world seed, a 2D position and a salt into a new seed
+pub fn hash(seed: u64, x: i32, z: i32, salt: u64) -> u64 {
+    let mut h = mix(seed ^ salt.wrapping_mul(0x9e37_79b9_7f4a_7c15));
+    h = mix(h ^ (x as u32 as u64));
+    mix(h ^ ((z as u32 as u64) << 32))
+}
+
+impl Rng {
+    pub fn new(seed: u64) -> Rng {
+        Rng { state: seed }
+    }
+
+    /// Creates a generator for the chunk at **chunk position** (x, z). Different salts give
+    /// independent streams, so adding a new feature doesn't change the existing ones.
+    pub fn for_chunk(seed: u64, chunk_x: i32, chunk_z: i32, salt: u64) -> Rng {
+        Rng::new(hash(seed, chunk_x, chunk_z, salt))
+    }
+
+    pub fn next_u64(&mut self) -> u64 {
+        self.state = self.state.wrapping_add(0x9e37_79b9_7f4a_7c15);
+        mix(self.state)
+    }
+
+    /// Returns a float in [0, 1)
+    pub fn next_f32(&mut self) -> f32 {
+        (self.next_u64() >> 40) as f32 / (1u64 << 24) as f32
+    }
+
+    /// Returns an integer in [min, max)
+    pub fn range(&mut self, min: i32, max: i32) -> i32 {
+        if max <= min {
+            return min;
+        }
+        min + (self.next_u64() % (max - min) as u64) as i32
+    }
+
+    /// Returns true with probability `p`
+    pub fn chance(&mut self, p: f32) -> bool {
+        self.next_f32() < p
+    }
+}
@@ -0,0 +1,246 @@
+use crate::chunk::Chunk;
+use crate::world::World;
+use crate::world_gen::{Generator, WorldGen};
+use std::fs;
+use std::io::{self, Write};
+use std::path::{Path, PathBuf};
+
+const CHUNK_SIZE: usize = 16 * 256 * 16;
+
+/// A world saved to a directory.
+///
+/// - `world.dat` holds one `key value` pair per line, like the seed, the generator and the world
+///   time in ticks. Unknown keys are ignored, so older versions can still open worlds written by
+///   newer ones.
+/// - `chunks/<x>.<z>.chunk` holds the blocks of the chunk at **chunk position** (x, z), run length
+///   encoded as pairs of (count, id) bytes in the same x, y, z order as [`Chunk::blocks`].
+pub struct WorldSave {
+    dir: PathBuf,
+}
+
+fn invalid_data(message: String) -> io::Error {
+    io::Error::new(io::ErrorKind::InvalidData, message)
+}
+
+/// Writes `contents` to a temporary file next to `path` and then renames it over `path`, so a
+/// crash while writing leaves the old file intact instead of a half written one
+pub fn write_atomic(path: &Path, contents: impl AsRef<[u8]>) -> io::Result<()> {
+    let mut temporary = path.as_os_str().to_owned();
+    temporary.push(".tmp");
+    let temporary = PathBuf::from(temporary);
+    let mut file = fs::File::create(&temporary)?;
+    file.write_all(contents.as_ref())?;
+    file.sync_all()?;
+    fs::rename(&temporary, path)
+}
+
+pub fn encode_chunk(chunk: &Chunk) -> Vec<u8> {
+    let mut data = vec![];
+    let mut run: Option<(u8, u8)> = None;
+    for id in chunk.blocks.iter().flatten().flatten() {
+        run = match run {
+            Some((count, current)) if current == *id && count < u8::MAX => {
+                Some((count + 1, current))
+            }
+            Some((count, current)) => {
+                data.push(count);
+                data.push(current);
+                Some((1, *id))
+            }
+            None => Some((1, *id)),
+        };
+    }
+    if let Some((count, current)) = run {
+        data.push(count);
+        data.push(current);
+    }
+    data
+}
+
+pub fn decode_chunk(data: &[u8]) -> io::Result<Chunk> {
+    if !data.len().is_multiple_of(2) {
+        return Err(invalid_data("chunk data has odd length".to_string()));
+    }
+    let mut ids = Vec::with_capacity(CHUNK_SIZE);
+    for pair in data.chunks(2) {
+        ids.extend(std::iter::repeat_n(pair[1], pair[0] as usize));
+    }
+    if ids.len() != CHUNK_SIZE {
+        return Err(invalid_data(format!(
+            "chunk data contains {} blocks instead of {}",
+            ids.len(),
+            CHUNK_SIZE
+        )));
+    }
+    let mut chunk = Chunk::new();
+    for (i, id) in chunk.blocks.iter_mut().flatten().flatten().enumerate() {
+        *id = ids[i];
+    }
+    Ok(chunk)
+}
+
+impl WorldSave {
+    pub fn new(dir: impl Into<PathBuf>) -> WorldSave {
+        WorldSave { dir: dir.into() }
+    }
+
+    pub fn dir(&self) -> &Path {
+        &self.dir
+    }
+
+    /// Returns whether a world has been saved in this directory
+    pub fn exists(&self) -> bool {
+        self.dir.join("world.dat").is_file()
+    }
+
+    fn chunk_path(&self, chunk_x: i32, chunk_z: i32) -> PathBuf {
+        self.dir
+            .join("chunks")
+            .join(format!("{}.{}.chunk", chunk_x, chunk_z))
+    }
+
+    /// Reads the `key value` pairs of `world.dat`
+    pub fn read_meta(&self) -> io::Result<Vec<(String, String)>> {
+        let text = fs::read_to_string(self.dir.join("world.dat"))?;
+        Ok(text
+            .lines()
+            .filter_map(|line| line.split_once(' '))
+            .map(|(key, value)| (key.to_string(), value.trim().to_string()))
+            .collect())
+    }
+
+    fn meta_value(meta: &[(String, String)], key: &str) -> Option<String> {
+        meta.iter()
+            .find(|(k, _)| k == key)
+            .map(|(_, value)| value.clone())
+    }
+
+    /// Writes the world settings and all loaded chunks
+    pub fn save(&self, world: &World) -> io::Result<()> {
+        fs::create_dir_all(self.dir.join("chunks"))?;
+        let world_gen = world.world_gen();
+        let meta = format!(
+            "seed {}\ngenerator {}\ntime {}\n",
+            world_gen.seed(),
+            world_gen.generator().name(),
+            world.time()
+        );
+        write_atomic(&self.dir.join("world.dat"), meta)?;
+        for ((chunk_x, chunk_z), chunk) in &world.chunks {
+            self.save_chunk(*chunk_x, *chunk_z, chunk)?;
+        }
+        Ok(())
+    }
+
+    pub fn save_chunk(&self, chunk_x: i32, chunk_z: i32, chunk: &Chunk) -> io::Result<()> {
+        write_atomic(&self.chunk_path(chunk_x, chunk_z), encode_chunk(chunk))
+    }
+
+    /// Loads the chunk at **chunk position** (x, z), if it has been saved
+    pub fn load_chunk(&self, chunk_x: i32, chunk_z: i32) -> io::Result<Option<Chunk>> {
+        match fs::read(self.chunk_path(chunk_x, chunk_z)) {
+            Ok(data) => decode_chunk(&data).map(Some),
+            Err(e) if e.kind() == io::ErrorKind::NotFound => Ok(None),
+            Err(e) => Err(e),
+        }
+    }
+
+    /// Returns the positions of all saved chunks
+    pub fn chunk_positions(&self) -> io::Result<Vec<(i32, i32)>> {
+        let mut positions = vec![];
+        let entries = match fs::read_dir(self.dir.join("chunks")) {
+            Ok(entries) => entries,
+            Err(e) if e.kind() == io::ErrorKind::NotFound => return Ok(positions),
+            Err(e) => return Err(e),
+        };
+        for entry in entries {
+            let name = entry?.file_name();
+            let name = name.to_string_lossy();
+            let Some(position) = name.strip_suffix(".chunk") else {
+                continue;
+            };
+            if let Some((x, z)) = position.split_once('.') {
+                if let (Ok(x), Ok(z)) = (x.parse(), z.parse()) {
+                    positions.push((x, z));
+                }
+            }
+        }
+        Ok(positions)
+    }
+
+    /// Loads the world settings without any chunks
+    pub fn load_empty(&self) -> io::Result<World> {
+        let meta = self.read_meta()?;
+        let seed = match WorldSave::meta_value(&meta, "seed") {
+            Some(seed) => seed
+                .parse()
+                .map_err(|_| invalid_data(format!("invalid seed '{}'", seed)))?,
+            None => return Err(invalid_data("world.dat has no seed".to_string())),
+        };
+        let generator = match WorldSave::meta_value(&meta, "generator") {
+            Some(name) => Generator::from_name(&name)
+                .ok_or_else(|| invalid_data(format!("unknown generator '{}'", name)))?,
+            None => Generator::Terrain,
+        };
+        let mut world = World::with_world_gen(WorldGen::with_generator(seed, generator));
+        if let Some(time) = WorldSave::meta_value(&meta, "time") {
+            let time = time
+                .parse()
+                .map_err(|_| invalid_data(format!("invalid time '{}'", time)))?;
+            world.set_time(time);
+        }
+        Ok(world)
+    }
+
+    /// Loads the world settings and all saved chunks
+    pub fn load(&self) -> io::Result<World> {
+        let mut world = self.load_empty()?;
+        for (chunk_x, chunk_z) in self.chunk_positions()? {
+            if let Some(chunk) = self.load_chunk(chunk_x, chunk_z)? {
+                world.chunks.insert((chunk_x, chunk_z), chunk);
+            }
+        }
+        Ok(world)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::save::{decode_chunk, encode_chunk, WorldSave};
+    use crate::world::World;
+    use crate::world_gen::WorldGen;
+
+    #[test]
+    fn chunk_round_trip() {
+        let chunk = WorldGen::new(1).generate_chunk(0, 0);
+        let data = encode_chunk(&chunk);
+        assert!(data.len() < 16 * 256 * 16);
+        assert!(decode_chunk(&data).unwrap().blocks == chunk.blocks);
+        assert!(decode_chunk(&data[2..]).is_err());
+    }
+
+    #[test]
+    fn world_round_trip() {
+        let dir = std::env::temp_dir().join(format!("raft-save-test-{}", std::process::id()));
+        let mut world = World::with_seed(77);
+        world.generate_chunk(-1, 2);
+        world.set_time(30000);
+        let save = WorldSave::new(&dir);
+        save.save(&world).unwrap();
+        assert!(save.exists());
+
+        let loaded = save.load().unwrap();
+        assert_eq!(loaded.world_gen().seed(), 77);
+        assert_eq!(loaded.time(), 30000);
+        assert_eq!(loaded.chunks.len(), 1);
+        assert!(loaded.chunks[&(-1, 2)].blocks == world.chunks[&(-1, 2)].blocks);
+        // saving again replaces the files without leaving temporary ones behind
+        save.save(&world).unwrap();
+        let names: Vec<_> = std::fs::read_dir(dir.join("chunks"))
+            .unwrap()
+            .map(|entry| entry.unwrap().file_name())
+            .collect();
+        assert_eq!(names, ["-1.2.chunk"]);
+        std::fs::remove_dir_all(&dir).unwrap();
+    }
+}
@@ -0,0 +1,453 @@
+use crate::fog::FogMode;
+use crate::gamepad::GamepadSettings;
+use crate::input::{Action, Bindings, Input};
+use crate::perspective::FovAxis;
+use crate::save::write_atomic;
+use crate::shadow::MAX_CASCADES;
+use std::fs;
+use std::io;
+use std::ops::RangeInclusive;
+use std::path::Path;
+use toml_edit::{value, Array, DocumentMut, Item, TableLike};
+
+pub const FOV_RANGE: RangeInclusive<f32> = 30.0..=110.0;
+pub const ZFAR_RANGE: RangeInclusive<f32> = 64.0..=4096.0;
+pub const FOG_START_RANGE: RangeInclusive<f32> = 0.0..=0.95;
+pub const SHADOW_RESOLUTION_RANGE: RangeInclusive<f32> = 256.0..=8192.0;
+pub const SENSITIVITY_RANGE: RangeInclusive<f32> = 0.0001..=0.005;
+pub const REACH_RANGE: RangeInclusive<f32> = 1.0..=8.0;
+pub const DEADZONE_RANGE: RangeInclusive<f32> = 0.0..=0.9;
+pub const RESPONSE_CURVE_RANGE: RangeInclusive<f32> = 0.5..=4.0;
+pub const LOOK_SPEED_RANGE: RangeInclusive<f32> = 0.5..=10.0;
+pub const TRIGGER_THRESHOLD_RANGE: RangeInclusive<f32> = 0.05..=1.0;
+
+/// Options of the game, saved in `settings.toml` in the working directory:
+///
+/// ```toml
+/// [graphics]
+/// fullscreen = false
+/// fov = 60.0
+/// fov_axis = "vertical" # or "horizontal"
+/// zfar = 1024.0
+/// fog = "linear" # or "off", "exponential" or "height"
+/// fog_start = 0.6
+/// scattering = true
+/// shadows = true
+/// shadow_splits = [16.0, 48.0, 128.0]
+/// shadow_resolution = 2048
+///
+/// [controls]
+/// mouse_sensitivity = 0.001
+/// forward = ["W", "GamepadLeftStickUp"]
+/// jump = ["Space", "GamepadSouth"]
+/// attack = ["MouseLeft", "GamepadRightTrigger"]
+/// # and the other actions of [`Action`]
+///
+/// [gamepad]
+/// deadzone = 0.15
+/// response_curve = 2.0
+/// look_speed = 3.0
+/// trigger_threshold = 0.5
+///
+/// [gameplay]
+/// reach = 6.0
+/// ```
+///
+/// Every action takes the name of an input or a list of them. Missing or invalid values fall back
+/// to their defaults. Unknown keys are ignored and kept when the settings are written back, so
+/// newer versions can add settings without older ones losing them.
+#[derive(Debug, Clone, PartialEq)]
+pub struct Settings {
+    pub fullscreen: bool,
+    /// Field of view in degrees, measured along `fov_axis`
+    pub fov: f32,
+    pub fov_axis: FovAxis,
+    /// Distance of the far clipping plane in blocks, which is also how far the fog reaches
+    pub zfar: f32,
+    pub fog: FogMode,
+    /// Fraction of `zfar` at which linear fog starts
+    pub fog_start: f32,
+    /// Whether sunlight scatters in the sky and fog, brightening them towards the sun
+    pub scattering: bool,
+    pub shadows: bool,
+    /// Distances from the camera at which each shadow cascade ends, see [`crate::shadow`]
+    pub shadow_splits: Vec<f32>,
+    /// Width and height of the shadow map of each cascade in texels
+    pub shadow_resolution: u32,
+    /// Radians the view turns per pixel of mouse movement
+    pub mouse_sensitivity: f32,
+    pub bindings: Bindings,
+    pub gamepad: GamepadSettings,
+    /// Distance from the camera at which blocks can be broken and placed
+    pub reach: f32,
+}
+
+impl Default for Settings {
+    fn default() -> Settings {
+        Settings {
+            fullscreen: false,
+            fov: 60.0,
+            fov_axis: FovAxis::Vertical,
+            zfar: 1024.0,
+            fog: FogMode::Linear,
+            fog_start: 0.6,
+            scattering: true,
+            shadows: true,
+            shadow_splits: vec![16.0, 48.0, 128.0],
+            shadow_resolution: 2048,
+            mouse_sensitivity: 0.001,
+            bindings: Bindings::default(),
+            gamepad: GamepadSettings::default(),
+            reach: 6.0,
+        }
+    }
+}
+
+/// Reads a number, which may be written as an integer, into `setting` if it is in `range`
+fn read_number(
+    table: &dyn TableLike,
+    key: &str,
+    setting: &mut f32,
+    range: RangeInclusive<f32>,
+    warnings: &mut Vec<String>,
+) {
+    let Some(item) = table.get(key) else {
+        return;
+    };
+    let number = item
+        .as_float()
+        .or_else(|| item.as_integer().map(|n| n as f64));
+    match number.map(|n| n as f32) {
+        Some(n) if range.contains(&n) => *setting = n,
+        Some(n) => warnings.push(format!(
+            "{} = {} is not between {} and {}, using {}",
+            key,
+            n,
+            range.start(),
+            range.end(),
+            setting
+        )),
+        None => warnings.push(format!("{} must be a number, using {}", key, setting)),
+    }
+}
+
+/// Reads the distances at which shadow cascades end, which have to be increasing, at most
+/// [`MAX_CASCADES`] of them and within [`ZFAR_RANGE`]
+fn read_splits(item: &Item) -> Option<Vec<f32>> {
+    let splits: Vec<f32> = item
+        .as_array()?
+        .iter()
+        .map(|split| {
+            let split = split
+                .as_float()
+                .or_else(|| split.as_integer().map(|n| n as f64));
+            split.map(|n| n as f32)
+        })
+        .collect::<Option<_>>()?;
+    let increasing = splits.windows(2).all(|pair| pair[0] < pair[1]);
+    let in_range = splits
+        .iter()
+        .all(|split| *split >= 1.0 && split <= ZFAR_RANGE.end());
+    (!splits.is_empty() && splits.len() <= MAX_CASCADES && increasing && in_range).then_some(splits)
+}
+
+/// Reads the name of an input or a list of them. Returns `None` if one of them is unknown.
+fn read_inputs(item: &Item) -> Option<Vec<Input>> {
+    if let Some(name) = item.as_str() {
+        return Some(vec![Input::parse(name)?]);
+    }
+    item.as_array()?
+        .iter()
+        .map(|name| Input::parse(name.as_str()?))
+        .collect()
+}
+
+/// Returns a TOML number that is written as short as the `f32` allows, like `0.001` rather than
+/// the `0.0010000000474974513` of converting it to `f64`
+fn number(n: f32) -> Item {
+    value(n.to_string().parse::<f64>().unwrap_or(n as f64))
+}
+
+/// Returns the table `name` of the document, replacing whatever else is there
+fn section<'a>(document: &'a mut DocumentMut, name: &str) -> &'a mut dyn TableLike {
+    if !document.get(name).is_some_and(Item::is_table_like) {
+        document[name] = toml_edit::table();
+    }
+    document[name].as_table_like_mut().unwrap()
+}
+
+impl Settings {
+    /// Parses a settings file. Returns an error if it isn't valid TOML, and the settings together
+    /// with a warning for every value that was replaced by its default otherwise.
+    pub fn from_toml(text: &str) -> Result<(Settings, Vec<String>), String> {
+        let document: DocumentMut = text.parse().map_err(|e| format!("{}", e))?;
+        let mut settings = Settings::default();
+        let mut warnings = vec![];
+        let empty = toml_edit::Table::new();
+        let table = |name| {
+            document
+                .get(name)
+                .and_then(Item::as_table_like)
+                .unwrap_or(&empty)
+        };
+
+        let graphics = table("graphics");
+        if let Some(item) = graphics.get("fullscreen") {
+            match item.as_bool() {
+                Some(fullscreen) => settings.fullscreen = fullscreen,
+                None => warnings.push("fullscreen must be true or false, using false".to_string()),
+            }
+        }
+        read_number(graphics, "fov", &mut settings.fov, FOV_RANGE, &mut warnings);
+        if let Some(item) = graphics.get("fov_axis") {
+            match item.as_str().and_then(FovAxis::from_name) {
+                Some(axis) => settings.fov_axis = axis,
+                None => warnings.push(format!(
+                    "fov_axis must be \"vertical\" or \"horizontal\", using \"{}\"",
+                    settings.fov_axis.name()
+                )),
+            }
+        }
+        read_number(
+            graphics,
+            "zfar",
+            &mut settings.zfar,
+            ZFAR_RANGE,
+            &mut warnings,
+        );
+        if let Some(item) = graphics.get("fog") {
+            match item.as_str().and_then(FogMode::from_name) {
+                Some(fog) => settings.fog = fog,
+                None => warnings.push(format!(
+                    "fog must be \"off\", \"linear\", \"exponential\" or \"height\", using \"{}\"",
+                    settings.fog.name()
+                )),
+            }
+        }
+        read_number(
+            graphics,
+            "fog_start",
+            &mut settings.fog_start,
+            FOG_START_RANGE,
+            &mut warnings,
+        );
+        if let Some(item) = graphics.get("scattering") {
+            match item.as_bool() {
+                Some(scattering) => settings.scattering = scattering,
+                None => warnings.push("scattering must be true or false, using true".to_string()),
+            }
+        }
+        if let Some(item) = graphics.get("shadows") {
+            match item.as_bool() {
+                Some(shadows) => settings.shadows = shadows,
+                None => warnings.push("shadows must be true or false, using true".to_string()),
+            }
+        }
+        if let Some(item) = graphics.get("shadow_splits") {
+            match read_splits(item) {
+                Some(splits) => settings.shadow_splits = splits,
+                None => warnings.push(format!(
+                    "shadow_splits must be a list of up to {} increasing distances, using {:?}",
+                    MAX_CASCADES, settings.shadow_splits
+                )),
+            }
+        }
+        let mut resolution = settings.shadow_resolution as f32;
+        read_number(
+            graphics,
+            "shadow_resolution",
+            &mut resolution,
+            SHADOW_RESOLUTION_RANGE,
+            &mut warnings,
+        );
+        settings.shadow_resolution = resolution as u32;
+
+        let controls = table("controls");
+        read_number(
+            controls,
+            "mouse_sensitivity",
+            &mut settings.mouse_sensitivity,
+            SENSITIVITY_RANGE,
+            &mut warnings,
+        );
+        for action in Action::ALL {
+            if let Some(item) = controls.get(action.name()) {
+                match read_inputs(item) {
+                    Some(inputs) => settings.bindings.bind(action, inputs),
+                    None => warnings.push(format!(
+                        "{} = {} is not an input or a list of inputs, keeping the default",
+                        action.name(),
+                        item.to_string().trim()
+                    )),
+                }
+            }
+        }
+
+        let gamepad = table("gamepad");
+        let numbers = [
+            ("deadzone", &mut settings.gamepad.deadzone, DEADZONE_RANGE),
+            (
+                "response_curve",
+                &mut settings.gamepad.response_curve,
+                RESPONSE_CURVE_RANGE,
+            ),
+            (
+                "look_speed",
+                &mut settings.gamepad.look_speed,
+                LOOK_SPEED_RANGE,
+            ),
+            (
+                "trigger_threshold",
+                &mut settings.gamepad.trigger_threshold,
+                TRIGGER_THRESHOLD_RANGE,
+            ),
+        ];
+        for (key, setting, range) in numbers {
+            read_number(gamepad, key, setting, range, &mut warnings);
+        }
+
+        let gameplay = table("gameplay");
+        read_number(
+            gameplay,
+            "reach",
+            &mut settings.reach,
+            REACH_RANGE,
+            &mut warnings,
+        );
+        Ok((settings, warnings))
+    }
+
+    /// Writes the settings into `previous`, the text of the settings file they replace, keeping
+    /// its unknown keys and comments. Starts over if `previous` isn't valid TOML.
+    pub fn to_toml(&self, previous: &str) -> String {
+        let mut document: DocumentMut = previous.parse().unwrap_or_default();
+
+        let graphics = section(&mut document, "graphics");
+        graphics.insert("fullscreen", value(self.fullscreen));
+        graphics.insert("fov", number(self.fov));
+        graphics.insert("fov_axis", value(self.fov_axis.name()));
+        graphics.insert("zfar", number(self.zfar));
+        graphics.insert("fog", value(self.fog.name()));
+        graphics.insert("fog_start", number(self.fog_start));
+        graphics.insert("scattering", value(self.scattering));
+        graphics.insert("shadows", value(self.shadows));
+        let splits: Array = self
+            .shadow_splits
+            .iter()
+            .map(|split| *split as f64)
+            .collect();
+        graphics.insert("shadow_splits", value(splits));
+        graphics.insert("shadow_resolution", value(self.shadow_resolution as i64));
+
+        let controls = section(&mut document, "controls");
+        controls.insert("mouse_sensitivity", number(self.mouse_sensitivity));
+        for action in Action::ALL {
+            let names: Vec<_> = self
+                .bindings
+                .inputs(action)
+                .iter()
+                .filter_map(|input| input.name())
+                .collect();
+            let item = match names[..] {
+                [name] => value(name),
+                _ => value(names.into_iter().collect::<Array>()),
+            };
+            controls.insert(action.name(), item);
+        }
+
+        let gamepad = section(&mut document, "gamepad");
+        gamepad.insert("deadzone", number(self.gamepad.deadzone));
+        gamepad.insert("response_curve", number(self.gamepad.response_curve));
+        gamepad.insert("look_speed", number(self.gamepad.look_speed));
+        gamepad.insert("trigger_threshold", number(self.gamepad.trigger_threshold));
+
+        let gameplay = section(&mut document, "gameplay");
+        gameplay.insert("reach", number(self.reach));
+        document.to_string()
+    }
+
+    /// Loads the settings file at `path`, or the defaults if there is none yet
+    pub fn load(path: &Path) -> io::Result<(Settings, Vec<String>)> {
+        match fs::read_to_string(path) {
+            Ok(text) => Settings::from_toml(&text)
+                .map_err(|e| io::Error::new(io::ErrorKind::InvalidData, e)),
+            Err(e) if e.kind() == io::ErrorKind::NotFound => Ok((Settings::default(), vec![])),
+            Err(e) => Err(e),
+        }
+    }
+
+    pub fn save(&self, path: &Path) -> io::Result<()> {
+        let previous = match fs::read_to_string(path) {
+            Ok(text) => text,
+            Err(e) if e.kind() == io::ErrorKind::NotFound => String::new(),
+            Err(e) => return Err(e),
+        };
+        write_atomic(path, self.to_toml(&previous))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::fog::FogMode;
+    use crate::input::{Action, Bindings, GamepadButton, Input};
+    use crate::perspective::FovAxis;
+    use crate::settings::Settings;
+    use glium::glutin::event::VirtualKeyCode;
+
+    #[test]
+    fn defaults_and_validation() {
+        let (settings, warnings) = Settings::from_toml("").unwrap();
+        assert_eq!(settings, Settings::default());
+        assert!(warnings.is_empty());
+
+        let text = "[graphics]\nfov = 90\nzfar = 1.0\nfullscreen = \"yes\"\nfog = \"height\"\n\
+                    fov_axis = \"horizontal\"\nshadow_splits = [10, 5]\n\
+                    [controls]\nforward = \"Up\"\njump = \"Hyperspace\"\n";
+        let (settings, warnings) = Settings::from_toml(text).unwrap();
+        assert_eq!(settings.fov, 90.0);
+        assert_eq!(settings.zfar, Settings::default().zfar);
+        assert!(!settings.fullscreen);
+        assert_eq!(settings.fog, FogMode::Height);
+        assert_eq!(settings.fov_axis, FovAxis::Horizontal);
+        let bindings = &settings.bindings;
+        assert_eq!(
+            bindings.inputs(Action::MoveForward),
+            [Input::Key(VirtualKeyCode::Up)]
+        );
+        assert_eq!(
+            bindings.inputs(Action::Jump),
+            Bindings::default().inputs(Action::Jump)
+        );
+        assert_eq!(settings.shadow_splits, Settings::default().shadow_splits);
+        assert_eq!(warnings.len(), 4);
+
+        assert!(Settings::from_toml("[graphics\n").is_err());
+    }
+
+    #[test]
+    fn keeps_unknown_keys() {
+        let previous =
+            "# my settings\n[graphics]\nfov = 70.0\nbloom = \"high\"\n\n[audio]\nvolume = 0.5\n";
+        let mut settings = Settings::from_toml(previous).unwrap().0;
+        settings.fov = 80.0;
+        settings.fov_axis = FovAxis::Horizontal;
+        settings.fog = FogMode::Exponential;
+        settings.scattering = false;
+        settings.shadow_splits = vec![8.0, 32.5];
+        settings.bindings.bind(
+            Action::Sneak,
+            vec![
+                Input::Key(VirtualKeyCode::LControl),
+                Input::Gamepad(GamepadButton::RightStick),
+            ],
+        );
+        let text = settings.to_toml(previous);
+        assert!(text.starts_with("# my settings\n"));
+        assert!(text.contains("bloom = \"high\""));
+        assert!(text.contains("[audio]\nvolume = 0.5"));
+
+        let (parsed, warnings) = Settings::from_toml(&text).unwrap();
+        assert_eq!(parsed, settings);
+        assert!(warnings.is_empty());
+    }
+}
@@ -0,0 +1,77 @@
+use crate::math::{bounding_sphere, frustum_corners, light_matrix, Mat4, Vec3};
+
+/// Most shadow cascades, which is the size of the arrays in the chunk shader
+pub const MAX_CASCADES: usize = 4;
+/// Distance from the camera at which the first cascade starts, the near clipping plane
+const NEAR: f32 = 0.1;
+/// Blocks towards the light beyond each cascade in which blocks still cast shadows into it
+const CASTER_DEPTH: f32 = 256.0;
+
+/// One of the shadow maps covering the view. Nearer cascades cover less of the world, so their
+/// shadows are sharper.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct Cascade {
+    /// Distance along the view at which the cascade ends and the next one starts
+    pub end: f32,
+    /// Projects the world onto the cascade's shadow map
+    pub matrix: Mat4,
+    /// Size of one texel of the shadow map in blocks
+    pub texel: f32,
+}
+
+/// Returns the cascades for the view from `position` along `direction`, with the vertical field
+/// of view `fov` in radians and the width divided by the height `aspect`. Each cascade ends at
+/// the next distance in `splits` and has a shadow map of `resolution` by `resolution` texels
+/// for light coming from `light`, the direction towards the light.
+pub fn cascades(
+    splits: &[f32],
+    position: Vec3,
+    direction: Vec3,
+    (fov, aspect): (f32, f32),
+    light: Vec3,
+    resolution: u32,
+) -> Vec<Cascade> {
+    let mut start = NEAR;
+    let mut cascades = vec![];
+    for end in splits.iter().copied().take(MAX_CASCADES) {
+        let corners = frustum_corners(position, direction, fov, aspect, start, end);
+        let (_, radius) = bounding_sphere(&corners);
+        cascades.push(Cascade {
+            end,
+            matrix: light_matrix(light, &corners, resolution, CASTER_DEPTH),
+            texel: 2.0 * radius / resolution as f32,
+        });
+        start = end;
+    }
+    cascades
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::math::{Mat4, Vec3};
+    use crate::shadow::{cascades, MAX_CASCADES};
+
+    #[test]
+    fn cascades_split_the_view() {
+        let position = Vec3(0.0, 80.0, 0.0);
+        let direction = Vec3(0.0, 0.0, 1.0);
+        let light = Vec3(0.5, 0.8, 0.1).normalize();
+        let splits = [16.0, 48.0, 128.0, 256.0, 512.0];
+        let cascades = cascades(&splits, position, direction, (1.0, 1.5), light, 2048);
+        assert_eq!(cascades.len(), MAX_CASCADES);
+        assert_eq!(cascades[1].end, 48.0);
+        assert!(cascades
+            .windows(2)
+            .all(|pair| pair[0].texel < pair[1].texel));
+
+        // a point straight ahead is in the cascade its distance falls in
+        let ahead = |distance: f32| position + direction.scale(distance);
+        let inside = |matrix: Mat4, point| {
+            let Vec3(x, y, z) = matrix.transform_point(point);
+            x.abs() <= 1.0 && y.abs() <= 1.0 && z.abs() <= 1.0
+        };
+        assert!(inside(cascades[0].matrix, ahead(10.0)));
+        assert!(inside(cascades[2].matrix, ahead(100.0)));
+        assert!(!inside(cascades[0].matrix, ahead(100.0)));
+    }
+}
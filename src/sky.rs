@@ -0,0 +1,211 @@
+use crate::math::Vec3;
+use crate::vertex::Vertex;
+use std::f32::consts::PI;
+
+/// Ticks in a full day. Time 0 is sunrise.
+pub const DAY_LENGTH: u64 = 24000;
+/// Times of day that can be set by name, see [`adjust_time`]
+pub const NAMED_TIMES: [(&str, u64); 6] = [
+    ("sunrise", 0),
+    ("day", 1000),
+    ("noon", 6000),
+    ("sunset", 12000),
+    ("night", 13000),
+    ("midnight", 18000),
+];
+/// Angle between the sun's path and the vertical, so the sun isn't straight overhead at noon
+const SUN_TILT: f32 = 0.35;
+
+const DAY_ZENITH: [f32; 3] = [0.25, 0.5, 0.95];
+const DAY_HORIZON: [f32; 3] = [0.7, 0.83, 1.0];
+const NIGHT_ZENITH: [f32; 3] = [0.005, 0.008, 0.03];
+const NIGHT_HORIZON: [f32; 3] = [0.03, 0.04, 0.09];
+const SUNSET: [f32; 3] = [1.0, 0.45, 0.15];
+/// Colour of the sunlight scattered around the sun in the day
+const SUN_GLOW: [f32; 3] = [1.0, 0.95, 0.85];
+/// Skylight at midnight, so the world stays faintly visible under the moon
+const NIGHT_LIGHT: f32 = 0.15;
+
+fn mix(a: [f32; 3], b: [f32; 3], t: f32) -> [f32; 3] {
+    [0, 1, 2].map(|i| a[i] + (b[i] - a[i]) * t)
+}
+
+fn smoothstep(from: f32, to: f32, x: f32) -> f32 {
+    let t = ((x - from) / (to - from)).clamp(0.0, 1.0);
+    t * t * (3.0 - 2.0 * t)
+}
+
+/// State of the sky at a moment of the day
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct Sky {
+    /// Angle of the sun above the eastern horizon in radians, going round once a day
+    pub angle: f32,
+    /// Direction towards the sun
+    pub sun: Vec3,
+    /// Direction towards the moon, which is always opposite the sun
+    pub moon: Vec3,
+    /// Colour of the sky straight up
+    pub zenith: [f32; 3],
+    /// Colour of the sky at the horizon, also used for the background behind everything
+    pub horizon: [f32; 3],
+    /// Brightness of the light blocks get from the sky, from [`NIGHT_LIGHT`] to 1
+    pub skylight: f32,
+    /// How visible the stars are, from 0 to 1
+    pub stars: f32,
+}
+
+impl Sky {
+    /// Returns the sky at world time `time`, plus `partial` of a tick for smooth movement
+    /// between ticks
+    pub fn at(time: u64, partial: f32) -> Sky {
+        let day = ((time % DAY_LENGTH) as f32 + partial) / DAY_LENGTH as f32;
+        let angle = day * 2.0 * PI;
+        // rises in the east (+x), sets in the west and passes a little to the south (+z)
+        let sun = Vec3(
+            angle.cos(),
+            angle.sin() * SUN_TILT.cos(),
+            angle.sin() * SUN_TILT.sin(),
+        );
+        let daylight = smoothstep(-0.2, 0.2, sun.1);
+        // sunrise and sunset colour the horizon while the sun is near it
+        let glow = (-(sun.1 / 0.15).powi(2)).exp() * 0.6;
+        let horizon = mix(NIGHT_HORIZON, DAY_HORIZON, daylight);
+        Sky {
+            angle,
+            sun,
+            moon: sun.scale(-1.0),
+            zenith: mix(NIGHT_ZENITH, DAY_ZENITH, daylight),
+            horizon: mix(horizon, SUNSET, glow),
+            skylight: NIGHT_LIGHT + (1.0 - NIGHT_LIGHT) * daylight,
+            stars: 1.0 - smoothstep(-0.3, 0.05, sun.1),
+        }
+    }
+
+    /// Returns the axis the sun, moon and stars turn around
+    pub fn axis(&self) -> Vec3 {
+        Vec3(0.0, -SUN_TILT.sin(), SUN_TILT.cos())
+    }
+
+    /// Returns the colour of the sunlight scattered in the air around the sun, which is orange
+    /// while the sun is low and fades out after sunset
+    pub fn scattering(&self) -> [f32; 3] {
+        let height = self.sun.1;
+        mix(SUNSET, SUN_GLOW, smoothstep(0.0, 0.4, height))
+            .map(|c| c * smoothstep(-0.15, 0.05, height))
+    }
+
+    /// Returns the direction towards the light that lights the world, the sun by day and the
+    /// moon by night
+    pub fn light(&self) -> Vec3 {
+        if self.sun.1 >= 0.0 {
+            self.sun
+        } else {
+            self.moon
+        }
+    }
+}
+
+/// Returns the day and the hour and minute on a 24 hour clock of world time `time`, taking
+/// sunrise to be at 6:00. Days start at midnight, so the first day starts 6 hours in.
+pub fn clock(time: u64) -> (u64, u64, u64) {
+    let time = time + DAY_LENGTH / 4;
+    // 1000 ticks per hour
+    let minutes = (time % DAY_LENGTH) * 60 / 1000;
+    (time / DAY_LENGTH + 1, minutes / 60, minutes % 60)
+}
+
+/// Returns the world time after changing `time` by `change`, which is a number of ticks, the
+/// name of a time of day in [`NAMED_TIMES`] for the next time it is that time of day, or `+` or
+/// `-` followed by a number of ticks to add or take away
+pub fn adjust_time(time: u64, change: &str) -> Option<u64> {
+    if let Some(ticks) = change.strip_prefix('+') {
+        return time.checked_add(ticks.parse().ok()?);
+    }
+    if let Some(ticks) = change.strip_prefix('-') {
+        return time.checked_sub(ticks.parse().ok()?);
+    }
+    if let Some((_, time_of_day)) = NAMED_TIMES.iter().find(|(name, _)| *name == change) {
+        let wait = (time_of_day + DAY_LENGTH - time % DAY_LENGTH) % DAY_LENGTH;
+        return Some(time + wait);
+    }
+    change.parse().ok()
+}
+
+/// Returns the triangles of a sphere of radius 1 around the origin, made of `rings` bands from
+/// pole to pole with `segments` quads each, on which the sky is drawn
+pub fn dome(rings: u32, segments: u32) -> Vec<Vertex> {
+    let point = |ring: u32, segment: u32| {
+        let pitch = PI * ring as f32 / rings as f32 - PI / 2.0;
+        let yaw = 2.0 * PI * segment as f32 / segments as f32;
+        let position = [
+            pitch.cos() * yaw.cos(),
+            pitch.sin(),
+            pitch.cos() * yaw.sin(),
+        ];
+        Vertex {
+            position,
+            normal: position.map(|c| -c),
+            tex_coords: [segment as f32 / segments as f32, ring as f32 / rings as f32],
+        }
+    };
+    let mut vertices = vec![];
+    for ring in 0..rings {
+        for segment in 0..segments {
+            let quad = [
+                point(ring, segment),
+                point(ring + 1, segment),
+                point(ring + 1, segment + 1),
+                point(ring, segment + 1),
+            ];
+            vertices.extend([quad[0], quad[1], quad[2], quad[0], quad[2], quad[3]]);
+        }
+    }
+    vertices
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::sky::{adjust_time, clock, Sky, DAY_LENGTH};
+
+    #[test]
+    fn sun_moves_through_the_day() {
+        let sunrise = Sky::at(0, 0.0);
+        assert!(sunrise.sun.0 > 0.99 && sunrise.sun.1.abs() < 1e-4);
+        let noon = Sky::at(6000, 0.0);
+        assert!(noon.sun.1 > 0.9);
+        assert_eq!(noon.light(), noon.sun);
+        assert_eq!(noon.skylight, 1.0);
+        assert_eq!(noon.stars, 0.0);
+        let midnight = Sky::at(DAY_LENGTH * 3 + 18000, 0.0);
+        assert!(midnight.moon.1 > 0.9);
+        assert_eq!(midnight.light(), midnight.moon);
+        assert!(midnight.skylight < 0.2);
+        assert_eq!(midnight.stars, 1.0);
+        assert_eq!(midnight.scattering(), [0.0; 3]);
+        assert!(noon.scattering()[2] > 0.8);
+        assert!(midnight.zenith[2] < noon.zenith[2]);
+        // the horizon turns orange at sunset
+        let sunset = Sky::at(12000, 0.0);
+        assert!(sunset.horizon[0] > sunset.horizon[2]);
+        // halfway between two ticks
+        assert!(Sky::at(6000, 0.5).angle > noon.angle);
+    }
+
+    #[test]
+    fn time_commands() {
+        assert_eq!(clock(0), (1, 6, 0));
+        assert_eq!(clock(DAY_LENGTH + 6500), (2, 12, 30));
+        assert_eq!(clock(17999), (1, 23, 59));
+        assert_eq!(clock(18000), (2, 0, 0));
+        assert_eq!(clock(23999), (2, 5, 59));
+        assert_eq!(adjust_time(100, "5000"), Some(5000));
+        assert_eq!(adjust_time(100, "+50"), Some(150));
+        assert_eq!(adjust_time(100, "-200"), None);
+        assert_eq!(adjust_time(7000, "noon"), Some(DAY_LENGTH + 6000));
+        assert_eq!(
+            adjust_time(DAY_LENGTH, "midnight"),
+            Some(DAY_LENGTH + 18000)
+        );
+        assert_eq!(adjust_time(100, "teatime"), None);
+    }
+}
@@ -0,0 +1,52 @@
+#version 150
+
+in vec3 v_direction;
+
+out vec4 color;
+
+uniform vec3 zenith;
+uniform vec3 horizon;
+uniform vec3 sun;
+uniform vec3 moon;
+// axis the sun, moon and stars turn around, and how far they have turned
+uniform vec3 sky_axis;
+uniform float sky_angle;
+uniform float stars;
+// colour of the sunlight scattered in the air, black without scattering
+uniform vec3 scattering;
+
+const vec3 sun_color = vec3(1.0, 0.95, 0.8);
+const vec3 moon_color = vec3(0.8, 0.82, 0.9);
+// number of cells across the star field, each of which may hold a star
+const float star_cells = 150.0;
+
+float hash(vec3 p) {
+    return fract(sin(dot(p, vec3(12.9898, 78.233, 45.164))) * 43758.5453);
+}
+
+// same as in fragment.glsl, so the fog matches the sky behind it
+vec3 scatter(vec3 direction) {
+    float to_sun = max(dot(direction, sun), 0.0);
+    return scattering * (pow(to_sun, 8.0) * 0.5 + to_sun * to_sun * 0.15);
+}
+
+vec3 rotate(vec3 v, vec3 axis, float angle) {
+    return v * cos(angle) + cross(axis, v) * sin(angle) + axis * dot(axis, v) * (1.0 - cos(angle));
+}
+
+void main() {
+    vec3 direction = normalize(v_direction);
+    vec3 sky = mix(horizon, zenith, sqrt(max(direction.y, 0.0))) + scatter(direction);
+
+    // the stars are fixed to the sky, so they turn with it
+    vec3 star_direction = rotate(direction, sky_axis, -sky_angle) * star_cells;
+    float star = step(0.997, hash(floor(star_direction)));
+    star *= smoothstep(0.4, 0.1, length(fract(star_direction) - 0.5));
+    sky += vec3(star * stars * smoothstep(-0.05, 0.1, direction.y));
+
+    float to_sun = dot(direction, sun);
+    sky += sun_color * (smoothstep(0.9990, 0.9994, to_sun) + pow(max(to_sun, 0.0), 64.0) * 0.3);
+    sky = mix(sky, moon_color, smoothstep(0.9995, 0.9997, dot(direction, moon)));
+
+    color = vec4(sky, 1.0);
+}
@@ -0,0 +1,15 @@
+#version 150
+in vec3 position;
+
+out vec3 v_direction;
+
+uniform mat4 perspective;
+uniform mat4 view;
+
+void main() {
+    v_direction = position;
+    // only the rotation of the view, so the dome stays around the camera
+    vec4 clip = perspective * mat4(mat3(view)) * vec4(position, 1.0);
+    // on the far plane, behind everything else
+    gl_Position = clip.xyww;
+}
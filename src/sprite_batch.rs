@@ -0,0 +1,139 @@
+use crate::vertex::Vertex2D;
+use glium::texture::RawImage2d;
+use glium::uniforms::MagnifySamplerFilter;
+use glium::VertexBuffer;
+use glium::{uniform, Blend, Display, DrawParameters, Frame, Program, Surface, Texture2d};
+
+/// Texture registered with a [`SpriteBatch`]
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct TextureId(usize);
+
+/// Whole texture, with (0, 0) at the bottom left
+pub const FULL_TEXTURE: ([f32; 2], [f32; 2]) = ([0.0, 0.0], [1.0, 1.0]);
+
+/// A rectangle of pixels from the bottom left of the screen, as (min, max)
+pub type Rect = ([f32; 2], [f32; 2]);
+
+/// Collects textured and coloured quads for the 2D overlay and draws them with as few draw calls
+/// as possible. Positions are in pixels from the bottom left of the screen. Quads are drawn in the
+/// order they were added, so later ones cover earlier ones.
+pub struct SpriteBatch {
+    program: Program,
+    textures: Vec<Texture2d>,
+    vertices: Vec<Vertex2D>,
+    /// Texture and vertex count of consecutive quads that use the same texture
+    runs: Vec<(TextureId, usize)>,
+    buffer: VertexBuffer<Vertex2D>,
+}
+
+impl SpriteBatch {
+    /// A plain white texture, for drawing solid colours
+    pub const WHITE: TextureId = TextureId(0);
+
+    pub fn new(display: &Display) -> SpriteBatch {
+        let program = Program::from_source(
+            display,
+            include_str!("sprite_vertex.glsl"),
+            include_str!("sprite_fragment.glsl"),
+            None,
+        )
+        .unwrap();
+        let white = RawImage2d::from_raw_rgba(vec![255u8; 4], (1, 1));
+        SpriteBatch {
+            program,
+            textures: vec![Texture2d::new(display, white).unwrap()],
+            vertices: vec![],
+            runs: vec![],
+            buffer: VertexBuffer::empty_dynamic(display, 6 * 256).unwrap(),
+        }
+    }
+
+    pub fn add_texture(&mut self, texture: Texture2d) -> TextureId {
+        self.textures.push(texture);
+        TextureId(self.textures.len() - 1)
+    }
+
+    /// Replaces a texture, for textures whose contents change while the game runs
+    pub fn set_texture(&mut self, id: TextureId, texture: Texture2d) {
+        self.textures[id.0] = texture;
+    }
+
+    /// Adds a quad with the given corners and texture coordinates, both in the order bottom left,
+    /// top left, bottom right, top right
+    pub fn quad(
+        &mut self,
+        texture: TextureId,
+        corners: [[f32; 2]; 4],
+        tex_coords: [[f32; 2]; 4],
+        colour: [f32; 4],
+    ) {
+        let vertex = |i: usize| Vertex2D {
+            position: corners[i],
+            tex_coords: tex_coords[i],
+            colour,
+        };
+        self.vertices.extend([0, 1, 2, 3, 2, 1].map(vertex));
+        match self.runs.last_mut() {
+            Some((last, count)) if *last == texture => *count += 6,
+            _ => self.runs.push((texture, 6)),
+        }
+    }
+
+    /// Adds an axis aligned rectangle showing the part `uv` of the texture
+    pub fn rect(&mut self, texture: TextureId, (min, max): Rect, uv: Rect, colour: [f32; 4]) {
+        let (uv_min, uv_max) = uv;
+        self.quad(
+            texture,
+            [min, [min[0], max[1]], [max[0], min[1]], max],
+            [
+                uv_min,
+                [uv_min[0], uv_max[1]],
+                [uv_max[0], uv_min[1]],
+                uv_max,
+            ],
+            colour,
+        );
+    }
+
+    /// Adds a rectangle of solid colour
+    pub fn fill(&mut self, rect: Rect, colour: [f32; 4]) {
+        self.rect(SpriteBatch::WHITE, rect, FULL_TEXTURE, colour);
+    }
+
+    /// Draws everything that has been added since the last draw, on top of what is on screen
+    pub fn draw(&mut self, display: &Display, target: &mut Frame) {
+        if self.vertices.len() > self.buffer.len() {
+            let capacity = self.vertices.len().next_power_of_two();
+            self.buffer = VertexBuffer::empty_dynamic(display, capacity).unwrap();
+        }
+        if !self.vertices.is_empty() {
+            self.buffer
+                .slice(0..self.vertices.len())
+                .unwrap()
+                .write(&self.vertices);
+        }
+        let (width, height) = target.get_dimensions();
+        let params = DrawParameters {
+            blend: Blend::alpha_blending(),
+            ..Default::default()
+        };
+        let mut first = 0;
+        for (texture, count) in self.runs.drain(..) {
+            let slice = self.buffer.slice(first..first + count).unwrap();
+            let tex = self.textures[texture.0]
+                .sampled()
+                .magnify_filter(MagnifySamplerFilter::Nearest);
+            target
+                .draw(
+                    slice,
+                    glium::index::NoIndices(glium::index::PrimitiveType::TrianglesList),
+                    &self.program,
+                    &uniform! { tex: tex, screen_size: (width as f32, height as f32) },
+                    &params,
+                )
+                .unwrap();
+            first += count;
+        }
+        self.vertices.clear();
+    }
+}
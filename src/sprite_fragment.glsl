@@ -0,0 +1,12 @@
+#version 150
+
+in vec2 v_tex_coords;
+in vec4 v_colour;
+
+out vec4 color;
+
+uniform sampler2D tex;
+
+void main() {
+    color = texture(tex, v_tex_coords) * v_colour;
+}
@@ -0,0 +1,16 @@
+#version 150
+
+in vec2 position;
+in vec2 tex_coords;
+in vec4 colour;
+
+out vec2 v_tex_coords;
+out vec4 v_colour;
+
+uniform vec2 screen_size;
+
+void main() {
+    gl_Position = vec4(position / screen_size * 2.0 - 1.0, 0.0, 1.0);
+    v_tex_coords = tex_coords;
+    v_colour = colour;
+}
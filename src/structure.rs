@@ -0,0 +1,573 @@
+use crate::object::{AIR, COBBLESTONE, LOG, PLANKS};
+use crate::rng::Rng;
+use crate::world_gen::{Generator, ProtoChunk, WorldGen};
+use std::collections::HashMap;
+
+/// The furthest a structure may reach from the chunk it starts in, in chunks
+pub const MAX_STRUCTURE_REACH: i32 = 3;
+
+const SALT_STRUCTURE: u64 = 64;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum StructureKind {
+    Village,
+    Dungeon,
+    Tower,
+}
+
+impl StructureKind {
+    pub const ALL: [StructureKind; 3] = [
+        StructureKind::Village,
+        StructureKind::Dungeon,
+        StructureKind::Tower,
+    ];
+
+    pub fn name(&self) -> &'static str {
+        match self {
+            StructureKind::Village => "village",
+            StructureKind::Dungeon => "dungeon",
+            StructureKind::Tower => "tower",
+        }
+    }
+
+    /// Size of the grid cells (regions) in chunks. Each region holds at most one start of this kind.
+    pub fn spacing(&self) -> i32 {
+        match self {
+            StructureKind::Village => 16,
+            StructureKind::Dungeon => 6,
+            StructureKind::Tower => 12,
+        }
+    }
+
+    /// Minimum distance in chunks between starts of neighbouring regions
+    fn separation(&self) -> i32 {
+        match self {
+            StructureKind::Village => 6,
+            StructureKind::Dungeon => 2,
+            StructureKind::Tower => 4,
+        }
+    }
+
+    /// Probability that a region contains a start at all
+    fn frequency(&self) -> f32 {
+        match self {
+            StructureKind::Village => 0.6,
+            StructureKind::Dungeon => 0.8,
+            StructureKind::Tower => 0.5,
+        }
+    }
+
+    fn salt(&self) -> u64 {
+        SALT_STRUCTURE + *self as u64
+    }
+}
+
+/// Axis aligned box in world coordinates, both corners inclusive
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct BoundingBox {
+    pub min: (i32, i32, i32),
+    pub max: (i32, i32, i32),
+}
+
+impl BoundingBox {
+    pub fn new(origin: (i32, i32, i32), size: (i32, i32, i32)) -> BoundingBox {
+        BoundingBox {
+            min: origin,
+            max: (
+                origin.0 + size.0 - 1,
+                origin.1 + size.1 - 1,
+                origin.2 + size.2 - 1,
+            ),
+        }
+    }
+
+    pub fn intersects(&self, other: &BoundingBox) -> bool {
+        self.min.0 <= other.max.0
+            && self.max.0 >= other.min.0
+            && self.min.1 <= other.max.1
+            && self.max.1 >= other.min.1
+            && self.min.2 <= other.max.2
+            && self.max.2 >= other.min.2
+    }
+
+    /// Returns whether any column of the chunk at **chunk position** (x, z) is inside this box
+    pub fn intersects_chunk(&self, chunk_x: i32, chunk_z: i32) -> bool {
+        self.min.0.div_euclid(16) <= chunk_x
+            && self.max.0.div_euclid(16) >= chunk_x
+            && self.min.2.div_euclid(16) <= chunk_z
+            && self.max.2.div_euclid(16) >= chunk_z
+    }
+
+    /// Returns a copy of this box grown by `amount` blocks horizontally
+    pub fn inflate(&self, amount: i32) -> BoundingBox {
+        BoundingBox {
+            min: (self.min.0 - amount, self.min.1, self.min.2 - amount),
+            max: (self.max.0 + amount, self.max.1, self.max.2 + amount),
+        }
+    }
+
+    /// Returns the smallest box containing both boxes
+    pub fn union(&self, other: &BoundingBox) -> BoundingBox {
+        BoundingBox {
+            min: (
+                self.min.0.min(other.min.0),
+                self.min.1.min(other.min.1),
+                self.min.2.min(other.min.2),
+            ),
+            max: (
+                self.max.0.max(other.max.0),
+                self.max.1.max(other.max.1),
+                self.max.2.max(other.max.2),
+            ),
+        }
+    }
+}
+
+/// A block layout that structures are assembled from
+pub struct Template {
+    pub name: &'static str,
+    /// Horizontal slices from bottom to top. Each string is a row along x, rows are stacked along z.
+    ///
+    /// `#` is cobblestone, `=` planks, `L` a log, `.` air and a space leaves the block untouched.
+    pub layers: &'static [&'static [&'static str]],
+}
+
+impl Template {
+    pub fn size(&self) -> (i32, i32, i32) {
+        let rows = self.layers[0];
+        (
+            rows[0].len() as i32,
+            self.layers.len() as i32,
+            rows.len() as i32,
+        )
+    }
+
+    fn block(c: char) -> Option<u8> {
+        match c {
+            '#' => Some(COBBLESTONE),
+            '=' => Some(PLANKS),
+            'L' => Some(LOG),
+            '.' => Some(AIR),
+            _ => None,
+        }
+    }
+}
+
+pub const WELL: Template = Template {
+    name: "well",
+    layers: &[
+        &["#####", "#####", "#####", "#####", "#####"],
+        &["#####", "#...#", "#...#", "#...#", "#####"],
+        &["=...=", ".....", ".....", ".....", "=...="],
+        &["=====", "=====", "=====", "=====", "====="],
+    ],
+};
+
+pub const HOUSE: Template = Template {
+    name: "house",
+    layers: &[
+        &[
+            "=======", "=======", "=======", "=======", "=======", "=======", "=======",
+        ],
+        &[
+            "L==.==L", "=.....=", "=.....=", "=.....=", "=.....=", "=.....=", "L=====L",
+        ],
+        &[
+            "L==.==L", "=.....=", "......=", "=.....=", "=......", "=.....=", "L=====L",
+        ],
+        &[
+            "L=====L", "=.....=", "=.....=", "=.....=", "=.....=", "=.....=", "L=====L",
+        ],
+        &[
+            "=======", "=======", "=======", "=======", "=======", "=======", "=======",
+        ],
+    ],
+};
+
+pub const TOWER_BASE: Template = Template {
+    name: "tower_base",
+    layers: &[
+        &["#####", "#####", "#####", "#####", "#####"],
+        &["#####", "#...#", "#...#", "#...#", "##.##"],
+        &["#####", "#...#", "#...#", "#...#", "##.##"],
+        &["#####", "#...#", "#...#", "#...#", "#####"],
+    ],
+};
+
+pub const TOWER_FLOOR: Template = Template {
+    name: "tower_floor",
+    layers: &[
+        &["#####", "#===#", "#===#", "#===#", "#####"],
+        &["#####", "#...#", "#...#", "#...#", "#####"],
+        &["##.##", "#...#", ".....", "#...#", "##.##"],
+        &["#####", "#...#", "#...#", "#...#", "#####"],
+    ],
+};
+
+pub const TOWER_TOP: Template = Template {
+    name: "tower_top",
+    layers: &[
+        &["#####", "#####", "#####", "#####", "#####"],
+        &["#.#.#", ".....", "#...#", ".....", "#.#.#"],
+    ],
+};
+
+pub const DUNGEON_ROOM: Template = Template {
+    name: "dungeon_room",
+    layers: &[
+        &[
+            "#######", "#######", "#######", "#######", "#######", "#######", "#######",
+        ],
+        &[
+            "###.###", "#.....#", "#.....#", ".......", "#.....#", "#.....#", "###.###",
+        ],
+        &[
+            "###.###", "#.....#", "#.....#", ".......", "#.....#", "#.....#", "###.###",
+        ],
+        &[
+            "#######", "#.....#", "#.....#", "#.....#", "#.....#", "#.....#", "#######",
+        ],
+        &[
+            "#######", "#######", "#######", "#######", "#######", "#######", "#######",
+        ],
+    ],
+};
+
+pub const CORRIDOR_X: Template = Template {
+    name: "corridor_x",
+    layers: &[
+        &["#####", "#####", "#####"],
+        &["#####", ".....", "#####"],
+        &["#####", ".....", "#####"],
+        &["#####", "#####", "#####"],
+    ],
+};
+
+pub const CORRIDOR_Z: Template = Template {
+    name: "corridor_z",
+    layers: &[
+        &["###", "###", "###", "###", "###"],
+        &["#.#", "#.#", "#.#", "#.#", "#.#"],
+        &["#.#", "#.#", "#.#", "#.#", "#.#"],
+        &["###", "###", "###", "###", "###"],
+    ],
+};
+
+/// A template placed at a position in the world
+#[derive(Clone)]
+pub struct StructurePiece {
+    pub template: &'static Template,
+    pub bounds: BoundingBox,
+}
+
+impl StructurePiece {
+    pub fn new(template: &'static Template, origin: (i32, i32, i32)) -> StructurePiece {
+        StructurePiece {
+            template,
+            bounds: BoundingBox::new(origin, template.size()),
+        }
+    }
+
+    /// Writes the part of this piece that lies inside `chunk`
+    pub fn place(&self, chunk: &mut ProtoChunk) {
+        let (ox, oy, oz) = self.bounds.min;
+        for (y, layer) in self.template.layers.iter().enumerate() {
+            for (z, row) in layer.iter().enumerate() {
+                for (x, c) in row.chars().enumerate() {
+                    if let Some(id) = Template::block(c) {
+                        chunk.set(ox + x as i32, oy + y as i32, oz + z as i32, id, |_| true);
+                    }
+                }
+            }
+        }
+    }
+}
+
+/// A complete structure, assembled from pieces that don't overlap
+#[derive(Clone)]
+pub struct StructureStart {
+    pub kind: StructureKind,
+    /// Grid cell this structure was started in
+    pub region: (i32, i32),
+    pub chunk_x: i32,
+    pub chunk_z: i32,
+    pub pieces: Vec<StructurePiece>,
+    pub bounds: BoundingBox,
+}
+
+impl StructureStart {
+    /// Returns the world position (x, y, z) of the first piece, which all others are arranged around
+    pub fn position(&self) -> (i32, i32, i32) {
+        self.pieces[0].bounds.min
+    }
+
+    fn new(kind: StructureKind, region: (i32, i32), chunk: (i32, i32)) -> StructureStart {
+        StructureStart {
+            kind,
+            region,
+            chunk_x: chunk.0,
+            chunk_z: chunk.1,
+            pieces: vec![],
+            bounds: BoundingBox::new((chunk.0 * 16, 0, chunk.1 * 16), (1, 1, 1)),
+        }
+    }
+
+    /// Adds `piece` unless it overlaps any of the existing pieces grown by `margin`.
+    /// Returns whether the piece was added.
+    fn try_add(&mut self, piece: StructurePiece, margin: i32) -> bool {
+        let inflated = piece.bounds.inflate(margin);
+        if self.pieces.iter().any(|p| p.bounds.intersects(&inflated)) {
+            return false;
+        }
+        let chunk_min = (
+            (self.chunk_x - MAX_STRUCTURE_REACH) * 16,
+            (self.chunk_z - MAX_STRUCTURE_REACH) * 16,
+        );
+        let chunk_max = (
+            (self.chunk_x + MAX_STRUCTURE_REACH + 1) * 16 - 1,
+            (self.chunk_z + MAX_STRUCTURE_REACH + 1) * 16 - 1,
+        );
+        if piece.bounds.min.0 < chunk_min.0
+            || piece.bounds.min.2 < chunk_min.1
+            || piece.bounds.max.0 > chunk_max.0
+            || piece.bounds.max.2 > chunk_max.1
+            || piece.bounds.min.1 < 1
+            || piece.bounds.max.1 > 255
+        {
+            return false;
+        }
+        self.bounds = if self.pieces.is_empty() {
+            piece.bounds
+        } else {
+            self.bounds.union(&piece.bounds)
+        };
+        self.pieces.push(piece);
+        true
+    }
+}
+
+impl WorldGen {
+    /// Returns the structure of the given kind started in grid cell (x, z), if there is one
+    pub fn structure_start(
+        &self,
+        kind: StructureKind,
+        region_x: i32,
+        region_z: i32,
+    ) -> Option<StructureStart> {
+        if self.generator() == Generator::Flat {
+            return None;
+        }
+        let mut rng = Rng::for_chunk(self.seed(), region_x, region_z, kind.salt());
+        if !rng.chance(kind.frequency()) {
+            return None;
+        }
+        let range = kind.spacing() - kind.separation();
+        let chunk = (
+            region_x * kind.spacing() + rng.range(0, range),
+            region_z * kind.spacing() + rng.range(0, range),
+        );
+        let mut start = StructureStart::new(kind, (region_x, region_z), chunk);
+        let (x, z) = (chunk.0 * 16 + 4, chunk.1 * 16 + 4);
+        match kind {
+            StructureKind::Village => self.assemble_village(&mut start, x, z, &mut rng),
+            StructureKind::Dungeon => {
+                let y = rng.range(12, 40);
+                assemble_dungeon(&mut start, (x, y, z), 2, &mut rng);
+            }
+            StructureKind::Tower => {
+                let y = self.terrain_height(x, z);
+                assemble_tower(&mut start, (x, y, z), &mut rng);
+            }
+        }
+        if start.pieces.is_empty() {
+            None
+        } else {
+            Some(start)
+        }
+    }
+
+    fn assemble_village(&self, start: &mut StructureStart, x: i32, z: i32, rng: &mut Rng) {
+        start.try_add(
+            StructurePiece::new(&WELL, (x, self.terrain_height(x + 2, z + 2), z)),
+            0,
+        );
+        for _ in 0..12 {
+            let hx = x + rng.range(-24, 24);
+            let hz = z + rng.range(-24, 24);
+            let y = self.terrain_height(hx + 3, hz + 3);
+            start.try_add(StructurePiece::new(&HOUSE, (hx, y, hz)), 2);
+        }
+    }
+
+    /// Returns all structures that have at least one block in the chunk at **chunk position** (x, z)
+    pub fn structures_in_chunk(&self, chunk_x: i32, chunk_z: i32) -> Vec<StructureStart> {
+        let mut structures = vec![];
+        for kind in StructureKind::ALL {
+            let spacing = kind.spacing();
+            let min = (
+                (chunk_x - MAX_STRUCTURE_REACH).div_euclid(spacing),
+                (chunk_z - MAX_STRUCTURE_REACH).div_euclid(spacing),
+            );
+            let max = (
+                (chunk_x + MAX_STRUCTURE_REACH).div_euclid(spacing),
+                (chunk_z + MAX_STRUCTURE_REACH).div_euclid(spacing),
+            );
+            for region_x in min.0..=max.0 {
+                for region_z in min.1..=max.1 {
+                    if let Some(start) = self.structure_start(kind, region_x, region_z) {
+                        if start.bounds.intersects_chunk(chunk_x, chunk_z) {
+                            structures.push(start);
+                        }
+                    }
+                }
+            }
+        }
+        structures
+    }
+
+    /// Searches the structure grid for the start of the given kind closest to world column (x, z),
+    /// looking at most `max_regions` grid cells away
+    pub fn nearest_structure(
+        &self,
+        kind: StructureKind,
+        x: i32,
+        z: i32,
+        max_regions: i32,
+    ) -> Option<StructureStart> {
+        let block_spacing = kind.spacing() * 16;
+        let (region_x, region_z) = (x.div_euclid(block_spacing), z.div_euclid(block_spacing));
+        let mut best: Option<(i64, StructureStart)> = None;
+        for ring in 0..=max_regions {
+            // every start in a further ring is at least this far away
+            let ring_distance = ((ring - 1).max(0) * block_spacing) as i64;
+            if let Some((distance, _)) = &best {
+                if *distance < ring_distance * ring_distance {
+                    break;
+                }
+            }
+            for dx in -ring..=ring {
+                for dz in -ring..=ring {
+                    if dx.abs() != ring && dz.abs() != ring {
+                        continue;
+                    }
+                    if let Some(start) = self.structure_start(kind, region_x + dx, region_z + dz) {
+                        let (sx, _, sz) = start.position();
+                        let distance = ((sx - x) as i64).pow(2) + ((sz - z) as i64).pow(2);
+                        if best.as_ref().is_none_or(|(d, _)| distance < *d) {
+                            best = Some((distance, start));
+                        }
+                    }
+                }
+            }
+        }
+        best.map(|(_, start)| start)
+    }
+}
+
+fn assemble_tower(start: &mut StructureStart, (x, y, z): (i32, i32, i32), rng: &mut Rng) {
+    start.try_add(StructurePiece::new(&TOWER_BASE, (x, y, z)), 0);
+    let mut y = y + TOWER_BASE.size().1;
+    for _ in 0..rng.range(2, 5) {
+        start.try_add(StructurePiece::new(&TOWER_FLOOR, (x, y, z)), 0);
+        y += TOWER_FLOOR.size().1;
+    }
+    start.try_add(StructurePiece::new(&TOWER_TOP, (x, y, z)), 0);
+}
+
+/// Adds a room at `origin` and then tries to grow corridors with more rooms out of each of its openings
+fn assemble_dungeon(
+    start: &mut StructureStart,
+    (x, y, z): (i32, i32, i32),
+    depth: i32,
+    rng: &mut Rng,
+) {
+    if !start.try_add(StructurePiece::new(&DUNGEON_ROOM, (x, y, z)), 0) || depth == 0 {
+        return;
+    }
+    let exits = [
+        (&CORRIDOR_X, (x + 7, y, z + 2), (x + 12, y, z)),
+        (&CORRIDOR_X, (x - 5, y, z + 2), (x - 12, y, z)),
+        (&CORRIDOR_Z, (x + 2, y, z + 7), (x, y, z + 12)),
+        (&CORRIDOR_Z, (x + 2, y, z - 5), (x, y, z - 12)),
+    ];
+    for (corridor, corridor_origin, room_origin) in exits {
+        if rng.chance(0.6) && start.try_add(StructurePiece::new(corridor, corridor_origin), 0) {
+            assemble_dungeon(start, room_origin, depth - 1, rng);
+        }
+    }
+}
+
+/// Record of every structure that has been placed in a world
+#[derive(Default)]
+pub struct StructureIndex {
+    starts: HashMap<(StructureKind, i32, i32), StructureStart>,
+}
+
+impl StructureIndex {
+    pub fn record(&mut self, start: StructureStart) {
+        self.starts
+            .entry((start.kind, start.region.0, start.region.1))
+            .or_insert(start);
+    }
+
+    pub fn iter(&self) -> impl Iterator<Item = &StructureStart> {
+        self.starts.values()
+    }
+
+    /// Returns the recorded structure of the given kind closest to world column (x, z)
+    pub fn nearest(&self, kind: StructureKind, x: i32, z: i32) -> Option<&StructureStart> {
+        self.starts
+            .values()
+            .filter(|start| start.kind == kind)
+            .min_by_key(|start| {
+                let (sx, _, sz) = start.position();
+                ((sx - x) as i64).pow(2) + ((sz - z) as i64).pow(2)
+            })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::object::COBBLESTONE;
+    use crate::structure::StructureKind;
+    use crate::world::World;
+    use crate::world_gen::WorldGen;
+
+    #[test]
+    fn pieces_do_not_overlap() {
+        let gen = WorldGen::new(3);
+        for kind in StructureKind::ALL {
+            for region in 0..8 {
+                if let Some(start) = gen.structure_start(kind, region, -region) {
+                    for (i, a) in start.pieces.iter().enumerate() {
+                        for b in &start.pieces[i + 1..] {
+                            assert!(!a.bounds.intersects(&b.bounds));
+                        }
+                    }
+                }
+            }
+        }
+    }
+
+    #[test]
+    fn dungeons_are_placed_and_recorded() {
+        let mut world = World::with_seed(11);
+        let start = WorldGen::new(11)
+            .nearest_structure(StructureKind::Dungeon, 0, 0, 16)
+            .expect("no dungeon found");
+        assert_eq!(world.structures().iter().count(), 0);
+        let (x, y, z) = start.position();
+        let (chunk_x, chunk_z) = (x.div_euclid(16), z.div_euclid(16));
+        world.generate_chunk(chunk_x, chunk_z);
+        let chunk = &world.chunks[&(chunk_x, chunk_z)];
+        assert_eq!(
+            chunk.blocks[x.rem_euclid(16) as usize][y as usize][z.rem_euclid(16) as usize],
+            COBBLESTONE
+        );
+        assert!(world
+            .structures()
+            .iter()
+            .any(|recorded| recorded.kind == StructureKind::Dungeon
+                && recorded.position() == (x, y, z)));
+    }
+}
@@ -0,0 +1,485 @@
+use crate::font::{Align, Font};
+use crate::sprite_batch::{Rect, SpriteBatch};
+use std::collections::HashMap;
+use std::ops::RangeInclusive;
+
+const ROW_HEIGHT: f32 = 40.0;
+const SPACING: f32 = 8.0;
+const TEXT_SCALE: f32 = 2.0;
+const TITLE_SCALE: f32 = 4.0;
+const PADDING: f32 = 10.0;
+const WIDGET_COLOUR: [f32; 4] = [0.25, 0.25, 0.25, 0.9];
+const HOVER_COLOUR: [f32; 4] = [0.4, 0.4, 0.45, 0.9];
+const ACTIVE_COLOUR: [f32; 4] = [0.3, 0.45, 0.7, 0.9];
+const HANDLE_COLOUR: [f32; 4] = [0.85, 0.85, 0.85, 1.0];
+const HANDLE_WIDTH: f32 = 8.0;
+const TEXT_COLOUR: [f32; 4] = [1.0, 1.0, 1.0, 1.0];
+const BACKDROP_COLOUR: [f32; 4] = [0.0, 0.0, 0.0, 0.6];
+
+/// Something to draw, recorded by [`Ui`] so that the widget logic doesn't need a window
+#[derive(Debug, Clone, PartialEq)]
+pub enum DrawCommand {
+    Fill(Rect, [f32; 4]),
+    Text {
+        text: String,
+        /// Top of the text, see [`Font::layout`]
+        position: [f32; 2],
+        scale: f32,
+        align: Align,
+        colour: [f32; 4],
+    },
+}
+
+/// Mouse and keyboard input for the UI, collected from window events between two frames.
+/// Positions are in pixels from the bottom left of the screen.
+#[derive(Debug, Default, Clone)]
+pub struct UiInput {
+    pub cursor: (f32, f32),
+    /// Whether the left mouse button is down
+    pub mouse_down: bool,
+    /// Whether the left mouse button went down since the last frame
+    pub clicked: bool,
+    /// Whether the left mouse button went up since the last frame
+    pub released: bool,
+    /// Characters typed since the last frame
+    pub typed: String,
+    /// Number of times backspace was pressed since the last frame
+    pub backspace: usize,
+    pub enter: bool,
+    /// Lines scrolled since the last frame, positive is up
+    pub scroll: f32,
+}
+
+impl UiInput {
+    pub fn press(&mut self) {
+        self.mouse_down = true;
+        self.clicked = true;
+    }
+
+    pub fn release(&mut self) {
+        self.mouse_down = false;
+        self.released = true;
+    }
+
+    /// Forgets everything that only happens once, to be called after each frame
+    pub fn end_frame(&mut self) {
+        self.clicked = false;
+        self.released = false;
+        self.typed.clear();
+        self.backspace = 0;
+        self.enter = false;
+        self.scroll = 0.0;
+    }
+}
+
+/// What the UI remembers between frames. Widgets are identified by their ids, which have to stay
+/// the same from frame to frame.
+#[derive(Debug, Default)]
+pub struct UiState {
+    /// Widget the mouse button was pressed on
+    active: Option<String>,
+    /// Text input that receives typed characters
+    focused: Option<String>,
+    /// First visible row of every list
+    scroll: HashMap<String, usize>,
+}
+
+fn contains((min, max): Rect, (x, y): (f32, f32)) -> bool {
+    (min[0]..=max[0]).contains(&x) && (min[1]..=max[1]).contains(&y)
+}
+
+/// Immediate mode UI: widgets are declared every frame and report how they were used right away.
+/// Widgets are laid out from top to bottom in a column. Sizes are in logical pixels, which are
+/// multiplied by the scale of the screen.
+pub struct Ui<'a> {
+    state: &'a mut UiState,
+    input: &'a UiInput,
+    commands: Vec<DrawCommand>,
+    left: f32,
+    /// Top of the next widget
+    top: f32,
+    width: f32,
+    /// Pixels per logical pixel
+    scale: f32,
+}
+
+impl<'a> Ui<'a> {
+    /// Starts a frame with a column `width` logical pixels wide, centred on a screen of the given
+    /// size in pixels with `scale` pixels per logical pixel
+    pub fn new(
+        state: &'a mut UiState,
+        input: &'a UiInput,
+        screen: (f32, f32),
+        width: f32,
+        scale: f32,
+    ) -> Ui<'a> {
+        let width = width * scale;
+        Ui {
+            state,
+            input,
+            commands: vec![],
+            left: (screen.0 - width) / 2.0,
+            top: screen.1 * 0.8,
+            width,
+            scale,
+        }
+    }
+
+    /// Darkens everything that was drawn before the UI
+    pub fn backdrop(&mut self, screen: (f32, f32)) {
+        self.commands.push(DrawCommand::Fill(
+            ([0.0, 0.0], [screen.0, screen.1]),
+            BACKDROP_COLOUR,
+        ));
+    }
+
+    fn next_rect(&mut self, height: f32) -> Rect {
+        let rect = (
+            [self.left, self.top - height],
+            [self.left + self.width, self.top],
+        );
+        self.top -= height + SPACING * self.scale;
+        rect
+    }
+
+    /// Returns the rectangle of a widget one row high
+    fn next_row(&mut self) -> Rect {
+        self.next_rect(ROW_HEIGHT * self.scale)
+    }
+
+    fn text(&mut self, text: &str, position: [f32; 2], scale: f32, align: Align) {
+        self.commands.push(DrawCommand::Text {
+            text: text.to_string(),
+            position,
+            scale,
+            align,
+            colour: TEXT_COLOUR,
+        });
+    }
+
+    /// Adds text vertically centred in `rect`
+    fn text_in(&mut self, text: &str, (min, max): Rect, align: Align) {
+        let scale = TEXT_SCALE * self.scale;
+        let height = Font::measure(text, scale).1;
+        let top = (min[1] + max[1] + height) / 2.0;
+        let padding = PADDING * self.scale;
+        let x = match align {
+            Align::Left => min[0] + padding,
+            Align::Centre => (min[0] + max[0]) / 2.0,
+            Align::Right => max[0] - padding,
+        };
+        self.text(text, [x, top], scale, align);
+    }
+
+    /// Tracks the mouse button on a widget. Returns whether the cursor is over it and whether it
+    /// was clicked, which is when the button is released over the widget it was pressed on.
+    fn interact(&mut self, id: &str, rect: Rect) -> (bool, bool) {
+        let hovered = contains(rect, self.input.cursor);
+        if hovered && self.input.clicked {
+            self.state.active = Some(id.to_string());
+        }
+        let active = self.state.active.as_deref() == Some(id);
+        (hovered, active && hovered && self.input.released)
+    }
+
+    fn colour(&self, id: &str, hovered: bool) -> [f32; 4] {
+        if self.state.active.as_deref() == Some(id) || self.state.focused.as_deref() == Some(id) {
+            ACTIVE_COLOUR
+        } else if hovered {
+            HOVER_COLOUR
+        } else {
+            WIDGET_COLOUR
+        }
+    }
+
+    pub fn title(&mut self, text: &str) {
+        let scale = TITLE_SCALE * self.scale;
+        let height = Font::measure(text, scale).1;
+        let rect = self.next_rect(height + SPACING * self.scale);
+        let position = [(rect.0[0] + rect.1[0]) / 2.0, rect.1[1]];
+        self.text(text, position, scale, Align::Centre);
+    }
+
+    pub fn label(&mut self, text: &str) {
+        let rect = self.next_row();
+        self.text_in(text, rect, Align::Left);
+    }
+
+    /// Returns whether the button was clicked
+    pub fn button(&mut self, label: &str) -> bool {
+        let rect = self.next_row();
+        let (hovered, clicked) = self.interact(label, rect);
+        self.commands
+            .push(DrawCommand::Fill(rect, self.colour(label, hovered)));
+        self.text_in(label, rect, Align::Centre);
+        clicked
+    }
+
+    /// Returns whether the value was changed
+    pub fn toggle(&mut self, label: &str, value: &mut bool) -> bool {
+        let rect = self.next_row();
+        let (hovered, clicked) = self.interact(label, rect);
+        if clicked {
+            *value = !*value;
+        }
+        self.commands
+            .push(DrawCommand::Fill(rect, self.colour(label, hovered)));
+        self.text_in(label, rect, Align::Left);
+        self.text_in(if *value { "On" } else { "Off" }, rect, Align::Right);
+        clicked
+    }
+
+    /// Slider showing `text` that sets `value` within `range` while it is dragged. Returns whether
+    /// the value was changed.
+    pub fn slider(
+        &mut self,
+        id: &str,
+        text: &str,
+        value: &mut f32,
+        range: RangeInclusive<f32>,
+    ) -> bool {
+        let rect = self.next_row();
+        let (hovered, _) = self.interact(id, rect);
+        let (min, max) = (*range.start(), *range.end());
+        let mut changed = false;
+        // the handle's centre moves over the slider minus half the handle at either end
+        let handle_width = HANDLE_WIDTH * self.scale;
+        let track = self.width - handle_width;
+        if self.state.active.as_deref() == Some(id) && self.input.mouse_down {
+            let t =
+                ((self.input.cursor.0 - rect.0[0] - handle_width / 2.0) / track).clamp(0.0, 1.0);
+            let new = min + t * (max - min);
+            changed = new != *value;
+            *value = new;
+        }
+        self.commands
+            .push(DrawCommand::Fill(rect, self.colour(id, hovered)));
+        let t = ((*value - min) / (max - min)).clamp(0.0, 1.0);
+        let x = rect.0[0] + t * track;
+        self.commands.push(DrawCommand::Fill(
+            ([x, rect.0[1]], [x + handle_width, rect.1[1]]),
+            HANDLE_COLOUR,
+        ));
+        self.text_in(text, rect, Align::Centre);
+        changed
+    }
+
+    /// Single line text field that takes typed characters after it has been clicked. Returns
+    /// whether Enter was pressed while it had focus.
+    pub fn text_input(&mut self, id: &str, value: &mut String) -> bool {
+        let rect = self.next_row();
+        let (hovered, _) = self.interact(id, rect);
+        if self.input.clicked {
+            if hovered {
+                self.state.focused = Some(id.to_string());
+            } else if self.state.focused.as_deref() == Some(id) {
+                self.state.focused = None;
+            }
+        }
+        let focused = self.state.focused.as_deref() == Some(id);
+        if focused {
+            for _ in 0..self.input.backspace {
+                value.pop();
+            }
+            value.extend(self.input.typed.chars().filter(|c| !c.is_control()));
+        }
+        self.commands
+            .push(DrawCommand::Fill(rect, self.colour(id, hovered)));
+        let shown = if focused {
+            format!("{}_", value)
+        } else {
+            value.clone()
+        };
+        self.text_in(&shown, rect, Align::Left);
+        focused && self.input.enter
+    }
+
+    /// List of items with room for `rows` of them, scrolled with the mouse wheel. Clicking an item
+    /// selects it. Returns whether the selection was changed.
+    pub fn list(
+        &mut self,
+        id: &str,
+        items: &[String],
+        selected: &mut Option<usize>,
+        rows: usize,
+    ) -> bool {
+        let row_height = ROW_HEIGHT * self.scale;
+        let rect = self.next_rect(rows as f32 * row_height);
+        self.commands.push(DrawCommand::Fill(rect, WIDGET_COLOUR));
+        let max_scroll = items.len().saturating_sub(rows);
+        let mut scroll = self.state.scroll.get(id).copied().unwrap_or(0);
+        if contains(rect, self.input.cursor) && self.input.scroll != 0.0 {
+            let steps = -self.input.scroll.signum() as isize;
+            scroll = scroll.saturating_add_signed(steps);
+        }
+        scroll = scroll.min(max_scroll);
+        self.state.scroll.insert(id.to_string(), scroll);
+
+        let mut changed = false;
+        for (row, index) in (scroll..items.len().min(scroll + rows)).enumerate() {
+            let top = rect.1[1] - row as f32 * row_height;
+            let row_rect = ([rect.0[0], top - row_height], [rect.1[0], top]);
+            let item_id = format!("{}#{}", id, index);
+            let (hovered, clicked) = self.interact(&item_id, row_rect);
+            if clicked && *selected != Some(index) {
+                *selected = Some(index);
+                changed = true;
+            }
+            if *selected == Some(index) {
+                self.commands
+                    .push(DrawCommand::Fill(row_rect, ACTIVE_COLOUR));
+            } else if hovered {
+                self.commands
+                    .push(DrawCommand::Fill(row_rect, HOVER_COLOUR));
+            }
+            self.text_in(&items[index], row_rect, Align::Left);
+        }
+        changed
+    }
+
+    /// Ends the frame and returns what to draw
+    pub fn finish(self) -> Vec<DrawCommand> {
+        if self.input.released {
+            self.state.active = None;
+        }
+        self.commands
+    }
+}
+
+/// Adds the commands to the batch
+pub fn render(commands: &[DrawCommand], batch: &mut SpriteBatch, font: &Font) {
+    for command in commands {
+        match command {
+            DrawCommand::Fill(rect, colour) => batch.fill(*rect, *colour),
+            DrawCommand::Text {
+                text,
+                position,
+                scale,
+                align,
+                colour,
+            } => font.draw(batch, text, *position, *scale, *align, *colour),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::ui::{DrawCommand, Ui, UiInput, UiState};
+
+    const SCREEN: (f32, f32) = (800.0, 600.0);
+
+    /// Runs one frame of a UI with a button, a slider and a text field, returning whether the
+    /// button was clicked
+    fn frame(state: &mut UiState, input: &mut UiInput, value: &mut f32, text: &mut String) -> bool {
+        let mut ui = Ui::new(state, input, SCREEN, 400.0, 1.0);
+        let clicked = ui.button("Play");
+        ui.slider("volume", "Volume", value, 0.0..=10.0);
+        ui.text_input("name", text);
+        ui.finish();
+        input.end_frame();
+        clicked
+    }
+
+    #[test]
+    fn widgets_react_to_mouse() {
+        let (mut state, mut input) = (UiState::default(), UiInput::default());
+        let (mut value, mut text) = (0.0, String::new());
+        // rows start at 80% of the height and are 40 pixels high with 8 pixels between them
+        let button = (400.0, 460.0);
+        // 3/4 of the way along the track, which leaves out half a handle at either end
+        let slider = (498.0, 412.0);
+        let field = (400.0, 364.0);
+
+        input.cursor = button;
+        input.press();
+        assert!(!frame(&mut state, &mut input, &mut value, &mut text));
+        input.release();
+        assert!(frame(&mut state, &mut input, &mut value, &mut text));
+
+        // pressing elsewhere and releasing on the button doesn't count
+        input.cursor = (10.0, 10.0);
+        input.press();
+        frame(&mut state, &mut input, &mut value, &mut text);
+        input.cursor = button;
+        input.release();
+        assert!(!frame(&mut state, &mut input, &mut value, &mut text));
+
+        input.cursor = slider;
+        input.press();
+        frame(&mut state, &mut input, &mut value, &mut text);
+        assert!((value - 7.5).abs() < 0.01);
+        input.cursor = (0.0, 0.0);
+        frame(&mut state, &mut input, &mut value, &mut text);
+        assert_eq!(value, 0.0);
+        input.release();
+        frame(&mut state, &mut input, &mut value, &mut text);
+
+        input.cursor = field;
+        input.press();
+        input.typed.push_str("abc");
+        frame(&mut state, &mut input, &mut value, &mut text);
+        input.release();
+        input.typed.push('d');
+        input.backspace = 2;
+        frame(&mut state, &mut input, &mut value, &mut text);
+        assert_eq!(text, "ad");
+    }
+
+    #[test]
+    fn lists_select_and_scroll() {
+        let (mut state, mut input) = (UiState::default(), UiInput::default());
+        let items: Vec<String> = (0..10).map(|i| format!("world {}", i)).collect();
+        let mut selected = None;
+        let mut run = |state: &mut UiState, input: &mut UiInput| {
+            let mut ui = Ui::new(state, input, SCREEN, 400.0, 1.0);
+            let changed = ui.list("worlds", &items, &mut selected, 3);
+            let commands = ui.finish();
+            input.end_frame();
+            (changed, commands)
+        };
+
+        input.cursor = (400.0, 460.0);
+        input.scroll = -1.0;
+        let (_, commands) = run(&mut state, &mut input);
+        let first = commands.iter().find_map(|command| match command {
+            DrawCommand::Text { text, .. } => Some(text.clone()),
+            _ => None,
+        });
+        assert_eq!(first.as_deref(), Some("world 1"));
+
+        input.press();
+        run(&mut state, &mut input);
+        input.release();
+        assert!(run(&mut state, &mut input).0);
+        assert_eq!(selected, Some(1));
+    }
+
+    #[test]
+    fn scales_to_logical_pixels() {
+        let (mut state, mut input) = (UiState::default(), UiInput::default());
+        // below the button at scale 1, whose row ends 40 pixels under the top at 480
+        input.cursor = (400.0, 420.0);
+        let run = |state: &mut UiState, input: &mut UiInput, scale| {
+            let mut ui = Ui::new(state, input, SCREEN, 400.0, scale);
+            let clicked = ui.button("Play");
+            let commands = ui.finish();
+            input.end_frame();
+            (clicked, commands)
+        };
+        for (scale, hit) in [(1.0, false), (2.0, true)] {
+            input.press();
+            run(&mut state, &mut input, scale);
+            input.release();
+            let (clicked, commands) = run(&mut state, &mut input, scale);
+            assert_eq!(clicked, hit);
+            let Some(DrawCommand::Fill((min, max), _)) = commands.first() else {
+                panic!("the button isn't drawn first");
+            };
+            assert_eq!(max[0] - min[0], 400.0 * scale);
+            assert!(commands.iter().any(|command| matches!(
+                command,
+                DrawCommand::Text { scale: text_scale, .. } if *text_scale == 2.0 * scale
+            )));
+        }
+    }
+}
@@ -7,6 +7,8 @@ in vec2 tex_coords;
 out vec3 v_normal;
 out vec3 v_position;
 out vec2 v_tex_coords;
+out vec3 v_world_position;
+out vec3 v_world_normal;
 
 uniform mat4 perspective;
 uniform mat4 view;
@@ -18,4 +20,6 @@ void main() {
     gl_Position = perspective * modelview * vec4(position, 1.0);
     v_position = gl_Position.xyz / gl_Position.w;
     v_tex_coords = tex_coords;
+    v_world_position = (model * vec4(position, 1.0)).xyz;
+    v_world_normal = mat3(model) * normal;
 }
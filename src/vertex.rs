@@ -7,3 +7,11 @@ pub struct Vertex {
     pub tex_coords: [f32; 2],
 }
 implement_vertex!(Vertex, position, normal, tex_coords);
+
+#[derive(Copy, Clone, Debug, PartialEq)]
+pub struct Vertex2D {
+    pub position: [f32; 2],
+    pub tex_coords: [f32; 2],
+    pub colour: [f32; 4],
+}
+implement_vertex!(Vertex2D, position, tex_coords, colour);
@@ -0,0 +1,155 @@
+use crate::math::Vec3;
+use crate::save::write_atomic;
+use crate::vertex::Vertex;
+use std::fs;
+use std::io;
+use std::path::Path;
+
+/// Colours given to new waypoints, in turn
+const COLOURS: [[u8; 3]; 6] = [
+    [230, 60, 60],
+    [60, 200, 230],
+    [240, 220, 60],
+    [200, 90, 230],
+    [90, 230, 110],
+    [250, 150, 50],
+];
+
+const BEACON_WIDTH: f32 = 0.3;
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct Waypoint {
+    pub name: String,
+    pub position: Vec3,
+    pub colour: [u8; 3],
+}
+
+impl Waypoint {
+    /// Returns the vertices of a thin column of light going up from the waypoint to the top of the world
+    pub fn beacon(&self) -> Vec<Vertex> {
+        let Vec3(x, y, z) = self.position;
+        let (x0, x1) = (x - BEACON_WIDTH / 2.0, x + BEACON_WIDTH / 2.0);
+        let (z0, z1) = (z - BEACON_WIDTH / 2.0, z + BEACON_WIDTH / 2.0);
+        let (y0, y1) = (y, 256.0);
+        let corners = [(x0, z0), (x1, z0), (x1, z1), (x0, z1), (x0, z0)];
+        let mut vertices = vec![];
+        for side in corners.windows(2) {
+            let (a, b) = (side[0], side[1]);
+            for (px, py, pz) in [
+                (a.0, y0, a.1),
+                (b.0, y0, b.1),
+                (a.0, y1, a.1),
+                (b.0, y0, b.1),
+                (b.0, y1, b.1),
+                (a.0, y1, a.1),
+            ] {
+                vertices.push(Vertex {
+                    position: [px, py, pz],
+                    normal: [0.0, 1.0, 0.0],
+                    tex_coords: [0.0, 0.0],
+                });
+            }
+        }
+        vertices
+    }
+}
+
+/// The waypoints of a world. Saved as `waypoints.txt` in the world directory, one waypoint per
+/// line as `x y z r g b name`.
+#[derive(Debug, Default)]
+pub struct Waypoints {
+    pub waypoints: Vec<Waypoint>,
+}
+
+impl Waypoints {
+    /// Adds a waypoint at `position`. An empty name is replaced by a numbered default name.
+    pub fn add(&mut self, name: &str, position: Vec3) -> &mut Waypoint {
+        let name = name.trim();
+        let name = if name.is_empty() {
+            format!("Waypoint {}", self.waypoints.len() + 1)
+        } else {
+            name.to_string()
+        };
+        let colour = COLOURS[self.waypoints.len() % COLOURS.len()];
+        self.waypoints.push(Waypoint {
+            name,
+            position,
+            colour,
+        });
+        self.waypoints.last_mut().unwrap()
+    }
+
+    pub fn remove(&mut self, name: &str) -> Option<Waypoint> {
+        let index = self.waypoints.iter().position(|w| w.name == name)?;
+        Some(self.waypoints.remove(index))
+    }
+
+    pub fn to_text(&self) -> String {
+        let mut text = String::new();
+        for w in &self.waypoints {
+            text += &format!(
+                "{} {} {} {} {} {} {}\n",
+                w.position.0,
+                w.position.1,
+                w.position.2,
+                w.colour[0],
+                w.colour[1],
+                w.colour[2],
+                w.name
+            );
+        }
+        text
+    }
+
+    /// Parses waypoints written by [`Waypoints::to_text`]. Lines that can't be parsed are skipped.
+    pub fn from_text(text: &str) -> Waypoints {
+        let parse = |line: &str| -> Option<Waypoint> {
+            let mut parts = line.splitn(7, ' ');
+            let mut number = || parts.next()?.parse::<f32>().ok();
+            let position = Vec3(number()?, number()?, number()?);
+            let colour = [number()? as u8, number()? as u8, number()? as u8];
+            let name = parts.next()?.to_string();
+            Some(Waypoint {
+                name,
+                position,
+                colour,
+            })
+        };
+        Waypoints {
+            waypoints: text.lines().filter_map(parse).collect(),
+        }
+    }
+
+    /// Loads the waypoints saved in the world directory `dir`, if there are any
+    pub fn load(dir: &Path) -> io::Result<Waypoints> {
+        match fs::read_to_string(dir.join("waypoints.txt")) {
+            Ok(text) => Ok(Waypoints::from_text(&text)),
+            Err(e) if e.kind() == io::ErrorKind::NotFound => Ok(Waypoints::default()),
+            Err(e) => Err(e),
+        }
+    }
+
+    pub fn save(&self, dir: &Path) -> io::Result<()> {
+        fs::create_dir_all(dir)?;
+        write_atomic(&dir.join("waypoints.txt"), self.to_text())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::math::Vec3;
+    use crate::waypoint::Waypoints;
+
+    #[test]
+    fn text_round_trip() {
+        let mut waypoints = Waypoints::default();
+        waypoints.add("home base", Vec3(1.5, 70.0, -3.25));
+        waypoints.add("  ", Vec3(0.0, 0.0, 0.0));
+        assert_eq!(waypoints.waypoints[1].name, "Waypoint 2");
+
+        let loaded = Waypoints::from_text(&(waypoints.to_text() + "garbage\n"));
+        assert_eq!(loaded.waypoints, waypoints.waypoints);
+        assert!(waypoints.remove("home base").is_some());
+        assert_eq!(waypoints.waypoints.len(), 1);
+    }
+}
@@ -0,0 +1,68 @@
+/// Size, scale and focus of the game window, kept up to date from window events
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct WindowState {
+    /// Width and height of the drawing area in physical pixels, which are 0 while minimized
+    pub size: (u32, u32),
+    /// Physical pixels per logical pixel on the monitor the window is on
+    pub scale_factor: f64,
+    /// Whether the window has keyboard focus. Input is ignored and the cursor is free while it
+    /// doesn't.
+    pub focused: bool,
+    /// Whether the window is fullscreen right now, the settings say whether it should be
+    pub fullscreen: bool,
+}
+
+impl WindowState {
+    pub fn new(size: (u32, u32), scale_factor: f64) -> WindowState {
+        WindowState {
+            size,
+            scale_factor,
+            focused: true,
+            fullscreen: false,
+        }
+    }
+
+    pub fn minimized(&self) -> bool {
+        self.size.0 == 0 || self.size.1 == 0
+    }
+
+    /// Returns the width divided by the height, or 1 while minimized so projections stay valid
+    pub fn aspect(&self) -> f32 {
+        if self.minimized() {
+            1.0
+        } else {
+            self.size.0 as f32 / self.size.1 as f32
+        }
+    }
+
+    /// Returns the size as floats, the screen size the UI is laid out in
+    pub fn screen(&self) -> (f32, f32) {
+        (self.size.0 as f32, self.size.1 as f32)
+    }
+
+    /// Returns the pixels per logical pixel that the UI and HUD are scaled by
+    pub fn ui_scale(&self) -> f32 {
+        self.scale_factor as f32
+    }
+
+    /// Converts `cursor`, in pixels from the top left corner like window events give it, to UI
+    /// coordinates, which start at the bottom left corner
+    pub fn ui_cursor(&self, (x, y): (f32, f32)) -> (f32, f32) {
+        (x, self.size.1 as f32 - y)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::window::WindowState;
+
+    #[test]
+    fn follows_the_window_size() {
+        let mut window = WindowState::new((1600, 900), 2.0);
+        assert_eq!(window.aspect(), 16.0 / 9.0);
+        assert_eq!(window.ui_cursor((100.0, 0.0)), (100.0, 900.0));
+        window.size = (0, 0);
+        assert!(window.minimized());
+        assert_eq!(window.aspect(), 1.0);
+    }
+}
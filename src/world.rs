@@ -1,55 +1,82 @@
+use crate::block_update::{gravity_update, BlockPos, BlockUpdate, BlockUpdates};
 use crate::chunk::Chunk;
-use crate::object::Object;
+use crate::object::{self, Object};
+use crate::rng::{hash, Rng};
+use crate::structure::{StructureIndex, StructureKind, StructureStart};
 use crate::world_gen::WorldGen;
 use std::collections::HashMap;
 
+const DEFAULT_SEED: u64 = 0x5eed;
+/// Most block updates that run in one tick. The rest wait for the next tick, so chains of updates
+/// can't freeze the game.
+const MAX_UPDATES_PER_TICK: usize = 4096;
+/// Blocks picked for a random tick in every 16 blocks high section of a chunk per tick
+const RANDOM_TICKS_PER_SECTION: usize = 3;
+const RANDOM_TICK_SALT: u64 = 0x7a4d_71c5;
+
 pub struct World {
     pub chunks: HashMap<(i32, i32), Chunk>,
     block_types: HashMap<u8, Object>,
+    world_gen: WorldGen,
+    structures: StructureIndex,
+    updates: BlockUpdates,
+    /// Blocks that started falling and their ids, which become entities
+    falling: Vec<(BlockPos, u8)>,
+}
+
+impl Default for World {
+    fn default() -> World {
+        World::new()
+    }
 }
 
 impl World {
     /// Returns a reference to the chunk at position (x, _, z) in world coordinates. Creates it if it doesn't exist yet
     pub fn chunk_at_pos_create(&mut self, x: i32, z: i32) -> &mut Chunk {
-        if !self.chunk_exists(x / 16, z / 16) {
-            self.create_empty_chunk(x / 16, z / 16);
+        let (chunk_x, chunk_z) = (x.div_euclid(16), z.div_euclid(16));
+        if !self.chunk_exists(chunk_x, chunk_z) {
+            self.create_empty_chunk(chunk_x, chunk_z);
         }
-        self.chunks.get_mut(&(x / 16, z / 16)).unwrap()
+        self.chunks.get_mut(&(chunk_x, chunk_z)).unwrap()
     }
     /// Returns a reference to the chunk at position (x, _, z) in world coordinates, if it exists
     pub fn chunk_at_pos(&self, x: i32, z: i32) -> Option<&Chunk> {
-        self.chunks.get(&(x / 16, z / 16))
+        self.chunks.get(&(x.div_euclid(16), z.div_euclid(16)))
+    }
+    /// Returns the id of the block at position (x, y, z) in world coordinates, if its chunk exists
+    pub fn block_id_at(&self, x: i32, y: i32, z: i32) -> Option<u8> {
+        if !(0..256).contains(&y) {
+            return None;
+        }
+        self.chunk_at_pos(x, z).map(|chunk| {
+            chunk.blocks[x.rem_euclid(16) as usize][y as usize][z.rem_euclid(16) as usize]
+        })
+    }
+    /// Sets the block at position (x, y, z) in world coordinates, marks its chunk for meshing and
+    /// notifies the blocks around it if it changed. Returns false if the chunk doesn't exist or y
+    /// is out of bounds.
+    pub fn set_block(&mut self, x: i32, y: i32, z: i32, id: u8) -> bool {
+        if !(0..256).contains(&y) {
+            return false;
+        }
+        let Some(chunk) = self.chunks.get_mut(&(x.div_euclid(16), z.div_euclid(16))) else {
+            return false;
+        };
+        let block =
+            &mut chunk.blocks[x.rem_euclid(16) as usize][y as usize][z.rem_euclid(16) as usize];
+        if *block != id {
+            *block = id;
+            chunk.mesh = None;
+            self.updates.block_changed((x, y, z));
+        }
+        true
     }
     /// Returns a mutable reference to the chunk at position (x, _, z) in world coordinates, if it exists
     // pub fn chunk_at_mut(&mut self, x: i32, z: i32) -> Option<&mut Chunk> {
     //     self.chunks.get_mut(&(x / 16, z / 16))
     // }
-    /// Returns a reference to the object type at position (x, y, z) in world coordinates, if it exists
-    pub fn block_at(&mut self, mut x: i32, y: i32, mut z: i32) -> Option<&Object> {
-        let chunk_x = x / 16 - if x < 0 { 1 } else { 0 };
-        let chunk_z = z / 16 - if z < 0 { 1 } else { 0 };
-        //println!("chunk: {}, {}", chunk_x, chunk_z);
-        if !self.chunk_exists(chunk_x, chunk_z) {
-            println!("chunk doesn't exist");
-            self.create_empty_chunk(chunk_x, chunk_z);
-        }
-        if let Some(chunk) = self.chunks.get(&(chunk_x, chunk_z)) {
-            while x < 0 {
-                //TODO improve
-                x += 16
-            }
-            while z < 0 {
-                z += 16
-            }
-            let id = chunk.blocks[(x % 16) as usize][y as usize][(z % 16) as usize];
-            self.block_types.get(&id)
-        } else {
-            None
-        }
-    }
     /// Creates an empty chunk at **chunk position** (x, z)
     pub fn create_empty_chunk(&mut self, chunk_x: i32, chunk_z: i32) {
-        println!("creating empty chunk");
         self.chunks.insert((chunk_x, chunk_z), Chunk::new());
     }
     /// Returns whether this chunk exists
@@ -58,15 +85,135 @@ impl World {
     }
 
     pub fn new() -> Self {
-        let mut world = World {
+        World::with_seed(DEFAULT_SEED)
+    }
+    pub fn with_seed(seed: u64) -> Self {
+        World::with_world_gen(WorldGen::new(seed))
+    }
+    pub fn with_world_gen(world_gen: WorldGen) -> Self {
+        World {
             chunks: HashMap::new(),
-            block_types: HashMap::new(),
-        };
-        world.block_types.insert(1, Object {});
-        world
+            block_types: object::default_block_types(),
+            world_gen,
+            structures: StructureIndex::default(),
+            updates: BlockUpdates::default(),
+            falling: vec![],
+        }
     }
+    pub fn world_gen(&self) -> &WorldGen {
+        &self.world_gen
+    }
+    /// Returns the definition of the block type with the given id, if it exists
+    pub fn block_type(&self, id: u8) -> Option<&Object> {
+        self.block_types.get(&id)
+    }
+    /// Returns the id of the block type with the given name, if it exists
+    pub fn block_id(&self, name: &str) -> Option<u8> {
+        self.block_types()
+            .find(|(_, object)| object.name == name)
+            .map(|(id, _)| id)
+    }
+    /// Returns all known block types and their ids
+    pub fn block_types(&self) -> impl Iterator<Item = (u8, &Object)> {
+        self.block_types.iter().map(|(id, object)| (*id, object))
+    }
+    /// Generates the chunk at **chunk position** (x, z), replacing it if it already exists
     pub fn generate_chunk(&mut self, chunk_x: i32, chunk_z: i32) {
-        self.chunks
-            .insert((chunk_x, chunk_z), WorldGen::generate_chunk());
+        let structures = self.world_gen.structures_in_chunk(chunk_x, chunk_z);
+        let chunk = self
+            .world_gen
+            .generate_chunk_with(chunk_x, chunk_z, &structures);
+        self.chunks.insert((chunk_x, chunk_z), chunk);
+        for start in structures {
+            self.structures.record(start);
+        }
+    }
+    /// Returns the number of ticks that have passed, which is also the time of day, see
+    /// [`crate::sky`]
+    pub fn time(&self) -> u64 {
+        self.updates.time()
+    }
+    pub fn set_time(&mut self, time: u64) {
+        self.updates.set_time(time);
+    }
+    /// Schedules an update of the block at `position` in `delay` ticks. Of the ticks due at the
+    /// same time, the ones with the highest priority run first.
+    pub fn schedule_tick(&mut self, position: BlockPos, delay: u64, priority: i32) {
+        self.updates.schedule(position, delay, priority);
+    }
+    /// Runs the update of the block at `position`, if its type reacts to updates
+    fn update_block(&mut self, (x, y, z): BlockPos, update: BlockUpdate) {
+        let Some(id) = self.block_id_at(x, y, z) else {
+            return;
+        };
+        let Some(object) = self.block_type(id) else {
+            return;
+        };
+        let (gravity, on_update) = (object.gravity, object.on_update);
+        if gravity {
+            gravity_update(self, (x, y, z), update);
+        }
+        let Some(on_update) = on_update else {
+            return;
+        };
+        let salt = self.time() ^ ((y as u64) << 48);
+        let mut rng = Rng::new(hash(self.world_gen.seed(), x, z, salt));
+        on_update(self, (x, y, z), update, &mut rng);
+    }
+    /// Records that the block `id`, which was at `position`, started falling
+    pub fn start_falling(&mut self, position: BlockPos, id: u8) {
+        self.falling.push((position, id));
+    }
+    /// Returns the blocks that started falling since the last call
+    pub fn take_falling(&mut self) -> Vec<(BlockPos, u8)> {
+        std::mem::take(&mut self.falling)
+    }
+    /// Runs one game tick: the block updates that are due and the random ticks of every loaded
+    /// chunk. The same world and seed always give the same result.
+    pub fn tick(&mut self) {
+        for _ in 0..MAX_UPDATES_PER_TICK {
+            let Some((position, update)) = self.updates.next_due() else {
+                break;
+            };
+            self.update_block(position, update);
+        }
+
+        let mut chunks: Vec<_> = self.chunks.keys().copied().collect();
+        chunks.sort();
+        let seed = hash(self.world_gen.seed(), 0, 0, self.time());
+        for (chunk_x, chunk_z) in chunks {
+            let mut rng = Rng::for_chunk(seed, chunk_x, chunk_z, RANDOM_TICK_SALT);
+            for section in 0..16 {
+                for _ in 0..RANDOM_TICKS_PER_SECTION {
+                    let x = chunk_x * 16 + rng.range(0, 16);
+                    let y = section * 16 + rng.range(0, 16);
+                    let z = chunk_z * 16 + rng.range(0, 16);
+                    let random_ticks = self
+                        .block_id_at(x, y, z)
+                        .and_then(|id| self.block_type(id))
+                        .is_some_and(|object| object.random_ticks);
+                    if random_ticks {
+                        self.update_block((x, y, z), BlockUpdate::Random);
+                    }
+                }
+            }
+        }
+        self.updates.advance();
+    }
+    /// Returns the record of all structures placed in generated chunks so far
+    pub fn structures(&self) -> &StructureIndex {
+        &self.structures
+    }
+    /// Finds the structure of the given kind closest to world column (x, z), whether or not its
+    /// chunks have been generated yet, and records it
+    pub fn nearest_structure(
+        &mut self,
+        kind: StructureKind,
+        x: i32,
+        z: i32,
+    ) -> Option<StructureStart> {
+        let start = self.world_gen.nearest_structure(kind, x, z, 16)?;
+        self.structures.record(start.clone());
+        Some(start)
     }
 }
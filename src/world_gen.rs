@@ -1,11 +1,378 @@
-pub struct WorldGen {}
-
 use crate::chunk::Chunk;
+use crate::feature::{Feature, ORES};
+use crate::object::{AIR, DIRT, GRASS, STONE};
+use crate::rng::{hash, Rng};
+use crate::structure::StructureStart;
+
+const BASE_HEIGHT: f32 = 64.0;
+const DIRT_DEPTH: i32 = 3;
+
+const SALT_TERRAIN: u64 = 1;
+const SALT_DETAIL: u64 = 2;
+const SALT_ORE: u64 = 16;
+const SALT_BOULDER: u64 = 32;
+const SALT_TREE: u64 = 33;
+const SALT_FLOWER: u64 = 34;
+
+/// What new chunks are filled with
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Generator {
+    /// Seeded terrain with features and structures
+    Terrain,
+    /// Only the bottom layer of stone, for worlds that are imported or built by hand
+    Flat,
+}
+
+impl Generator {
+    pub fn name(&self) -> &'static str {
+        match self {
+            Generator::Terrain => "terrain",
+            Generator::Flat => "flat",
+        }
+    }
+
+    pub fn from_name(name: &str) -> Option<Generator> {
+        match name {
+            "terrain" => Some(Generator::Terrain),
+            "flat" => Some(Generator::Flat),
+            _ => None,
+        }
+    }
+}
+
+pub struct WorldGen {
+    seed: u64,
+    generator: Generator,
+}
+
+/// A chunk that is still being generated.
+///
+/// Generation happens in two phases: first the terrain shape of the chunk is filled in, then the
+/// chunk is populated with the features of itself and its eight neighbours, clipped to the chunk.
+/// Features are planned from the seed and the terrain height function only, so a feature that
+/// straddles a chunk border comes out the same no matter which of the chunks is generated first.
+pub struct ProtoChunk {
+    pub chunk_x: i32,
+    pub chunk_z: i32,
+    pub blocks: [[[u8; 16]; 256]; 16],
+}
+
+impl ProtoChunk {
+    pub fn new(chunk_x: i32, chunk_z: i32) -> ProtoChunk {
+        ProtoChunk {
+            chunk_x,
+            chunk_z,
+            blocks: [[[AIR; 16]; 256]; 16],
+        }
+    }
+
+    /// Converts the world position (x, y, z) into an index into `blocks`, if it lies inside this chunk
+    fn local(&self, x: i32, y: i32, z: i32) -> Option<(usize, usize, usize)> {
+        if x.div_euclid(16) != self.chunk_x || z.div_euclid(16) != self.chunk_z {
+            return None;
+        }
+        if !(0..256).contains(&y) {
+            return None;
+        }
+        Some((
+            x.rem_euclid(16) as usize,
+            y as usize,
+            z.rem_euclid(16) as usize,
+        ))
+    }
+
+    /// Returns the block at world position (x, y, z), if it lies inside this chunk
+    pub fn get(&self, x: i32, y: i32, z: i32) -> Option<u8> {
+        self.local(x, y, z).map(|(x, y, z)| self.blocks[x][y][z])
+    }
+
+    /// Sets the block at world position (x, y, z) if it lies inside this chunk and `replace` accepts the old block
+    pub fn set(&mut self, x: i32, y: i32, z: i32, id: u8, replace: impl Fn(u8) -> bool) {
+        if let Some((x, y, z)) = self.local(x, y, z) {
+            if replace(self.blocks[x][y][z]) {
+                self.blocks[x][y][z] = id;
+            }
+        }
+    }
+
+    pub fn into_chunk(self) -> Chunk {
+        Chunk {
+            blocks: self.blocks,
+            mesh: None,
+        }
+    }
+}
+
+fn smoothstep(t: f32) -> f32 {
+    t * t * (3.0 - 2.0 * t)
+}
 
 impl WorldGen {
-    pub fn generate_chunk() -> Chunk {
-        let mut chunk = Chunk::new();
+    pub fn new(seed: u64) -> WorldGen {
+        WorldGen::with_generator(seed, Generator::Terrain)
+    }
+
+    pub fn with_generator(seed: u64, generator: Generator) -> WorldGen {
+        WorldGen { seed, generator }
+    }
+
+    pub fn seed(&self) -> u64 {
+        self.seed
+    }
+
+    pub fn generator(&self) -> Generator {
+        self.generator
+    }
+
+    /// Smoothly interpolated random values in [0, 1] on a grid with the given cell size
+    fn value_noise(&self, x: i32, z: i32, cell: i32, salt: u64) -> f32 {
+        let corner =
+            |cx: i32, cz: i32| (hash(self.seed, cx, cz, salt) >> 40) as f32 / (1u64 << 24) as f32;
+        let (cx, cz) = (x.div_euclid(cell), z.div_euclid(cell));
+        let tx = smoothstep(x.rem_euclid(cell) as f32 / cell as f32);
+        let tz = smoothstep(z.rem_euclid(cell) as f32 / cell as f32);
+        let top = corner(cx, cz) * (1.0 - tx) + corner(cx + 1, cz) * tx;
+        let bottom = corner(cx, cz + 1) * (1.0 - tx) + corner(cx + 1, cz + 1) * tx;
+        top * (1.0 - tz) + bottom * tz
+    }
+
+    /// Returns the height of the topmost solid block of the terrain in world column (x, z)
+    pub fn terrain_height(&self, x: i32, z: i32) -> i32 {
+        let hills = self.value_noise(x, z, 64, SALT_TERRAIN) * 2.0 - 1.0;
+        let detail = self.value_noise(x, z, 16, SALT_DETAIL) * 2.0 - 1.0;
+        (BASE_HEIGHT + hills * 16.0 + detail * 4.0) as i32
+    }
+
+    /// First generation phase: fills in stone, dirt and grass up to the terrain height
+    fn shape_terrain(&self, chunk: &mut ProtoChunk) {
+        for x in 0..16 {
+            for z in 0..16 {
+                let height = self.terrain_height(chunk.chunk_x * 16 + x, chunk.chunk_z * 16 + z);
+                for y in 0..=height {
+                    chunk.blocks[x as usize][y as usize][z as usize] = if y == height {
+                        GRASS
+                    } else if y >= height - DIRT_DEPTH {
+                        DIRT
+                    } else {
+                        STONE
+                    };
+                }
+            }
+        }
+    }
+
+    /// Plans the features originating in the chunk at **chunk position** (x, z), in world coordinates
+    pub fn plan_features(&self, chunk_x: i32, chunk_z: i32) -> Vec<Feature> {
+        let mut features = vec![];
+        let random_column = |rng: &mut Rng| {
+            (
+                chunk_x * 16 + rng.range(0, 16),
+                chunk_z * 16 + rng.range(0, 16),
+            )
+        };
+
+        for (i, ore) in ORES.iter().enumerate() {
+            let mut rng = Rng::for_chunk(self.seed, chunk_x, chunk_z, SALT_ORE + i as u64);
+            for _ in 0..ore.veins_per_chunk {
+                let (x, z) = random_column(&mut rng);
+                let y = ore.depth.sample(&mut rng);
+                features.push(Feature::ore_vein(ore, x, y, z, &mut rng));
+            }
+        }
+
+        let mut rng = Rng::for_chunk(self.seed, chunk_x, chunk_z, SALT_BOULDER);
+        if rng.chance(0.2) {
+            let (x, z) = random_column(&mut rng);
+            features.push(Feature::Boulder {
+                x,
+                y: self.terrain_height(x, z) + 1,
+                z,
+                radius: rng.range(1, 3),
+            });
+        }
+
+        let mut rng = Rng::for_chunk(self.seed, chunk_x, chunk_z, SALT_TREE);
+        for _ in 0..rng.range(0, 4) {
+            let (x, z) = random_column(&mut rng);
+            features.push(Feature::Tree {
+                x,
+                y: self.terrain_height(x, z) + 1,
+                z,
+                height: rng.range(4, 7),
+            });
+        }
+
+        let mut rng = Rng::for_chunk(self.seed, chunk_x, chunk_z, SALT_FLOWER);
+        for _ in 0..rng.range(0, 8) {
+            let (x, z) = random_column(&mut rng);
+            features.push(Feature::Flower {
+                x,
+                y: self.terrain_height(x, z) + 1,
+                z,
+            });
+        }
+        features
+    }
+
+    /// Second generation phase: places the features of this chunk and all of its neighbours
+    fn populate(&self, chunk: &mut ProtoChunk) {
+        let mut features = vec![];
+        for dx in -1..=1 {
+            for dz in -1..=1 {
+                features.extend(self.plan_features(chunk.chunk_x + dx, chunk.chunk_z + dz));
+            }
+        }
+        features.sort_by_key(Feature::phase);
+        for feature in &features {
+            feature.place(chunk);
+        }
+    }
+
+    /// Generates the chunk at **chunk position** (x, z)
+    pub fn generate_chunk(&self, chunk_x: i32, chunk_z: i32) -> Chunk {
+        let structures = self.structures_in_chunk(chunk_x, chunk_z);
+        self.generate_chunk_with(chunk_x, chunk_z, &structures)
+    }
+
+    /// Generates the chunk at **chunk position** (x, z), building the given structures into it.
+    /// `structures` should be the result of [`WorldGen::structures_in_chunk`] for this chunk.
+    pub fn generate_chunk_with(
+        &self,
+        chunk_x: i32,
+        chunk_z: i32,
+        structures: &[StructureStart],
+    ) -> Chunk {
+        if self.generator == Generator::Flat {
+            return Chunk::new();
+        }
+        let mut chunk = ProtoChunk::new(chunk_x, chunk_z);
+        self.shape_terrain(&mut chunk);
+        self.populate(&mut chunk);
+        for piece in structures.iter().flat_map(|start| &start.pieces) {
+            piece.place(&mut chunk);
+        }
+        chunk.into_chunk()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::feature::{Feature, ORES};
+    use crate::object::{LEAVES, LOG, STONE};
+    use crate::world::World;
+    use crate::world_gen::{ProtoChunk, WorldGen};
+
+    #[test]
+    fn generation_is_deterministic() {
+        let a = WorldGen::new(7).generate_chunk(3, -2);
+        let b = WorldGen::new(7).generate_chunk(3, -2);
+        assert!(a.blocks == b.blocks);
+        let c = WorldGen::new(8).generate_chunk(3, -2);
+        assert!(a.blocks != c.blocks);
+    }
+
+    #[test]
+    fn neighbours_complete_border_trees() {
+        let gen = &WorldGen::new(1234);
+        let near = |x: i32, z: i32, other: &Feature| match *other {
+            Feature::Tree { x: ox, z: oz, .. } | Feature::Boulder { x: ox, z: oz, .. } => {
+                (ox, oz) != (x, z) && (ox - x).abs() <= 6 && (oz - z).abs() <= 6
+            }
+            _ => false,
+        };
+        for chunk_x in 0..256 {
+            let nearby: Vec<Feature> = (chunk_x - 1..=chunk_x + 2)
+                .flat_map(|cx| (-1..=1).flat_map(move |cz| gen.plan_features(cx, cz)))
+                .collect();
+            let Some(tree) = gen
+                .plan_features(chunk_x, 0)
+                .into_iter()
+                .find(|f| match *f {
+                    Feature::Tree { x, z, .. } => {
+                        x.rem_euclid(16) == 15
+                            && (2..14).contains(&z.rem_euclid(16))
+                            && !nearby.iter().any(|other| near(x, z, other))
+                    }
+                    _ => false,
+                })
+            else {
+                continue;
+            };
+            let Feature::Tree { x, y, z, height } = tree else {
+                unreachable!()
+            };
+
+            // the chunk comes out the same whether its neighbours were generated first or not
+            let mut alone = World::with_seed(1234);
+            alone.generate_chunk(chunk_x, 0);
+            let mut together = World::with_seed(1234);
+            for cx in chunk_x - 1..=chunk_x + 2 {
+                for cz in -1..=1 {
+                    together.generate_chunk(cx, cz);
+                }
+            }
+            assert!(alone.chunks[&(chunk_x, 0)].blocks == together.chunks[&(chunk_x, 0)].blocks);
+
+            // both halves of the tree match the tree placed on its own, a missing or extra
+            // leaf on either side of the border fails
+            let is_tree = |block: u8| block == LOG || block == LEAVES;
+            for cx in [chunk_x, chunk_x + 1] {
+                let mut expected = ProtoChunk::new(cx, 0);
+                gen.shape_terrain(&mut expected);
+                tree.place(&mut expected);
+                let chunk = &together.chunks[&(cx, 0)];
+                for bx in x - 2..=x + 2 {
+                    for by in y..=y + height + 1 {
+                        for bz in z - 2..=z + 2 {
+                            if let Some(block) = expected.get(bx, by, bz) {
+                                let actual = chunk.blocks[bx.rem_euclid(16) as usize][by as usize]
+                                    [bz.rem_euclid(16) as usize];
+                                assert_eq!(is_tree(block), is_tree(actual));
+                            }
+                        }
+                    }
+                }
+            }
+            return;
+        }
+        panic!("no lone tree on a chunk border found");
+    }
+
+    #[test]
+    fn trees_cross_chunk_borders() {
+        let gen = WorldGen::new(99);
+        for chunk_x in 0..32 {
+            for feature in gen.plan_features(chunk_x, 0) {
+                if let Feature::Tree { x, y, z, height } = feature {
+                    if x.rem_euclid(16) != 15 {
+                        continue;
+                    }
+                    // the leaves one block to the east belong to the next chunk
+                    let neighbour = gen.generate_chunk(chunk_x + 1, 0);
+                    let block =
+                        neighbour.blocks[0][(y + height - 1) as usize][z.rem_euclid(16) as usize];
+                    assert!(block == LEAVES || block == LOG);
+                    return;
+                }
+            }
+        }
+        panic!("no tree on a chunk border found");
+    }
 
-        chunk
+    #[test]
+    fn ores_stay_in_their_depth_range() {
+        let gen = WorldGen::new(5);
+        let chunk = gen.generate_chunk(0, 0);
+        let diamond = ORES[3].block;
+        for x in 0..16 {
+            for y in 0..256 {
+                for z in 0..16 {
+                    if chunk.blocks[x][y][z] == diamond {
+                        assert!(y < 16 + 4);
+                    }
+                }
+            }
+        }
+        assert!(chunk.blocks.iter().flatten().flatten().any(|b| *b == STONE));
     }
 }